@@ -0,0 +1,411 @@
+//! Incremental construction of a module, for interactive use
+//! such as a REPL feeding it one parsed item at a time.
+
+use std::fmt;
+
+use crate::{
+    ast::{Function, Instruction, Module},
+    resolve::{ResolveError, Symbols},
+    validate::ValidationError,
+};
+
+/// Returned when an item can't be added to a
+/// [`ModuleAssembler`], which is left as it was, or when the
+/// module it assembled isn't complete.
+#[derive(Debug)]
+pub enum AssembleError {
+    /// An identifier could not be resolved
+    Resolve(ResolveError),
+    /// The module would not type check
+    Validation(ValidationError),
+    /// An instruction was added before any function
+    NoFunction,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembleError::Resolve(error) => {
+                write!(f, "resolve error: {error}")
+            }
+            AssembleError::Validation(error) => {
+                write!(f, "validation error: {error}")
+            }
+            AssembleError::NoFunction => {
+                f.write_str("no function to add instructions to")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {
+    fn source(
+        &self,
+    ) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssembleError::Resolve(error) => Some(error),
+            AssembleError::Validation(error) => Some(error),
+            AssembleError::NoFunction => None,
+        }
+    }
+}
+
+/// Accumulates functions and instructions into a module,
+/// checking every addition as it's made so that errors are
+/// reported right away. Only the function being added to is
+/// checked, so the cost of an addition doesn't grow with the
+/// module.
+///
+/// Instructions go to the body of the last function added,
+/// which stays open: it's only checked to leave its results
+/// on the stack once another function is added or the module
+/// is built.
+///
+/// Functions may call functions that weren't added yet, so
+/// that they can call each other. Such calls are resolved, and
+/// the functions making them type checked, once the module is
+/// built.
+pub struct ModuleAssembler {
+    module: Module,
+    /// The index spaces of the module, which grow along with it
+    symbols: Symbols,
+    /// The positions of the functions calling functions that
+    /// weren't added yet, in ascending order
+    pending: Vec<usize>,
+}
+
+impl Default for ModuleAssembler {
+    fn default() -> Self {
+        let module = Module::default();
+        let symbols = Symbols::of(&module)
+            .expect("an empty module has no identifiers");
+
+        Self {
+            module,
+            symbols,
+            pending: vec![],
+        }
+    }
+}
+
+impl ModuleAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function, returning its index in the function
+    /// index space.
+    ///
+    /// The function that was open until now is considered
+    /// complete, so it's type checked in full.
+    pub fn add_function(
+        &mut self,
+        mut function: Function,
+    ) -> Result<u32, AssembleError> {
+        if let Some(open) = self.open_function() {
+            if !self.is_pending(open) {
+                self.module
+                    .validate_function(open, false)
+                    .map_err(AssembleError::Validation)?;
+            }
+        }
+
+        let identifier = function.identifier.clone();
+        let index = self
+            .symbols
+            .functions
+            .push(identifier.as_ref())
+            .map_err(AssembleError::Resolve)?;
+
+        let deferred = match self
+            .symbols
+            .resolve_function(&mut function, true)
+        {
+            Ok(deferred) => deferred,
+            Err(error) => {
+                self.symbols.functions.pop(identifier.as_ref());
+                return Err(AssembleError::Resolve(error));
+            }
+        };
+
+        self.module.functions.push(function);
+        let position = self.module.functions.len() - 1;
+
+        if deferred {
+            self.pending.push(position);
+        } else if let Err(error) =
+            self.module.validate_function(position, true)
+        {
+            self.module.functions.pop();
+            self.symbols.functions.pop(identifier.as_ref());
+            return Err(AssembleError::Validation(error));
+        }
+
+        Ok(index)
+    }
+
+    /// Appends an instruction to the body of the last function
+    /// added.
+    ///
+    /// The function is only type checked if it doesn't call a
+    /// function that wasn't added yet.
+    pub fn add_instruction(
+        &mut self,
+        mut instruction: Instruction,
+    ) -> Result<(), AssembleError> {
+        let open = self
+            .open_function()
+            .ok_or(AssembleError::NoFunction)?;
+
+        let deferred = self
+            .symbols
+            .resolve_appended(
+                &self.module.functions[open],
+                &mut instruction,
+                true,
+            )
+            .map_err(AssembleError::Resolve)?;
+        self.module.functions[open].body.push(instruction);
+
+        if self.is_pending(open) {
+            return Ok(());
+        }
+        if deferred {
+            self.pending.push(open);
+            return Ok(());
+        }
+
+        self.module.validate_function(open, true).map_err(
+            |error| {
+                self.module.functions[open].body.pop();
+                AssembleError::Validation(error)
+            },
+        )
+    }
+
+    /// Produces the module assembled so far, checking that
+    /// every call refers to a function that was added and that
+    /// every function, including the open one, is complete.
+    pub fn build(&self) -> Result<Module, AssembleError> {
+        let mut module = self.module.clone();
+
+        for &position in &self.pending {
+            self.symbols
+                .resolve_function(
+                    &mut module.functions[position],
+                    false,
+                )
+                .map_err(AssembleError::Resolve)?;
+        }
+
+        // The others were checked in full as they were closed
+        let unchecked = self
+            .pending
+            .iter()
+            .copied()
+            .chain(self.open_function());
+        for position in unchecked {
+            module
+                .validate_function(position, false)
+                .map_err(AssembleError::Validation)?;
+        }
+
+        Ok(module)
+    }
+
+    /// Discards everything added so far.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The position of the function instructions are added to.
+    fn open_function(&self) -> Option<usize> {
+        self.module.functions.len().checked_sub(1)
+    }
+
+    fn is_pending(&self, position: usize) -> bool {
+        self.pending.binary_search(&position).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssembleError, ModuleAssembler};
+    use crate::{
+        ast::Function,
+        parser::{parse_function, parse_instruction},
+        validate::ValidationErrorKind,
+    };
+
+    fn function(input: &str) -> Function {
+        parse_function(input).unwrap().1
+    }
+
+    #[test]
+    fn assembles_functions_incrementally() {
+        let mut assembler = ModuleAssembler::new();
+
+        let double = assembler
+            .add_function(function(
+                "(func $double (param $x i32) (result i32)
+                    (i32.add (local.get $x) (local.get $x)))",
+            ))
+            .unwrap();
+        let main = assembler
+            .add_function(function("(func $main (result i32))"))
+            .unwrap();
+
+        // The body is incomplete until it leaves an i32
+        assert!(assembler.build().is_err());
+
+        let (_, call) =
+            parse_instruction("(call $double (i32.const 2))")
+                .unwrap();
+        assembler.add_instruction(call).unwrap();
+
+        let module = assembler.build().unwrap();
+
+        assert_eq!((double, main), (0, 1));
+        assert_eq!(module.functions.len(), 2);
+        assert_eq!(module.functions[1].body.len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_additions() {
+        let mut assembler = ModuleAssembler::new();
+        let (_, nop) = parse_instruction("(nop)").unwrap();
+
+        assert!(matches!(
+            assembler.add_instruction(nop),
+            Err(AssembleError::NoFunction)
+        ));
+
+        assembler
+            .add_function(function("(func (param i64))"))
+            .unwrap();
+
+        let (_, add) = parse_instruction(
+            "(i32.add (local.get 0) (i32.const 1))",
+        )
+        .unwrap();
+        let Err(AssembleError::Validation(error)) =
+            assembler.add_instruction(add)
+        else {
+            panic!("expected a validation error");
+        };
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::TypeMismatch { .. }
+        ));
+
+        // The open function is closed by the next one, so it
+        // must be complete by then
+        let (_, value) =
+            parse_instruction("(i64.const 1)").unwrap();
+        assembler.add_instruction(value).unwrap();
+        assert!(matches!(
+            assembler.add_function(function("(func)")),
+            Err(AssembleError::Validation(_))
+        ));
+
+        let (_, drop) = parse_instruction("(drop)").unwrap();
+        assembler.add_instruction(drop).unwrap();
+        assert!(matches!(
+            assembler.add_function(function(
+                "(func $f (drop (local.get $x)))"
+            )),
+            Err(AssembleError::Resolve(_))
+        ));
+
+        // Rejected additions leave the module as it was
+        let module = assembler.build().unwrap();
+        assert_eq!(module.functions.len(), 1);
+        assert_eq!(module.functions[0].body.len(), 2);
+    }
+
+    #[test]
+    fn allows_calls_to_functions_added_later() {
+        let mut assembler = ModuleAssembler::new();
+
+        assembler
+            .add_function(function(
+                "(func $even (param $n i32) (result i32)
+                    (call $odd (local.get $n)))",
+            ))
+            .unwrap();
+        assembler
+            .add_function(function(
+                "(func $odd (param $n i32) (result i32)
+                    (call $even (local.get $n)))",
+            ))
+            .unwrap();
+
+        let module = assembler.build().unwrap();
+        assert_eq!(
+            module.functions[0].body,
+            function(
+                "(func (param i32) (result i32)
+                    (call 1 (local.get 0)))"
+            )
+            .body
+        );
+
+        // Calls are checked once every function is in
+        let (_, call) =
+            parse_instruction("(call $missing)").unwrap();
+        assembler.add_instruction(call).unwrap();
+        assert!(matches!(
+            assembler.build(),
+            Err(AssembleError::Resolve(_))
+        ));
+    }
+
+    #[test]
+    fn type_checks_deferred_calls_on_build() {
+        let mut assembler = ModuleAssembler::new();
+
+        // $main can't be checked until $f's results are known
+        assembler
+            .add_function(function(
+                "(func $main (result i32) (call $f))",
+            ))
+            .unwrap();
+        assembler
+            .add_function(function(
+                "(func $f (result i64) (i64.const 1))",
+            ))
+            .unwrap();
+
+        assert!(matches!(
+            assembler.build(),
+            Err(AssembleError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn resets_to_an_empty_module() {
+        let mut assembler = ModuleAssembler::new();
+
+        assembler
+            .add_function(function("(func $f (call $g))"))
+            .unwrap();
+        assembler.reset();
+
+        assert_eq!(
+            assembler.build().unwrap().functions.len(),
+            0
+        );
+
+        // Identifiers of discarded functions are free again
+        assert_eq!(
+            assembler
+                .add_function(function("(func $f)"))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            assembler.build().unwrap().functions.len(),
+            1
+        );
+    }
+}
@@ -2,13 +2,201 @@
 
 use crate::small_string::SmallString;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Program {
     pub modules: Vec<Module>,
 }
 
 /// Represents a WebAssembly Text Format module
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Module {
-    // TODO
+    /// The functions defined in this module.
+    /// Ordered according to the order the
+    /// functions were defined.
+    pub functions: Vec<Function>,
+    /// The functions imported by this module.
+    /// Ordered according to the order the
+    /// imports were declared.
+    pub imports: Vec<FunctionImport>,
+    /// The exports declared at module level, as in
+    /// `(export "f" (func $f))`. Inline exports are kept in
+    /// the item they export instead.
+    pub exports: Vec<Export>,
+    /// The globals defined in this module.
+    /// Ordered according to the order the
+    /// globals were defined.
+    pub globals: Vec<Global>,
+    /// The types defined in this module.
+    /// Ordered according to the order the
+    /// types were defined.
+    pub types: Vec<TypeDefinition>,
+    /// The recursive type groups of this module, whose types
+    /// are part of `types` like any other.
+    pub rec_groups: Vec<RecGroup>,
+    /// The linear memories defined in this module.
+    /// Ordered according to the order the
+    /// memories were defined.
+    pub memories: Vec<Memory>,
+}
+
+/// Represents a linear memory definition, sized in pages of
+/// 64 KiB.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memory {
+    /// The identifier for this memory, if any.
+    pub identifier: Option<SmallString>,
+    /// The names this memory is exported as, if any.
+    pub exports: Vec<SmallString>,
+    /// The amount of pages this memory starts with
+    pub min: u32,
+    /// The amount of pages this memory may grow to. Only
+    /// bounded by the host if absent.
+    pub max: Option<u32>,
+}
+
+/// Represents a type definition, which names a function type
+/// for `call_indirect` to refer to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeDefinition {
+    /// The identifier for this type, if any.
+    pub identifier: Option<SmallString>,
+    /// The parameters of the function type. Their identifiers,
+    /// if any, are only documentation
+    pub parameters: Vec<Parameter>,
+    /// The results of the function type
+    pub results: Vec<Type>,
+}
+
+/// A recursive type group from the GC proposal, whose types
+/// may refer to each other:
+///
+/// ```not-rust
+/// (rec (type $a (func)) (type $b (func)))
+/// ```
+///
+/// Only parsed for now. Its types keep their place in the type
+/// index space, and this records which of them were grouped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecGroup {
+    /// The index of the first type of the group
+    pub start: u32,
+    /// The amount of types in the group
+    pub count: u32,
+}
+
+/// Represents a global variable definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Global {
+    /// The identifier for this global, if any.
+    pub identifier: Option<SmallString>,
+    /// The names this global is exported as, if any.
+    pub exports: Vec<SmallString>,
+    /// The type of the value this global holds
+    pub type_: Type,
+    /// Whether `global.set` may change this global's value
+    pub mutable: bool,
+    /// The instruction that computes the initial value of this
+    /// global, usually a `const`.
+    pub init: Instruction,
+}
+
+/// An export declared at module level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    /// The name this export is visible as to the host
+    pub name: SmallString,
+    pub kind: ExportKind,
+    /// The exported item, within the index space of `kind`
+    pub index: Index,
+}
+
+/// What kind of item an export refers to, which determines the
+/// index space its index belongs to.
+///
+/// Tables will get their own kind once they can be declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExportKind {
+    Function,
+    Memory,
+    Global,
+}
+
+/// Returned when indexing past the functions of a [`Module`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionOutOfRange {
+    /// The index that was requested
+    pub index: usize,
+    /// The amount of functions in the module
+    pub len: usize,
+}
+
+/// Helpers for passes that transform the functions of a module.
+///
+/// Indices are positions in [`Module::functions`], which do not
+/// count imports. Removing or inserting a function shifts every
+/// function after it, so callers must re-run identifier
+/// resolution afterwards.
+impl Module {
+    /// Removes and returns the function at `index`, shifting all
+    /// functions after it one position down.
+    pub fn remove_function(
+        &mut self,
+        index: usize,
+    ) -> Result<Function, FunctionOutOfRange> {
+        self.check_function_index(index, self.functions.len())?;
+
+        Ok(self.functions.remove(index))
+    }
+
+    /// Inserts `function` at `index`, shifting all functions
+    /// after it one position up. `index` may be equal to the
+    /// amount of functions, in which case `function` is
+    /// appended.
+    pub fn insert_function(
+        &mut self,
+        index: usize,
+        function: Function,
+    ) -> Result<(), FunctionOutOfRange> {
+        self.check_function_index(
+            index,
+            self.functions.len() + 1,
+        )?;
+
+        self.functions.insert(index, function);
+
+        Ok(())
+    }
+
+    /// Replaces the function at `index` with `function`,
+    /// returning the old one. Does not shift any function.
+    pub fn replace_function(
+        &mut self,
+        index: usize,
+        function: Function,
+    ) -> Result<Function, FunctionOutOfRange> {
+        self.check_function_index(index, self.functions.len())?;
+
+        Ok(std::mem::replace(
+            &mut self.functions[index],
+            function,
+        ))
+    }
+
+    fn check_function_index(
+        &self,
+        index: usize,
+        bound: usize,
+    ) -> Result<(), FunctionOutOfRange> {
+        if index < bound {
+            Ok(())
+        } else {
+            Err(FunctionOutOfRange {
+                index,
+                len: self.functions.len(),
+            })
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -29,8 +217,29 @@ pub enum NumericalType {
     Float64,
 }
 
+impl NumericalType {
+    /// The zero value of this type, used to default-initialize
+    /// locals and globals.
+    pub fn zero_value(&self) -> NumericalValue {
+        match self {
+            NumericalType::Int32 => NumericalValue::Int32(0),
+            NumericalType::Int64 => NumericalValue::Int64(0),
+            NumericalType::Float32 => {
+                NumericalValue::Float32(0.0)
+            }
+            NumericalType::Float64 => {
+                NumericalValue::Float64(0.0)
+            }
+        }
+    }
+}
+
 /// The same as [`NumericalType`] but actually carries a value
 /// that it represents
+///
+/// Only `PartialEq`, since floats compare as floats do: a NaN
+/// constant is not equal to itself. Every AST node that holds
+/// instructions is `PartialEq` only for the same reason.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum NumericalValue {
     /// Signed integer of 32 bits
@@ -43,6 +252,18 @@ pub enum NumericalValue {
     Float64(f64),
 }
 
+impl NumericalValue {
+    /// The type of this value.
+    pub fn type_(&self) -> NumericalType {
+        match self {
+            NumericalValue::Int32(_) => NumericalType::Int32,
+            NumericalValue::Int64(_) => NumericalType::Int64,
+            NumericalValue::Float32(_) => NumericalType::Float32,
+            NumericalValue::Float64(_) => NumericalType::Float64,
+        }
+    }
+}
+
 /// A function parameter.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Parameter {
@@ -66,7 +287,10 @@ pub struct Local {
 }
 
 /// Represents a function definition.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Not `Eq`, since its body may hold float constants, see
+/// [`NumericalValue`].
+#[derive(Clone, Debug, PartialEq)]
 pub struct Function {
     /// The identifier for this function, if any.
     pub identifier: Option<SmallString>,
@@ -77,10 +301,16 @@ pub struct Function {
     /// Ordered according to the order the
     /// parameters were defined.
     pub parameters: Vec<Parameter>,
+    /// The types of the values this function returns.
+    /// Ordered according to the order the
+    /// results were defined.
+    pub results: Vec<Type>,
     /// The local variables of this function.
     /// Ordered according to the order the
     /// locals were defined.
     pub local_variables: Vec<Local>,
+    /// The instructions of this function, which may be folded.
+    pub body: Vec<Instruction>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -96,6 +326,65 @@ pub struct Instruction {
     pub arguments: Vec<Instruction>,
 }
 
+impl Instruction {
+    /// Calls `f` on this instruction and then on each of its
+    /// (possibly nested) arguments, in pre-order.
+    ///
+    /// The bodies of blocks are visited after their arguments.
+    pub fn walk(&self, f: &mut impl FnMut(&Instruction)) {
+        f(self);
+
+        for argument in &self.arguments {
+            argument.walk(f);
+        }
+
+        let bodies: [&[Instruction]; 2] = match &self.opcode {
+            Opcode::Block(block) | Opcode::Loop(block) => {
+                [&block.body, &[]]
+            }
+            Opcode::If(if_block) => {
+                [&if_block.then, &if_block.else_]
+            }
+            _ => [&[], &[]],
+        };
+
+        for instruction in bodies.into_iter().flatten() {
+            instruction.walk(f);
+        }
+    }
+
+    /// Like [`Instruction::walk`], but allows modifying each
+    /// instruction.
+    ///
+    /// Since `f` runs before the arguments are visited, any
+    /// arguments it adds get visited as well.
+    pub fn walk_mut(
+        &mut self,
+        f: &mut impl FnMut(&mut Instruction),
+    ) {
+        f(self);
+
+        for argument in &mut self.arguments {
+            argument.walk_mut(f);
+        }
+
+        let bodies: [&mut [Instruction]; 2] =
+            match &mut self.opcode {
+                Opcode::Block(block) | Opcode::Loop(block) => {
+                    [&mut block.body, &mut []]
+                }
+                Opcode::If(if_block) => {
+                    [&mut if_block.then, &mut if_block.else_]
+                }
+                _ => [&mut [], &mut []],
+            };
+
+        for instruction in bodies.into_iter().flatten() {
+            instruction.walk_mut(f);
+        }
+    }
+}
+
 /// Represents an `import` statement for functions.
 ///
 /// Consists of the namespace from which we're importing from,
@@ -111,7 +400,7 @@ pub struct Instruction {
 ///          ↑↑↑↑↑↑↑         ↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑↑
 ///         namespace           WAT function signature
 /// ```
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct FunctionImport {
     pub namespace: SmallString,
     pub fn_name: SmallString,
@@ -124,6 +413,32 @@ pub struct FunctionImport {
 pub enum Opcode {
     /// Calls a function
     Call(Index),
+    /// Calls the function at the index on top of the stack,
+    /// within a table, checking it has the given type.
+    ///
+    /// ```not-rust
+    /// (call_indirect (type $signature) (local.get $pointer))
+    /// ```
+    CallIndirect {
+        /// The expected type of the callee
+        type_index: Index,
+        /// The table the callee is looked up in, 0 if omitted
+        table_index: Index,
+    },
+    /// Conditionally branches to the label at the given depth.
+    ///
+    /// In the folded form the label comes before the condition:
+    ///
+    /// ```not-rust
+    /// (br_if $label (local.get $x))
+    /// ```
+    BrIf(Index),
+    /// Unconditionally branches to the label at the given
+    /// depth.
+    Br(Index),
+    /// Returns from the current function, with the values on
+    /// top of the stack as its results.
+    Return,
     /// Fetch or set a local or global variable
     VariableInstruction(VariableOperation),
     /// Pushes a numerical constant to the stack.
@@ -133,6 +448,17 @@ pub enum Opcode {
     /// An arithmetic operation
     Arithmetic(ArithmeticOperation),
     Comparison(ComparisonOperation),
+    Conversion(ConversionOperation),
+    /// Loads from or stores to linear memory.
+    ///
+    /// E.g. `i32.load offset=4`, `i64.store8`
+    MemoryAccess(MemoryOperation),
+    /// Pushes the current size of the memory, in pages.
+    MemorySize,
+    /// Grows the memory by the amount of pages on top of the
+    /// stack, pushing its previous size, or -1 if it can't
+    /// grow.
+    MemoryGrow,
     /// Denotes a point in code that should not be reachable.
     /// `unreachable` is an unconditional trap: in the case
     /// where an unreachable is reached and executed, the
@@ -145,6 +471,177 @@ pub enum Opcode {
     /// (unreachable (i32.const 5) (i32.const 5))
     /// ```
     Unreachable(Unreachable),
+    /// Does nothing.
+    Nop,
+    /// Pops a value from the stack and throws it away.
+    Drop,
+    /// Pops a condition and two values, pushing back the first
+    /// value if the condition is not zero and the second one
+    /// otherwise.
+    ///
+    /// ```not-rust
+    /// (select (local.get $a) (local.get $b) (local.get $cond))
+    /// ```
+    Select,
+    /// A block of instructions. Branching to it jumps past its
+    /// end.
+    ///
+    /// ```not-rust
+    /// (block $exit (result i32) (i32.const 1))
+    /// ```
+    Block(Block),
+    /// A block of instructions. Branching to it jumps back to
+    /// its start.
+    Loop(Block),
+    /// Runs one of two blocks depending on the condition on top
+    /// of the stack.
+    ///
+    /// In the folded form the condition comes before the arms:
+    ///
+    /// ```not-rust
+    /// (if (local.get $x) (then (nop)) (else (unreachable)))
+    /// ```
+    If(IfBlock),
+}
+
+impl Opcode {
+    /// Whether this opcode always pops and pushes the same
+    /// number of values.
+    ///
+    /// The counts of a few opcodes are only lower bounds.
+    /// `call` and `call_indirect` depend on the callee's
+    /// signature. `br`, `br_if` and `return` may carry values
+    /// to their targets. `unreachable` accepts any arity.
+    pub fn has_fixed_arity(&self) -> bool {
+        !matches!(
+            self,
+            Opcode::Call(_)
+                | Opcode::CallIndirect { .. }
+                | Opcode::BrIf(_)
+                | Opcode::Br(_)
+                | Opcode::Return
+                | Opcode::Unreachable(_)
+        )
+    }
+
+    /// The number of operands this opcode pops from the stack.
+    ///
+    /// See [`Opcode::has_fixed_arity`] for the opcodes where
+    /// this is only a lower bound.
+    pub fn operand_count(&self) -> usize {
+        match self {
+            Opcode::Call(_)
+            | Opcode::Br(_)
+            | Opcode::Return
+            | Opcode::Unreachable(_)
+            | Opcode::Nop
+            | Opcode::MemorySize
+            | Opcode::Block(_)
+            | Opcode::Loop(_) => 0,
+            Opcode::CallIndirect { .. }
+            | Opcode::BrIf(_)
+            | Opcode::Drop
+            | Opcode::MemoryGrow
+            | Opcode::If(_) => 1,
+            Opcode::Select => 3,
+            Opcode::VariableInstruction(operation) => {
+                match operation.instruction {
+                    VariableInstruction::Get => 0,
+                    VariableInstruction::Set
+                    | VariableInstruction::Tee => 1,
+                }
+            }
+            Opcode::Constant(_) => 0,
+            Opcode::Conversion(_) => 1,
+            Opcode::MemoryAccess(operation) => {
+                match operation.instr {
+                    MemoryInstruction::Store
+                    | MemoryInstruction::NarrowStore {
+                        ..
+                    } => 2,
+                    _ => 1,
+                }
+            }
+            Opcode::Comparison(ComparisonOperation {
+                instr: ComparisonInstruction::EqualZero,
+                ..
+            }) => 1,
+            Opcode::Arithmetic(_) | Opcode::Comparison(_) => 2,
+        }
+    }
+
+    /// The number of results this opcode pushes to the stack.
+    ///
+    /// See [`Opcode::has_fixed_arity`] for the opcodes where
+    /// this is only a lower bound.
+    pub fn result_count(&self) -> usize {
+        match self {
+            Opcode::Call(_)
+            | Opcode::CallIndirect { .. }
+            | Opcode::BrIf(_)
+            | Opcode::Br(_)
+            | Opcode::Return
+            | Opcode::Unreachable(_)
+            | Opcode::Nop
+            | Opcode::Drop => 0,
+            Opcode::Select
+            | Opcode::MemorySize
+            | Opcode::MemoryGrow => 1,
+            Opcode::VariableInstruction(operation) => {
+                match operation.instruction {
+                    VariableInstruction::Get
+                    | VariableInstruction::Tee => 1,
+                    VariableInstruction::Set => 0,
+                }
+            }
+            Opcode::Constant(_)
+            | Opcode::Arithmetic(_)
+            | Opcode::Comparison(_)
+            | Opcode::Conversion(_) => 1,
+            Opcode::MemoryAccess(operation) => {
+                match operation.instr {
+                    MemoryInstruction::Store
+                    | MemoryInstruction::NarrowStore {
+                        ..
+                    } => 0,
+                    _ => 1,
+                }
+            }
+            Opcode::Block(Block { block_type, .. })
+            | Opcode::Loop(Block { block_type, .. })
+            | Opcode::If(IfBlock { block_type, .. }) => {
+                usize::from(block_type.is_some())
+            }
+        }
+    }
+}
+
+/// The contents of a `block` or `loop`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The label branches may use to refer to this block,
+    /// instead of its depth.
+    pub label: Option<SmallString>,
+    /// The type of the value this block leaves on the stack, if
+    /// any.
+    pub block_type: Option<Type>,
+    pub body: Vec<Instruction>,
+}
+
+/// The contents of an `if`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfBlock {
+    /// The label branches may use to refer to this block,
+    /// instead of its depth.
+    pub label: Option<SmallString>,
+    /// The type of the value this block leaves on the stack, if
+    /// any. Both arms must agree on it.
+    pub block_type: Option<Type>,
+    /// Runs when the condition is not zero
+    pub then: Vec<Instruction>,
+    /// Runs when the condition is zero. Empty if there's no
+    /// `else` arm.
+    pub else_: Vec<Instruction>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -168,6 +665,56 @@ pub struct Constant {
     pub value: NumericalValue,
 }
 
+impl Constant {
+    pub fn new(value: NumericalValue) -> Self {
+        Self { value }
+    }
+
+    /// An `i32.const`
+    pub fn i32(value: i32) -> Self {
+        Self::new(NumericalValue::Int32(value))
+    }
+
+    /// An `i64.const`
+    pub fn i64(value: i64) -> Self {
+        Self::new(NumericalValue::Int64(value))
+    }
+
+    /// An `f32.const`
+    pub fn f32(value: f32) -> Self {
+        Self::new(NumericalValue::Float32(value))
+    }
+
+    /// An `f64.const`
+    pub fn f64(value: f64) -> Self {
+        Self::new(NumericalValue::Float64(value))
+    }
+}
+
+impl From<i32> for Constant {
+    fn from(value: i32) -> Self {
+        Self::i32(value)
+    }
+}
+
+impl From<i64> for Constant {
+    fn from(value: i64) -> Self {
+        Self::i64(value)
+    }
+}
+
+impl From<f32> for Constant {
+    fn from(value: f32) -> Self {
+        Self::f32(value)
+    }
+}
+
+impl From<f64> for Constant {
+    fn from(value: f64) -> Self {
+        Self::f64(value)
+    }
+}
+
 /// An arithmetic operation
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ArithmeticOperation {
@@ -190,13 +737,64 @@ pub struct ComparisonOperation {
     pub instr: ComparisonInstruction,
 }
 
+/// Accesses linear memory at the address on top of the stack,
+/// plus a constant offset.
+///
+/// E.g. `i32.load8_u offset=4 align=1`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryOperation {
+    /// The type of the value loaded or stored
+    pub type_: NumericalType,
+    /// Whether this is a load or a store, and how many bytes it
+    /// accesses
+    pub instr: MemoryInstruction,
+    /// Added to the address operand. 0 if omitted.
+    pub offset: u32,
+    /// The alignment hint of the access, in bytes. Defaults to
+    /// the width of the access, its natural alignment.
+    pub align: u32,
+}
+
+impl MemoryOperation {
+    /// The number of bytes this operation accesses, which is
+    /// also its natural alignment.
+    pub fn width(&self) -> u32 {
+        match self.instr {
+            MemoryInstruction::SignedLoad { bits }
+            | MemoryInstruction::UnsignedLoad { bits }
+            | MemoryInstruction::NarrowStore { bits } => {
+                bits / 8
+            }
+            MemoryInstruction::Load
+            | MemoryInstruction::Store => match self.type_ {
+                NumericalType::Int32
+                | NumericalType::Float32 => 4,
+                NumericalType::Int64
+                | NumericalType::Float64 => 8,
+            },
+        }
+    }
+}
+
+/// Converts the value on top of the stack from one numerical
+/// type to another, e.g. `f64.convert_i32_s`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionOperation {
+    /// The type the operand is converted to, which prefixes the
+    /// mnemonic
+    pub type_: NumericalType,
+    /// How the operand is converted
+    pub instr: ConversionInstruction,
+    /// The type of the operand
+    pub source: NumericalType,
+}
+
 /// An index for an instruction, may be an identifier or a
 /// numerical index.
 ///
 /// # Examples
 ///
-/// * `call $function` (function is an identifier in an
-/// indexing position)
+/// * `call $function` (an identifier in an indexing position)
 /// * ` local.get 0` (0 is a numerical index)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Index {
@@ -246,16 +844,100 @@ pub enum ArithmeticInstruction {
     SignedRemainder,
     /// i32.rem_u or i64.rem_u
     UnsignedRemainder,
+    /// i32.and or i64.and
+    And,
+    /// i32.or or i64.or
+    Or,
+    /// i32.xor or i64.xor
+    Xor,
+    /// i32.shl or i64.shl
+    ShiftLeft,
+    /// i32.shr_s or i64.shr_s
+    SignedShiftRight,
+    /// i32.shr_u or i64.shr_u
+    UnsignedShiftRight,
+    /// i32.rotl or i64.rotl
+    RotateLeft,
+    /// i32.rotr or i64.rotr
+    RotateRight,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ComparisonInstruction {
+    /// i32.eqz or i64.eqz. Unlike every other comparison, takes
+    /// a single operand, which is compared against zero.
+    EqualZero,
+    /// i32.eq, i64.eq, f32.eq, or f64.eq
     Equal,
+    /// i32.ne, i64.ne, f32.ne, or f64.ne
     NotEqual,
+    /// f32.gt or f64.gt
     GreaterThan,
+    /// f32.lt or f64.lt
     LessThan,
+    /// f32.ge or f64.ge
     GreaterOrEqual,
+    /// f32.le or f64.le
     LessOrEqual,
+    /// i32.gt_s or i64.gt_s
+    SignedGreaterThan,
+    /// i32.gt_u or i64.gt_u
+    UnsignedGreaterThan,
+    /// i32.lt_s or i64.lt_s
+    SignedLessThan,
+    /// i32.lt_u or i64.lt_u
+    UnsignedLessThan,
+    /// i32.ge_s or i64.ge_s
+    SignedGreaterOrEqual,
+    /// i32.ge_u or i64.ge_u
+    UnsignedGreaterOrEqual,
+    /// i32.le_s or i64.le_s
+    SignedLessOrEqual,
+    /// i32.le_u or i64.le_u
+    UnsignedLessOrEqual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryInstruction {
+    /// i32.load, i64.load, f32.load or f64.load
+    Load,
+    /// Loads `bits` bits and sign-extends them, e.g.
+    /// i32.load8_s or i64.load32_s
+    SignedLoad { bits: u32 },
+    /// Loads `bits` bits and zero-extends them, e.g.
+    /// i32.load16_u or i64.load8_u
+    UnsignedLoad { bits: u32 },
+    /// i32.store, i64.store, f32.store or f64.store
+    Store,
+    /// Stores the lowest `bits` bits, e.g. i32.store8 or
+    /// i64.store32
+    NarrowStore { bits: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConversionInstruction {
+    /// i32.wrap_i64
+    Wrap,
+    /// i64.extend_i32_s
+    SignedExtend,
+    /// i64.extend_i32_u
+    UnsignedExtend,
+    /// i32.trunc_f32_s, i32.trunc_f64_s, i64.trunc_f32_s or
+    /// i64.trunc_f64_s
+    SignedTruncate,
+    /// i32.trunc_f32_u, i32.trunc_f64_u, i64.trunc_f32_u or
+    /// i64.trunc_f64_u
+    UnsignedTruncate,
+    /// f32.convert_i32_s, f32.convert_i64_s, f64.convert_i32_s
+    /// or f64.convert_i64_s
+    SignedConvert,
+    /// f32.convert_i32_u, f32.convert_i64_u, f64.convert_i32_u
+    /// or f64.convert_i64_u
+    UnsignedConvert,
+    /// f32.demote_f64
+    Demote,
+    /// f64.promote_f32
+    Promote,
 }
 
 /// Zero-sized type to denote the `unreachable` instruction,
@@ -265,3 +947,377 @@ pub enum ComparisonInstruction {
 /// instruction traps.
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct Unreachable;
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ArithmeticInstruction, ArithmeticOperation, Block,
+        ComparisonInstruction, ComparisonOperation, Constant,
+        Function, FunctionOutOfRange, IfBlock, Index,
+        Instruction, Module, NumericalType, NumericalValue,
+        Opcode, Program, ScopeKind, Unreachable,
+        VariableInstruction, VariableOperation,
+    };
+
+    fn named_function(name: &str) -> Function {
+        Function {
+            identifier: Some(name.into()),
+            exports: vec![],
+            parameters: vec![],
+            results: vec![],
+            local_variables: vec![],
+            body: vec![],
+        }
+    }
+
+    fn function_names(module: &Module) -> Vec<&str> {
+        module
+            .functions
+            .iter()
+            .filter_map(|function| {
+                function.identifier.as_deref()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn removing_function_shifts_later_ones() {
+        let mut module = Module {
+            functions: ["a", "b", "c"]
+                .map(named_function)
+                .into(),
+            ..Module::default()
+        };
+
+        let removed = module.remove_function(1).unwrap();
+
+        assert_eq!(removed, named_function("b"));
+        assert_eq!(function_names(&module), ["a", "c"]);
+
+        module.insert_function(0, named_function("d")).unwrap();
+        module.insert_function(3, named_function("e")).unwrap();
+        assert_eq!(
+            function_names(&module),
+            ["d", "a", "c", "e"]
+        );
+
+        let replaced = module
+            .replace_function(2, named_function("f"))
+            .unwrap();
+        assert_eq!(replaced, named_function("c"));
+        assert_eq!(
+            function_names(&module),
+            ["d", "a", "f", "e"]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_function_indices() {
+        let mut module = Module {
+            functions: vec![named_function("a")],
+            ..Module::default()
+        };
+        fn out_of_range<T>(
+            index: usize,
+        ) -> Result<T, FunctionOutOfRange> {
+            Err(FunctionOutOfRange { index, len: 1 })
+        }
+
+        assert_eq!(module.remove_function(1), out_of_range(1));
+        assert_eq!(
+            module.replace_function(1, named_function("b")),
+            out_of_range(1)
+        );
+        assert_eq!(
+            module.insert_function(2, named_function("b")),
+            out_of_range(2)
+        );
+        assert_eq!(function_names(&module), ["a"]);
+    }
+
+    /// Builds `(i32.add (i32.const 1) (i32.mul (i32.const 2)
+    /// (i32.const 3)))`
+    fn nested_arithmetic() -> Instruction {
+        let constant = |value| Instruction {
+            opcode: Opcode::Constant(Constant::i32(value)),
+            arguments: vec![],
+        };
+        let arithmetic = |instr, arguments| Instruction {
+            opcode: Opcode::Arithmetic(ArithmeticOperation {
+                type_: NumericalType::Int32,
+                instr,
+            }),
+            arguments,
+        };
+
+        arithmetic(
+            ArithmeticInstruction::Addition,
+            vec![
+                constant(1),
+                arithmetic(
+                    ArithmeticInstruction::Multiplication,
+                    vec![constant(2), constant(3)],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn walks_instructions_in_pre_order() {
+        let mut nodes = 0;
+        let mut constants = vec![];
+
+        nested_arithmetic().walk(&mut |instruction| {
+            nodes += 1;
+
+            if let Opcode::Constant(Constant {
+                value: NumericalValue::Int32(value),
+            }) = instruction.opcode
+            {
+                constants.push(value);
+            }
+        });
+
+        assert_eq!(nodes, 5);
+        assert_eq!(constants, [1, 2, 3]);
+    }
+
+    #[test]
+    fn walks_into_block_bodies() {
+        let if_block = Instruction {
+            opcode: Opcode::If(IfBlock {
+                label: None,
+                block_type: None,
+                then: vec![nested_arithmetic()],
+                else_: vec![Instruction {
+                    opcode: Opcode::Loop(Block {
+                        label: None,
+                        block_type: None,
+                        body: vec![nested_arithmetic()],
+                    }),
+                    arguments: vec![],
+                }],
+            }),
+            arguments: vec![nested_arithmetic()],
+        };
+
+        let mut nodes = 0;
+        if_block.walk(&mut |_| nodes += 1);
+
+        // The if, its loop and three arithmetic trees
+        assert_eq!(nodes, 2 + 3 * 5);
+    }
+
+    #[test]
+    fn walks_instructions_mutably() {
+        let mut instruction = nested_arithmetic();
+
+        instruction.walk_mut(&mut |instruction| {
+            if let Opcode::Constant(Constant {
+                value: NumericalValue::Int32(value),
+            }) = &mut instruction.opcode
+            {
+                *value *= 10;
+            }
+        });
+
+        let mut constants = vec![];
+        instruction.walk(&mut |instruction| {
+            if let Opcode::Constant(constant) =
+                instruction.opcode
+            {
+                constants.push(constant.value);
+            }
+        });
+
+        assert_eq!(
+            constants,
+            [10, 20, 30].map(NumericalValue::Int32)
+        );
+    }
+
+    #[test]
+    fn builds_constants() {
+        let literal = |value| Constant { value };
+
+        assert_eq!(
+            Constant::i32(5),
+            literal(NumericalValue::Int32(5))
+        );
+        assert_eq!(
+            Constant::i64(-5),
+            literal(NumericalValue::Int64(-5))
+        );
+        assert_eq!(
+            Constant::f32(1.5),
+            literal(NumericalValue::Float32(1.5))
+        );
+        assert_eq!(
+            Constant::f64(2.5),
+            literal(NumericalValue::Float64(2.5))
+        );
+
+        assert_eq!(Constant::from(5_i32), Constant::i32(5));
+        assert_eq!(Constant::from(5_i64), Constant::i64(5));
+        assert_eq!(Constant::from(1.5_f32), Constant::f32(1.5));
+        assert_eq!(Constant::from(2.5_f64), Constant::f64(2.5));
+    }
+
+    #[test]
+    fn reports_opcode_stack_effects() {
+        let variable = |instruction| {
+            Opcode::VariableInstruction(VariableOperation {
+                scope: ScopeKind::Local,
+                instruction,
+                index: Index::Numerical(0),
+            })
+        };
+
+        let cases = [
+            (
+                Opcode::Constant(Constant {
+                    value: NumericalValue::Int32(1),
+                }),
+                (0, 1),
+            ),
+            (
+                Opcode::Arithmetic(ArithmeticOperation {
+                    type_: NumericalType::Int64,
+                    instr: ArithmeticInstruction::Addition,
+                }),
+                (2, 1),
+            ),
+            (
+                Opcode::Comparison(ComparisonOperation {
+                    type_: NumericalType::Float32,
+                    instr: ComparisonInstruction::Equal,
+                }),
+                (2, 1),
+            ),
+            (variable(VariableInstruction::Get), (0, 1)),
+            (variable(VariableInstruction::Set), (1, 0)),
+            (variable(VariableInstruction::Tee), (1, 1)),
+            (
+                Opcode::Comparison(ComparisonOperation {
+                    type_: NumericalType::Int64,
+                    instr: ComparisonInstruction::EqualZero,
+                }),
+                (1, 1),
+            ),
+            (Opcode::Nop, (0, 0)),
+            (Opcode::Drop, (1, 0)),
+            (Opcode::Select, (3, 1)),
+        ];
+
+        for (opcode, (operands, results)) in cases {
+            assert!(opcode.has_fixed_arity());
+            assert_eq!(opcode.operand_count(), operands);
+            assert_eq!(opcode.result_count(), results);
+        }
+    }
+
+    #[test]
+    fn flags_variadic_opcodes() {
+        let call = Opcode::Call(Index::Numerical(0));
+        let br_if = Opcode::BrIf(Index::Numerical(0));
+        let br = Opcode::Br(Index::Numerical(0));
+        let unreachable = Opcode::Unreachable(Unreachable);
+
+        assert!(!call.has_fixed_arity());
+        assert!(!Opcode::CallIndirect {
+            type_index: Index::Numerical(0),
+            table_index: Index::Numerical(0),
+        }
+        .has_fixed_arity());
+        assert!(!br_if.has_fixed_arity());
+        assert!(!br.has_fixed_arity());
+        assert!(!Opcode::Return.has_fixed_arity());
+        assert!(!unreachable.has_fixed_arity());
+        assert_eq!(br_if.operand_count(), 1);
+    }
+
+    #[test]
+    fn compares_modules_and_programs() {
+        let function = Function {
+            identifier: Some("f".into()),
+            exports: vec![],
+            parameters: vec![],
+            results: vec![],
+            local_variables: vec![],
+            body: vec![],
+        };
+        let with_function = Module {
+            functions: vec![function],
+            ..Module::default()
+        };
+
+        assert_eq!(Module::default(), Module::default());
+        assert_eq!(with_function, with_function.clone());
+        assert_ne!(Module::default(), with_function);
+
+        let empty = Program { modules: vec![] };
+        let single = Program {
+            modules: vec![Module::default()],
+        };
+
+        assert_eq!(single, single.clone());
+        assert_ne!(empty, single);
+    }
+
+    #[test]
+    fn compares_functions_by_their_bodies() {
+        let parse = |input| {
+            crate::parser::parse_function(input).unwrap().1
+        };
+
+        assert_eq!(
+            parse("(func $f (result f64) (f64.mul (f64.const 1.5) (f64.const -0)))"),
+            parse("(func $f (result f64)\n  (f64.mul (f64.const 1.5) (f64.const -0)))"),
+        );
+        assert_ne!(
+            parse("(func (drop (i32.const 1)))"),
+            parse("(func (drop (i32.const 2)))"),
+        );
+
+        // Floats compare as floats
+        assert_ne!(
+            parse("(func (drop (f32.const nan)))"),
+            parse("(func (drop (f32.const nan)))"),
+        );
+    }
+
+    #[test]
+    fn clones_and_compares_function_imports() {
+        let (_, import) = crate::parser::parse_function_import(
+            r#"(import "console" "log" (func $log (param i32)))"#,
+        )
+        .unwrap();
+
+        let mut renamed = import.clone();
+        assert_eq!(renamed, import);
+
+        renamed.fn_name = "warn".into();
+        assert_ne!(renamed, import);
+        assert_eq!(renamed.signature, import.signature);
+    }
+
+    #[test]
+    fn maps_numerical_types_to_their_zero_values() {
+        let expected = [
+            (NumericalType::Int32, NumericalValue::Int32(0)),
+            (NumericalType::Int64, NumericalValue::Int64(0)),
+            (
+                NumericalType::Float32,
+                NumericalValue::Float32(0.0),
+            ),
+            (
+                NumericalType::Float64,
+                NumericalValue::Float64(0.0),
+            ),
+        ];
+
+        for (type_, zero) in expected {
+            assert_eq!(type_.zero_value(), zero);
+        }
+    }
+}
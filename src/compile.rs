@@ -0,0 +1,224 @@
+//! The whole pipeline from WAT source to a WASM binary.
+
+use std::{fmt, io};
+
+use nom::{
+    combinator::all_consuming,
+    error::{convert_error, VerboseError},
+    sequence::terminated,
+};
+
+use crate::{
+    ast::Program,
+    emitter::Emitter,
+    parser::{parse_module, ws},
+    resolve::ResolveError,
+    validate::ValidationError,
+};
+
+/// Returned by [`compile`], telling which step of the pipeline
+/// failed.
+#[derive(Debug)]
+pub enum CompileError {
+    /// The input is not a valid module, described in a
+    /// human-readable report pointing at the error
+    Parse(String),
+    /// An identifier could not be resolved
+    Resolve(ResolveError),
+    /// The module does not type check
+    Validation(ValidationError),
+    /// The module could not be encoded
+    Emit(io::Error),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Parse(report) => {
+                write!(f, "parse error:\n{report}")
+            }
+            CompileError::Resolve(error) => {
+                write!(f, "resolve error: {error}")
+            }
+            CompileError::Validation(error) => {
+                write!(f, "validation error: {error}")
+            }
+            CompileError::Emit(error) => {
+                write!(f, "emit error: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {
+    fn source(
+        &self,
+    ) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileError::Parse(_) => None,
+            CompileError::Resolve(error) => Some(error),
+            CompileError::Validation(error) => Some(error),
+            CompileError::Emit(error) => Some(error),
+        }
+    }
+}
+
+/// Turns an error of one of the parsers into a human-readable
+/// report pointing at where `input` failed to parse.
+///
+/// The parsers only ever work on complete input, but should one
+/// ask for more, that is reported as the input ending early
+/// rather than panicking.
+pub fn describe_parse_error(
+    input: &str,
+    error: nom::Err<VerboseError<&str>>,
+) -> String {
+    match error {
+        nom::Err::Incomplete(nom::Needed::Size(needed)) => {
+            format!(
+                "unexpected end of input: {needed} more bytes needed"
+            )
+        }
+        nom::Err::Incomplete(nom::Needed::Unknown) => {
+            "unexpected end of input".to_owned()
+        }
+        nom::Err::Error(error) | nom::Err::Failure(error) => {
+            convert_error(input, error)
+        }
+    }
+}
+
+/// Compiles a WAT module into a WASM binary: parses, resolves
+/// identifiers, validates and emits it.
+///
+/// The input must hold exactly one `(module ...)`, optionally
+/// surrounded by whitespace and comments.
+pub fn compile(input: &str) -> Result<Vec<u8>, CompileError> {
+    let (_, mut module) =
+        all_consuming(terminated(parse_module, ws))(input)
+            .map_err(|error| {
+                CompileError::Parse(describe_parse_error(
+                    input, error,
+                ))
+            })?;
+
+    module.resolve_indices().map_err(CompileError::Resolve)?;
+    module.validate().map_err(CompileError::Validation)?;
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter
+        .emit_program(Program {
+            modules: vec![module],
+        })
+        .map_err(CompileError::Emit)?;
+
+    emitter.finish().map_err(CompileError::Emit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::{compile, describe_parse_error, CompileError};
+    use crate::{
+        emitter::testing::assert_valid_wasm,
+        resolve::{IdentifierKind, ResolveError},
+        validate::{Location, ValidationErrorKind},
+    };
+
+    #[test]
+    fn compiles_the_add_module() {
+        let bytes = compile(
+            r#"
+            ;; Adds two numbers
+            (module
+              (import "console" "log" (func $log (param i32)))
+              (func (export "add") (param $a i32) (param $b i32) (result i32)
+                (i32.add (local.get $a) (local.get $b))))
+            "#,
+        )
+        .unwrap();
+
+        assert_valid_wasm(&bytes);
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .unwrap();
+    }
+
+    #[test]
+    fn reports_type_errors_as_validation_errors() {
+        let error = compile(
+            "(module (func (result i32) (i32.add (i32.const 1) (f32.const 2))))",
+        )
+        .unwrap_err();
+
+        let CompileError::Validation(error) = error else {
+            panic!("unexpected {error:?}");
+        };
+        assert_eq!(error.location, Location::Function(0));
+        assert!(matches!(
+            error.kind,
+            ValidationErrorKind::TypeMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn compiles_control_flow() {
+        for input in [
+            "(module (func (block)))",
+            "(module
+              (func $f (param $x i32) (result i32)
+                (if (result i32) (local.get $x)
+                  (then (call $f (i32.const 0)))
+                  (else (loop $l (result i32)
+                    (br_if $l (local.get $x))
+                    (i32.const 1))))))",
+        ] {
+            let bytes = compile(input).unwrap();
+
+            assert_valid_wasm(&bytes);
+            wasmparser::Validator::new()
+                .validate_all(&bytes)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn describes_incomplete_input() {
+        let needed = NonZeroUsize::new(3).unwrap();
+
+        assert_eq!(
+            describe_parse_error(
+                "(module",
+                nom::Err::Incomplete(nom::Needed::Unknown)
+            ),
+            "unexpected end of input"
+        );
+        assert_eq!(
+            describe_parse_error(
+                "(module",
+                nom::Err::Incomplete(nom::Needed::Size(needed))
+            ),
+            "unexpected end of input: 3 more bytes needed"
+        );
+    }
+
+    #[test]
+    fn tells_failing_steps_apart() {
+        assert!(matches!(
+            compile("(module (func)"),
+            Err(CompileError::Parse(_))
+        ));
+        assert!(matches!(
+            compile("(module) (module)"),
+            Err(CompileError::Parse(_))
+        ));
+        assert!(matches!(
+            compile("(module (func (call $nope)))"),
+            Err(CompileError::Resolve(ResolveError::Unknown {
+                kind: IdentifierKind::Function,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,646 @@
+//! Renders the AST back to WebAssembly Text Format.
+//!
+//! Instructions are always written in their folded form and
+//! everything is kept on a single line, so the output parses
+//! back to an equal AST, but is not meant to be pretty.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::ast::{
+    ArithmeticInstruction, ArithmeticOperation, Block,
+    ComparisonInstruction, ComparisonOperation,
+    ConversionInstruction, ConversionOperation, Function,
+    IfBlock, Index, Instruction, Local, MemoryInstruction,
+    MemoryOperation, NumericalType, NumericalValue, Opcode,
+    Parameter, ScopeKind, Type, VariableInstruction,
+    VariableOperation,
+};
+
+impl Display for NumericalType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NumericalType::Int32 => "i32",
+            NumericalType::Int64 => "i64",
+            NumericalType::Float32 => "f32",
+            NumericalType::Float64 => "f64",
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Numerical(numerical_type) => {
+                numerical_type.fmt(f)
+            }
+        }
+    }
+}
+
+impl Display for Index {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Index::Identifier(identifier) => {
+                write!(f, "${identifier}")
+            }
+            Index::Numerical(index) => write!(f, "{index}"),
+        }
+    }
+}
+
+impl Display for Parameter {
+    /// E.g. `(param $x i32)`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("(param")?;
+        write_identifier(f, self.identifier.as_deref())?;
+        write!(f, " {})", self.type_)
+    }
+}
+
+impl Display for Local {
+    /// E.g. `(local $x i32)`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("(local")?;
+        write_identifier(f, self.identifier.as_deref())?;
+        write!(f, " {})", self.type_)
+    }
+}
+
+impl Display for Function {
+    /// E.g. `(func $add (param $x i32) (result i32) ...)`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("(func")?;
+        write_identifier(f, self.identifier.as_deref())?;
+
+        for export in &self.exports {
+            f.write_str(" (export ")?;
+            write_string(f, export)?;
+            f.write_str(")")?;
+        }
+        for parameter in &self.parameters {
+            write!(f, " {parameter}")?;
+        }
+        write_results(f, &self.results)?;
+        for local in &self.local_variables {
+            write!(f, " {local}")?;
+        }
+        for instruction in &self.body {
+            write!(f, " {instruction}")?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+impl Display for Instruction {
+    /// Writes the folded form of this instruction, e.g.
+    /// `(i32.add (i32.const 1) (i32.const 2))`.
+    ///
+    /// Folded blocks and loops can't hold arguments, so any
+    /// arguments they have are written before them, which is
+    /// where they'd run anyway.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.opcode {
+            Opcode::Block(block) | Opcode::Loop(block) => {
+                for argument in &self.arguments {
+                    write!(f, "{argument} ")?;
+                }
+
+                let keyword = match self.opcode {
+                    Opcode::Block(_) => "block",
+                    _ => "loop",
+                };
+                write!(f, "({keyword}")?;
+                write_block_header(
+                    f,
+                    block.label.as_deref(),
+                    &block.block_type,
+                )?;
+                write_instructions(f, &block.body)?;
+
+                f.write_str(")")
+            }
+            Opcode::If(if_block) => {
+                f.write_str("(if")?;
+                write_block_header(
+                    f,
+                    if_block.label.as_deref(),
+                    &if_block.block_type,
+                )?;
+                // The condition comes before the arms
+                write_instructions(f, &self.arguments)?;
+
+                f.write_str(" (then")?;
+                write_instructions(f, &if_block.then)?;
+                f.write_str(")")?;
+                if !if_block.else_.is_empty() {
+                    f.write_str(" (else")?;
+                    write_instructions(f, &if_block.else_)?;
+                    f.write_str(")")?;
+                }
+
+                f.write_str(")")
+            }
+            opcode => {
+                write!(f, "({opcode}")?;
+                write_instructions(f, &self.arguments)?;
+
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl Display for Opcode {
+    /// Writes the mnemonic of this opcode alongside its
+    /// immediates, e.g. `local.get $x` or `i32.const 5`.
+    ///
+    /// Blocks, loops and ifs are written in their flat form,
+    /// delimited by `end`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Call(index) => write!(f, "call {index}"),
+            Opcode::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                f.write_str("call_indirect")?;
+                // The table is 0 unless stated otherwise
+                if *table_index != Index::Numerical(0) {
+                    write!(f, " {table_index}")?;
+                }
+
+                write!(f, " (type {type_index})")
+            }
+            Opcode::BrIf(index) => write!(f, "br_if {index}"),
+            Opcode::Br(index) => write!(f, "br {index}"),
+            Opcode::Return => f.write_str("return"),
+            Opcode::VariableInstruction(operation) => {
+                write_variable_operation(f, operation)
+            }
+            Opcode::Constant(constant) => {
+                write!(f, "{}.const ", constant.value.type_())?;
+                write_value(f, constant.value)
+            }
+            Opcode::Arithmetic(operation) => {
+                write_arithmetic(f, operation)
+            }
+            Opcode::Comparison(operation) => {
+                write_comparison(f, operation)
+            }
+            Opcode::Conversion(operation) => {
+                write_conversion(f, operation)
+            }
+            Opcode::MemoryAccess(operation) => {
+                write_memory_operation(f, operation)
+            }
+            Opcode::MemorySize => f.write_str("memory.size"),
+            Opcode::MemoryGrow => f.write_str("memory.grow"),
+            Opcode::Unreachable(_) => f.write_str("unreachable"),
+            Opcode::Nop => f.write_str("nop"),
+            Opcode::Drop => f.write_str("drop"),
+            Opcode::Select => f.write_str("select"),
+            Opcode::Block(block) => {
+                write_flat_block(f, "block", block)
+            }
+            Opcode::Loop(block) => {
+                write_flat_block(f, "loop", block)
+            }
+            Opcode::If(IfBlock {
+                label,
+                block_type,
+                then,
+                else_,
+            }) => {
+                f.write_str("if")?;
+                write_block_header(
+                    f,
+                    label.as_deref(),
+                    block_type,
+                )?;
+                write_instructions(f, then)?;
+                if !else_.is_empty() {
+                    f.write_str(" else")?;
+                    write_instructions(f, else_)?;
+                }
+
+                f.write_str(" end")
+            }
+        }
+    }
+}
+
+fn write_identifier(
+    f: &mut Formatter<'_>,
+    identifier: Option<&str>,
+) -> fmt::Result {
+    match identifier {
+        Some(identifier) => write!(f, " ${identifier}"),
+        None => Ok(()),
+    }
+}
+
+/// Writes `string` quoted, escaping anything that can't appear
+/// in it as is.
+fn write_string(
+    f: &mut Formatter<'_>,
+    string: &str,
+) -> fmt::Result {
+    f.write_str("\"")?;
+
+    for ch in string.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            ch if ch.is_control() => {
+                let mut bytes = [0; 4];
+                for byte in ch.encode_utf8(&mut bytes).bytes() {
+                    write!(f, "\\{byte:02x}")?;
+                }
+            }
+            ch => write!(f, "{ch}")?,
+        }
+    }
+
+    f.write_str("\"")
+}
+
+fn write_results(
+    f: &mut Formatter<'_>,
+    results: &[Type],
+) -> fmt::Result {
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    f.write_str(" (result")?;
+    for result in results {
+        write!(f, " {result}")?;
+    }
+
+    f.write_str(")")
+}
+
+fn write_instructions(
+    f: &mut Formatter<'_>,
+    instructions: &[Instruction],
+) -> fmt::Result {
+    instructions
+        .iter()
+        .try_for_each(|instruction| write!(f, " {instruction}"))
+}
+
+fn write_block_header(
+    f: &mut Formatter<'_>,
+    label: Option<&str>,
+    block_type: &Option<Type>,
+) -> fmt::Result {
+    write_identifier(f, label)?;
+    write_results(f, block_type.as_slice())
+}
+
+fn write_flat_block(
+    f: &mut Formatter<'_>,
+    keyword: &str,
+    block: &Block,
+) -> fmt::Result {
+    f.write_str(keyword)?;
+    write_block_header(
+        f,
+        block.label.as_deref(),
+        &block.block_type,
+    )?;
+    write_instructions(f, &block.body)?;
+
+    f.write_str(" end")
+}
+
+fn write_variable_operation(
+    f: &mut Formatter<'_>,
+    operation: &VariableOperation,
+) -> fmt::Result {
+    let scope = match operation.scope {
+        ScopeKind::Local => "local",
+        ScopeKind::Global => "global",
+    };
+    let instruction = match operation.instruction {
+        VariableInstruction::Get => "get",
+        VariableInstruction::Set => "set",
+        VariableInstruction::Tee => "tee",
+    };
+
+    write!(f, "{scope}.{instruction} {}", operation.index)
+}
+
+/// Writes a constant so that it parses back to the same bits,
+/// NaN payloads included.
+fn write_value(
+    f: &mut Formatter<'_>,
+    value: NumericalValue,
+) -> fmt::Result {
+    match value {
+        NumericalValue::Int32(int) => write!(f, "{int}"),
+        NumericalValue::Int64(int) => write!(f, "{int}"),
+        NumericalValue::Float32(float) if float.is_nan() => {
+            write_nan(f, float.to_bits().into(), 32)
+        }
+        NumericalValue::Float64(float) if float.is_nan() => {
+            write_nan(f, float.to_bits(), 64)
+        }
+        // Debug is the shortest form that reads back exactly,
+        // switching to an exponent for very large or small
+        // magnitudes
+        NumericalValue::Float32(float) if float.is_finite() => {
+            write!(f, "{float:?}")
+        }
+        NumericalValue::Float64(float) if float.is_finite() => {
+            write!(f, "{float:?}")
+        }
+        NumericalValue::Float32(float) => {
+            write_infinity(f, float.is_sign_negative())
+        }
+        NumericalValue::Float64(float) => {
+            write_infinity(f, float.is_sign_negative())
+        }
+    }
+}
+
+fn write_infinity(
+    f: &mut Formatter<'_>,
+    negative: bool,
+) -> fmt::Result {
+    f.write_str(if negative { "-inf" } else { "inf" })
+}
+
+/// Writes the NaN with the given bit pattern, either as `nan`
+/// if it's canonical, or with its payload.
+fn write_nan(
+    f: &mut Formatter<'_>,
+    bits: u64,
+    width: u32,
+) -> fmt::Result {
+    let mantissa_bits = match width {
+        32 => 23,
+        _ => 52,
+    };
+    let payload = bits & ((1 << mantissa_bits) - 1);
+
+    if bits >> (width - 1) & 1 == 1 {
+        f.write_str("-")?;
+    }
+    if payload == 1 << (mantissa_bits - 1) {
+        f.write_str("nan")
+    } else {
+        write!(f, "nan:0x{payload:x}")
+    }
+}
+
+fn write_arithmetic(
+    f: &mut Formatter<'_>,
+    operation: &ArithmeticOperation,
+) -> fmt::Result {
+    use ArithmeticInstruction as Instr;
+
+    let name = match operation.instr {
+        Instr::Addition => "add",
+        Instr::Subtraction => "sub",
+        Instr::Multiplication => "mul",
+        Instr::FloatDivision => "div",
+        Instr::SignedDivision => "div_s",
+        Instr::UnsignedDisivion => "div_u",
+        Instr::SignedRemainder => "rem_s",
+        Instr::UnsignedRemainder => "rem_u",
+        Instr::And => "and",
+        Instr::Or => "or",
+        Instr::Xor => "xor",
+        Instr::ShiftLeft => "shl",
+        Instr::SignedShiftRight => "shr_s",
+        Instr::UnsignedShiftRight => "shr_u",
+        Instr::RotateLeft => "rotl",
+        Instr::RotateRight => "rotr",
+    };
+
+    write!(f, "{}.{name}", operation.type_)
+}
+
+fn write_comparison(
+    f: &mut Formatter<'_>,
+    operation: &ComparisonOperation,
+) -> fmt::Result {
+    use ComparisonInstruction as Instr;
+
+    let name = match operation.instr {
+        Instr::EqualZero => "eqz",
+        Instr::Equal => "eq",
+        Instr::NotEqual => "ne",
+        Instr::GreaterThan => "gt",
+        Instr::LessThan => "lt",
+        Instr::GreaterOrEqual => "ge",
+        Instr::LessOrEqual => "le",
+        Instr::SignedGreaterThan => "gt_s",
+        Instr::UnsignedGreaterThan => "gt_u",
+        Instr::SignedLessThan => "lt_s",
+        Instr::UnsignedLessThan => "lt_u",
+        Instr::SignedGreaterOrEqual => "ge_s",
+        Instr::UnsignedGreaterOrEqual => "ge_u",
+        Instr::SignedLessOrEqual => "le_s",
+        Instr::UnsignedLessOrEqual => "le_u",
+    };
+
+    write!(f, "{}.{name}", operation.type_)
+}
+
+fn write_conversion(
+    f: &mut Formatter<'_>,
+    operation: &ConversionOperation,
+) -> fmt::Result {
+    use ConversionInstruction as Instr;
+
+    let (name, suffix) = match operation.instr {
+        Instr::Wrap => ("wrap", ""),
+        Instr::SignedExtend => ("extend", "_s"),
+        Instr::UnsignedExtend => ("extend", "_u"),
+        Instr::SignedTruncate => ("trunc", "_s"),
+        Instr::UnsignedTruncate => ("trunc", "_u"),
+        Instr::SignedConvert => ("convert", "_s"),
+        Instr::UnsignedConvert => ("convert", "_u"),
+        Instr::Demote => ("demote", ""),
+        Instr::Promote => ("promote", ""),
+    };
+
+    write!(
+        f,
+        "{}.{name}_{}{suffix}",
+        operation.type_, operation.source
+    )
+}
+
+fn write_memory_operation(
+    f: &mut Formatter<'_>,
+    operation: &MemoryOperation,
+) -> fmt::Result {
+    write!(f, "{}.", operation.type_)?;
+
+    match operation.instr {
+        MemoryInstruction::Load => f.write_str("load")?,
+        MemoryInstruction::SignedLoad { bits } => {
+            write!(f, "load{bits}_s")?
+        }
+        MemoryInstruction::UnsignedLoad { bits } => {
+            write!(f, "load{bits}_u")?
+        }
+        MemoryInstruction::Store => f.write_str("store")?,
+        MemoryInstruction::NarrowStore { bits } => {
+            write!(f, "store{bits}")?
+        }
+    }
+
+    // Both immediates have defaults they can be left out at
+    if operation.offset != 0 {
+        write!(f, " offset={}", operation.offset)?;
+    }
+    if operation.align != operation.width() {
+        write!(f, " align={}", operation.align)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{Instruction, NumericalValue, Opcode},
+        parser::{parse_function, parse_instruction},
+    };
+
+    fn parse(input: &str) -> Instruction {
+        let (rest, instruction) =
+            parse_instruction(input).unwrap();
+        assert!(rest.is_empty(), "{rest:?} left over");
+
+        instruction
+    }
+
+    #[test]
+    fn renders_folded_instructions() {
+        assert_eq!(
+            parse("(i32.add (i32.const 1) (i32.const 2))")
+                .to_string(),
+            "(i32.add (i32.const 1) (i32.const 2))"
+        );
+        assert_eq!(
+            parse("local.get $x").to_string(),
+            "(local.get $x)"
+        );
+        assert_eq!(
+            parse("block $b (result i32) i32.const 1 end")
+                .to_string(),
+            "(block $b (result i32) (i32.const 1))"
+        );
+    }
+
+    #[test]
+    fn renders_functions() {
+        let (_, function) = parse_function(
+            r#"(func $add (export "add") (param $x i32) (param i32) (result i32) (local f64)
+                (i32.add (local.get $x) (local.get 1)))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            function.to_string(),
+            r#"(func $add (export "add") (param $x i32) (param i32) (result i32) (local f64) (i32.add (local.get $x) (local.get 1)))"#
+        );
+    }
+
+    #[test]
+    fn round_trips_instructions() {
+        let instructions = [
+            "(i32.add (i32.const 1) (i32.const 2))",
+            "(i64.const -9223372036854775808)",
+            "(f64.mul (f64.const 0.1) (f64.const -1.5e300))",
+            "(f32.const 1e-45)",
+            "(drop (f32.const -0x1.8p-2))",
+            "(local.set $x (call $f (local.get $y) (global.get 0)))",
+            "(local.tee 0 (i32.eqz (i32.load8_u offset=4 align=1 (i32.const 0))))",
+            "(i64.store32 (i32.const 8) (i64.extend_i32_u (i32.const 1)))",
+            "(f32.demote_f64 (f64.convert_i64_s (i64.trunc_f32_s (f32.const 2))))",
+            "(call_indirect 1 (type $t) (i32.const 0))",
+            "(drop (memory.grow (memory.size)))",
+            "(select (nop) (unreachable (i32.const 1)) (return))",
+            "(block $outer (result i32) (loop $inner (br_if $inner (i32.const 0)) (br 1)) (i32.const 5))",
+            "(if $check (result f64) (i32.const 1) (then (f64.const inf)) (else (f64.const -0x1p-1074)))",
+            "if (nop) end",
+            "loop $l (br $l) end",
+        ];
+
+        for input in instructions {
+            let instruction = parse(input);
+            let rendered = instruction.to_string();
+
+            assert_eq!(
+                parse(&rendered),
+                instruction,
+                "{input} was rendered as {rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_special_floats_bit_for_bit() {
+        for input in [
+            "(f32.const nan)",
+            "(f32.const -nan:0x200000)",
+            "(f64.const -inf)",
+            "(f64.const nan:0x4000000000000)",
+            "(f64.const -0.0)",
+        ] {
+            let bits =
+                |instruction: Instruction| match instruction
+                    .opcode
+                {
+                    Opcode::Constant(constant) => {
+                        match constant.value {
+                            NumericalValue::Float32(float) => {
+                                u64::from(float.to_bits())
+                            }
+                            NumericalValue::Float64(float) => {
+                                float.to_bits()
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    _ => unreachable!(),
+                };
+
+            let instruction = parse(input);
+            assert_eq!(instruction.to_string(), input);
+            assert_eq!(
+                bits(parse(&instruction.to_string())),
+                bits(instruction)
+            );
+        }
+    }
+
+    #[test]
+    fn escapes_export_names() {
+        let (_, function) =
+            parse_function(r#"(func (export "a\"b\\c\n\01"))"#)
+                .unwrap();
+        let rendered = function.to_string();
+
+        assert_eq!(
+            rendered,
+            r#"(func (export "a\"b\\c\n\01"))"#
+        );
+        assert_eq!(
+            parse_function(&rendered).unwrap().1,
+            function
+        );
+    }
+}
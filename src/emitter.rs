@@ -3,19 +3,49 @@ use std::io::{self, Write};
 mod arithmetic_operation;
 mod constant;
 pub mod emittable;
+mod function;
+mod index;
+mod instruction;
+mod memory_operation;
+mod module;
+mod names;
 mod numerical_value;
+mod producers;
 mod unreachable;
+mod value_type;
+mod variable_operation;
 
 pub use emittable::Emittable;
+pub(crate) use function::local_runs;
+pub(crate) use module::{FunctionType, TypeTable};
 
-use crate::ast::Program;
+use crate::{
+    ast::Program,
+    leb128::{SignedLeb128, UnsignedLeb128},
+};
 
 const MAGIC: &[u8] = b"\0asm";
-const VERSION: &[u8] = b"1000";
+const VERSION: &[u8] = &[0x01, 0x00, 0x00, 0x00];
+
+/// Controls which optional parts of a module get emitted.
+///
+/// Everything optional is omitted by default, which yields the
+/// smallest output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EmitOptions {
+    /// Whether to emit the `name` custom section, which maps
+    /// the indices of functions, parameters and locals back to
+    /// their identifiers
+    pub emit_names: bool,
+    /// Whether to emit the `producers` custom section
+    pub emit_producers: bool,
+}
 
 pub struct Emitter<W> {
     /// Where this Emitter will write to
     writer: W,
+    /// Which optional sections this Emitter will write
+    options: EmitOptions,
 }
 
 impl<W: Write> Emitter<W> {
@@ -32,6 +62,25 @@ impl<W: Write> Emitter<W> {
         self.writer.write_all(bytes)
     }
 
+    /// Emit `value` as an unsigned LEB128 integer.
+    ///
+    /// Indices and counts (e.g. section sizes) are always
+    /// encoded this way.
+    pub fn emit_leb128_u32(
+        &mut self,
+        value: u32,
+    ) -> io::Result<usize> {
+        self.emit_element(UnsignedLeb128::from(value as u64))
+    }
+
+    /// Emit `value` as a signed LEB128 integer.
+    pub fn emit_leb128_i64(
+        &mut self,
+        value: i64,
+    ) -> io::Result<usize> {
+        self.emit_element(SignedLeb128::from(value))
+    }
+
     /// Emits the WASM magic constant
     fn emit_magic(&mut self) -> io::Result<()> {
         self.emit_bytes(MAGIC)
@@ -44,43 +93,245 @@ impl<W: Write> Emitter<W> {
 
     /// Builds a new emitter with the given writer
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self::with_options(writer, EmitOptions::default())
+    }
+
+    /// Builds a new emitter with the given writer and options
+    pub fn with_options(
+        writer: W,
+        options: EmitOptions,
+    ) -> Self {
+        Self { writer, options }
+    }
+
+    /// The options this emitter was built with
+    pub fn options(&self) -> EmitOptions {
+        self.options
     }
 
     /// Emit the given program to WASM
+    ///
+    /// A WASM binary holds a single module, so the program may
+    /// contain at most one module.
     pub fn emit_program(
         &mut self,
-        _program: Program,
+        program: Program,
     ) -> io::Result<()> {
+        if program.modules.len() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a WASM binary can only hold a single module",
+            ));
+        }
+
         self.emit_magic()?;
         self.emit_version()?;
 
+        for module in program.modules {
+            self.emit_element(module)?;
+        }
+
+        if self.options.emit_producers {
+            self.emit_producers_section(
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+            )?;
+        }
+
         Ok(())
     }
 
+    /// Flushes and returns the underlying writer, consuming the
+    /// emitter.
+    ///
+    /// Callers must call `finish` once they're done instead of
+    /// dropping the emitter, since buffered writers would
+    /// otherwise silently discard flush errors.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
     #[cfg(test)]
     pub fn into_inner(self) -> W {
         self.writer
     }
 }
 
-impl<W> Emitter<std::io::Cursor<W>> {
+impl<W> Emitter<io::Cursor<W>> {
     #[cfg(test)]
     pub fn new_cursored(writer: W) -> Self {
         use std::io::Cursor;
 
         Self {
             writer: Cursor::new(writer),
+            options: EmitOptions::default(),
+        }
+    }
+}
+
+/// Helpers for emitter tests, along with lightweight structural
+/// checks for emitted WASM so that they don't need an external
+/// validator.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::io;
+
+    use super::{Emittable, Emitter, MAGIC, VERSION};
+    use crate::leb128::read_unsigned;
+
+    /// Emits `element` on its own, checking that the amount of
+    /// bytes reported as written is the amount of bytes output.
+    pub fn try_emit<T>(element: T) -> io::Result<Vec<u8>>
+    where
+        Emitter<Vec<u8>>: Emittable<T>,
+    {
+        let mut emitter = Emitter::new(Vec::new());
+        let written = emitter.emit_element(element)?;
+        let bytes = emitter.into_inner();
+
+        assert_eq!(written, bytes.len());
+
+        Ok(bytes)
+    }
+
+    /// Like [`try_emit`], for elements that must emit
+    /// successfully.
+    pub fn emit<T>(element: T) -> Vec<u8>
+    where
+        Emitter<Vec<u8>>: Emittable<T>,
+    {
+        try_emit(element).unwrap()
+    }
+
+    /// Asserts that `bytes` starts with the WASM preamble and is
+    /// followed by well-delimited sections until EOF.
+    pub fn assert_valid_wasm(bytes: &[u8]) {
+        assert!(bytes.len() >= 8, "missing WASM preamble");
+        assert_eq!(&bytes[..4], MAGIC, "bad magic");
+        assert_eq!(&bytes[4..8], VERSION, "bad version");
+
+        let mut position = 8;
+        while position < bytes.len() {
+            let id = bytes[position];
+            assert!(id <= 12, "unknown section id {id}");
+            position += 1;
+
+            let (size, read) = read_unsigned(&bytes[position..])
+                .expect("malformed section size");
+            position += read;
+
+            let end = position + size as usize;
+            assert!(
+                end <= bytes.len(),
+                "section {id} overruns the module"
+            );
+            position = end;
         }
+
+        assert_eq!(position, bytes.len());
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::MAGIC;
+    use std::io::BufWriter;
+
+    use super::{testing::assert_valid_wasm, MAGIC, VERSION};
+    use crate::{
+        ast::Program,
+        emitter::{EmitOptions, Emittable, Emitter},
+        leb128::{SignedLeb128, UnsignedLeb128},
+    };
 
     #[test]
     fn assert_correct_magic() {
         assert_eq!(MAGIC, &[0x00, 0x61, 0x73, 0x6d])
     }
+
+    #[test]
+    fn assert_correct_version() {
+        assert_eq!(VERSION, &[0x01, 0x00, 0x00, 0x00])
+    }
+
+    #[test]
+    fn emits_valid_empty_program() {
+        let mut emitter = Emitter::new(Vec::new());
+
+        emitter
+            .emit_program(Program { modules: vec![] })
+            .unwrap();
+
+        assert_valid_wasm(&emitter.into_inner());
+    }
+
+    #[test]
+    fn omits_optional_sections_by_default() {
+        let emitter = Emitter::new(Vec::<u8>::new());
+
+        assert_eq!(
+            emitter.options(),
+            EmitOptions {
+                emit_names: false,
+                emit_producers: false,
+            }
+        );
+    }
+
+    #[test]
+    fn finish_flushes_buffered_writers() {
+        let mut emitter =
+            Emitter::new(BufWriter::new(Vec::new()));
+
+        emitter.emit_bytes(&[0xde, 0xad]).unwrap();
+        let writer = emitter.finish().unwrap();
+
+        assert!(writer.buffer().is_empty());
+        assert_eq!(writer.get_ref(), &[0xde, 0xad]);
+    }
+
+    #[test]
+    #[should_panic(expected = "overruns")]
+    fn rejects_truncated_sections() {
+        let mut bytes = [MAGIC, VERSION].concat();
+        // A type section claiming 5 bytes with only 1 present
+        bytes.extend([0x01, 0x05, 0x00]);
+
+        assert_valid_wasm(&bytes);
+    }
+
+    #[test]
+    fn emit_leb128_u32_matches_emittable() {
+        for value in [0, 15, 128, 624485, u32::MAX] {
+            let mut helper = Emitter::new(Vec::new());
+            let mut element = Emitter::new(Vec::new());
+
+            let written = helper.emit_leb128_u32(value).unwrap();
+            element
+                .emit_element(UnsignedLeb128::from(value as u64))
+                .unwrap();
+
+            let bytes = helper.into_inner();
+            assert_eq!(written, bytes.len());
+            assert_eq!(bytes, element.into_inner());
+        }
+    }
+
+    #[test]
+    fn emit_leb128_i64_matches_emittable() {
+        for value in [i64::MIN, -128, -1, 0, 63, 64, i64::MAX] {
+            let mut helper = Emitter::new(Vec::new());
+            let mut element = Emitter::new(Vec::new());
+
+            let written = helper.emit_leb128_i64(value).unwrap();
+            element
+                .emit_element(SignedLeb128::from(value))
+                .unwrap();
+
+            let bytes = helper.into_inner();
+            assert_eq!(written, bytes.len());
+            assert_eq!(bytes, element.into_inner());
+        }
+    }
 }
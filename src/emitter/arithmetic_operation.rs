@@ -7,8 +7,8 @@ impl<W: Write> Emittable<ArithmeticOperation> for Emitter<W> {
     // Does not type check or see if there are enough operands
     // for the operation.
     //
-    // This method may only be called within a larger `Emittable`
-    // implementation that checks for this stuff.
+    // That's the job of `Module::validate`, which should run
+    // before emission.
     fn emit_element(
         &mut self,
         element: ArithmeticOperation,
@@ -11,10 +11,10 @@ impl<W: Write> Emittable<Constant> for Emitter<W> {
         let opcode = element.value.to_opcode();
 
         // Emit the `const` opcode for the given value
-        self.emit_byte(opcode)?;
+        let opcode_len = self.emit_byte(opcode)?;
 
         // .. and then the actual literal
-        self.emit_element(element.value)
+        Ok(opcode_len + self.emit_element(element.value)?)
     }
 }
 
@@ -24,7 +24,7 @@ mod tests {
 
     use crate::{
         ast::{Constant, NumericalValue},
-        emitter::{Emittable, Emitter},
+        emitter::{testing::emit, Emittable, Emitter},
     };
 
     #[test]
@@ -35,7 +35,6 @@ mod tests {
             value: NumericalValue::Int32(128),
         };
 
-        // constant.emit_to(&mut buf.as_mut_slice()).unwrap();
         emitter.emit_element(constant).unwrap();
 
         assert_eq!(
@@ -67,6 +66,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn emits_negative_i32_consts_correctly() {
+        // The value is sign extended to i64 before being
+        // encoded, which must not change its encoding
+        assert_eq!(emit(Constant::i32(-1)), [0x41, 0x7f]);
+        assert_eq!(
+            emit(Constant::i32(-128)),
+            [0x41, 0x80, 0x7f]
+        );
+        assert_eq!(
+            emit(Constant::i32(i32::MIN)),
+            [0x41, 0x80, 0x80, 0x80, 0x80, 0x78]
+        );
+    }
+
+    #[test]
+    fn emits_negative_i64_consts_correctly() {
+        assert_eq!(
+            emit(Constant::i64(-9999999)),
+            [0x42, 0x81, 0xd3, 0x9d, 0x7b]
+        );
+    }
+
     #[test]
     fn emits_f32_const_correctly() {
         let mut emitter = Emitter::new(Cursor::new([0_u8; 5]));
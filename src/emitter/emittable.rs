@@ -1,5 +1,7 @@
-use std::io::{self};
+use std::io;
 
+/// Implemented by [`Emitter`](super::Emitter) for everything
+/// that can be encoded to WebAssembly.
 pub trait Emittable<T> {
     /// Emit `element` to WebAssembly.
     fn emit_element(&mut self, element: T) -> io::Result<usize>;
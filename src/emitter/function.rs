@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::{
+    ast::{Function, Local, Type},
+    opcode::opcodes::END,
+};
+
+/// Groups `locals` into runs of consecutive locals of the same
+/// type, which is how the code section declares them.
+pub(crate) fn local_runs(locals: &[Local]) -> Vec<(u32, Type)> {
+    let mut runs: Vec<(u32, Type)> = Vec::new();
+
+    for local in locals {
+        match runs.last_mut() {
+            Some((count, type_)) if *type_ == local.type_ => {
+                *count += 1;
+            }
+            _ => runs.push((1, local.type_.clone())),
+        }
+    }
+
+    runs
+}
+
+impl<W: Write> Emitter<W> {
+    /// Emits the locals of a function body as a vector of
+    /// `(count, type)` runs.
+    pub(super) fn emit_locals(
+        &mut self,
+        locals: &[Local],
+    ) -> io::Result<usize> {
+        self.emit_vector(
+            local_runs(locals),
+            |body, (count, type_)| {
+                Ok(body.emit_leb128_u32(count)?
+                    + body.emit_element(type_)?)
+            },
+        )
+    }
+
+    /// Emits an entry of the code section: the size of the body,
+    /// its locals and its instructions.
+    ///
+    /// Does not resolve identifiers: every index in the body
+    /// must already be numerical.
+    pub(super) fn emit_function_body(
+        &mut self,
+        function: Function,
+    ) -> io::Result<usize> {
+        let mut body = Emitter::new(Vec::new());
+
+        body.emit_locals(&function.local_variables)?;
+        for instruction in function.body {
+            body.emit_element(instruction)?;
+        }
+        body.emit_byte(END)?;
+
+        let written =
+            self.emit_leb128_u32(body.writer.len() as u32)?;
+        self.emit_bytes(&body.writer)?;
+
+        Ok(written + body.writer.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::local_runs;
+    use crate::{
+        ast::{Local, NumericalType, Type},
+        emitter::Emitter,
+    };
+
+    fn locals(types: &[NumericalType]) -> Vec<Local> {
+        types
+            .iter()
+            .map(|type_| Local {
+                identifier: None,
+                type_: Type::Numerical(type_.clone()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_consecutive_locals_of_the_same_type() {
+        use NumericalType::{Float64, Int32};
+
+        let runs =
+            local_runs(&locals(&[Int32, Int32, Float64, Int32]));
+
+        assert_eq!(
+            runs,
+            [
+                (2, Type::Numerical(Int32)),
+                (1, Type::Numerical(Float64)),
+                (1, Type::Numerical(Int32)),
+            ]
+        );
+        assert!(local_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn emits_local_runs() {
+        use NumericalType::{Float32, Float64, Int32, Int64};
+
+        let mut emitter = Emitter::new(Vec::new());
+        let written = emitter
+            .emit_locals(&locals(&[
+                Int32, Int32, Float64, Int32, Int64, Float32,
+                Float32, Float32,
+            ]))
+            .unwrap();
+
+        let bytes = emitter.into_inner();
+        assert_eq!(written, bytes.len());
+        assert_eq!(
+            bytes,
+            [
+                0x05, // Runs
+                0x02, 0x7f, // 2 × i32
+                0x01, 0x7c, // 1 × f64
+                0x01, 0x7f, // 1 × i32
+                0x01, 0x7e, // 1 × i64
+                0x03, 0x7d, // 3 × f32
+            ]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::ast::Index;
+
+impl<W: Write> Emittable<Index> for Emitter<W> {
+    /// Emits a numerical index as an unsigned LEB128 integer.
+    ///
+    /// Identifiers can't be resolved at this point, so they're
+    /// an `InvalidInput` error.
+    fn emit_element(
+        &mut self,
+        element: Index,
+    ) -> io::Result<usize> {
+        match element {
+            Index::Numerical(index) => {
+                let index = u32::try_from(index).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "index does not fit in an u32",
+                    )
+                })?;
+
+                self.emit_leb128_u32(index)
+            }
+            Index::Identifier(identifier) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "`${identifier}` must be resolved to an index before emission"
+                ),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        ast::Index,
+        emitter::testing::{emit, try_emit},
+    };
+
+    #[test]
+    fn emits_numerical_indices() {
+        assert_eq!(emit(Index::Numerical(3)), [0x03]);
+        assert_eq!(emit(Index::Numerical(300)), [0xac, 0x02]);
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        for index in [
+            Index::Identifier("x".into()),
+            Index::Numerical(-1),
+            Index::Numerical(1 << 32),
+        ] {
+            assert_eq!(
+                try_emit(index).unwrap_err().kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::{
+    ast::{Instruction, Opcode, Type},
+    opcode::{
+        opcodes::{BLOCK, CALL_INDIRECT, ELSE, END, IF, LOOP},
+        ToOpcode,
+    },
+};
+
+/// The block type of blocks that leave no value on the stack
+const EMPTY_BLOCK_TYPE: u8 = 0x40;
+
+impl<W: Write> Emittable<Instruction> for Emitter<W> {
+    /// Emits the folded arguments of this instruction, in order,
+    /// and then the instruction itself, since WASM's binary
+    /// format is a flat stack-machine encoding.
+    fn emit_element(
+        &mut self,
+        element: Instruction,
+    ) -> io::Result<usize> {
+        let mut bytes_written = 0;
+
+        for argument in element.arguments {
+            bytes_written += self.emit_element(argument)?;
+        }
+
+        bytes_written += self.emit_element(element.opcode)?;
+
+        Ok(bytes_written)
+    }
+}
+
+impl<W: Write> Emittable<Opcode> for Emitter<W> {
+    fn emit_element(
+        &mut self,
+        element: Opcode,
+    ) -> io::Result<usize> {
+        match element {
+            Opcode::Unreachable(unreachable) => {
+                self.emit_element(unreachable)
+            }
+            Opcode::Constant(constant) => {
+                self.emit_element(constant)
+            }
+            Opcode::Arithmetic(operation) => {
+                self.emit_element(operation)
+            }
+            Opcode::Comparison(operation) => {
+                self.emit_byte(operation.to_opcode())
+            }
+            Opcode::Conversion(operation) => {
+                self.emit_byte(operation.to_opcode())
+            }
+            Opcode::Return
+            | Opcode::Nop
+            | Opcode::Drop
+            | Opcode::Select => {
+                self.emit_byte(element.to_opcode())
+            }
+            Opcode::VariableInstruction(operation) => {
+                self.emit_element(operation)
+            }
+            Opcode::MemoryAccess(operation) => {
+                self.emit_element(operation)
+            }
+            Opcode::MemorySize | Opcode::MemoryGrow => {
+                let written =
+                    self.emit_byte(element.to_opcode())?;
+
+                // Only memory 0 can be referred to
+                Ok(written + self.emit_leb128_u32(0)?)
+            }
+            Opcode::Block(block) => self.emit_block(
+                BLOCK,
+                block.block_type,
+                block.body,
+                Vec::new(),
+            ),
+            Opcode::Loop(block) => self.emit_block(
+                LOOP,
+                block.block_type,
+                block.body,
+                Vec::new(),
+            ),
+            Opcode::If(if_block) => self.emit_block(
+                IF,
+                if_block.block_type,
+                if_block.then,
+                if_block.else_,
+            ),
+            Opcode::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                let mut written =
+                    self.emit_byte(CALL_INDIRECT)?;
+                written += self.emit_element(type_index)?;
+                written += self.emit_element(table_index)?;
+
+                Ok(written)
+            }
+            Opcode::Call(ref index)
+            | Opcode::BrIf(ref index)
+            | Opcode::Br(ref index) => {
+                let written =
+                    self.emit_byte(element.to_opcode())?;
+
+                Ok(written + self.emit_element(index.clone())?)
+            }
+        }
+    }
+}
+
+impl<W: Write> Emitter<W> {
+    /// Emits a `block`, `loop` or `if`: its opcode and block
+    /// type, the instructions of `body` and then the closing
+    /// `end`.
+    ///
+    /// `else_` is only emitted, after an `else`, if it isn't
+    /// empty.
+    fn emit_block(
+        &mut self,
+        opcode: u8,
+        block_type: Option<Type>,
+        body: Vec<Instruction>,
+        else_: Vec<Instruction>,
+    ) -> io::Result<usize> {
+        let mut written = self.emit_byte(opcode)?;
+        written += match block_type {
+            Some(type_) => self.emit_element(type_)?,
+            None => self.emit_byte(EMPTY_BLOCK_TYPE)?,
+        };
+
+        for instruction in body {
+            written += self.emit_element(instruction)?;
+        }
+        if !else_.is_empty() {
+            written += self.emit_byte(ELSE)?;
+            for instruction in else_ {
+                written += self.emit_element(instruction)?;
+            }
+        }
+
+        Ok(written + self.emit_byte(END)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        emitter::testing::emit, parser::parse_instruction,
+    };
+
+    #[test]
+    fn emits_memory_size_and_grow() {
+        let (_, instruction) =
+            parse_instruction("(memory.grow (memory.size))")
+                .unwrap();
+
+        assert_eq!(emit(instruction), &[0x3f, 0x00, 0x40, 0x00]);
+    }
+
+    #[test]
+    fn emits_default_memargs_of_folded_accesses() {
+        let (_, instruction) = parse_instruction(
+            "(i32.store (local.get 0) (i32.const 1))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            emit(instruction),
+            &[
+                0x20, 0x00, // local.get 0
+                0x41, 0x01, // i32.const 1
+                0x36, 0x02, 0x00, // i32.store align=4
+            ]
+        );
+    }
+
+    #[test]
+    fn flattens_unreachable_operands() {
+        let (_, instruction) = parse_instruction(
+            "(unreachable (i32.const 1) (i32.const 2))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            emit(instruction),
+            &[
+                0x41, 0x01, // i32.const 1
+                0x41, 0x02, // i32.const 2
+                0x00, // unreachable
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_an_empty_block() {
+        let (_, instruction) =
+            parse_instruction("(block)").unwrap();
+
+        assert_eq!(emit(instruction), &[0x02, 0x40, 0x0b]);
+    }
+
+    #[test]
+    fn emits_the_result_type_of_a_loop() {
+        let (_, instruction) = parse_instruction(
+            "(loop (result i32) (i32.const 1))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            emit(instruction),
+            &[
+                0x03, 0x7f, // loop (result i32)
+                0x41, 0x01, // i32.const 1
+                0x0b, // end
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_both_arms_of_an_if() {
+        let (_, instruction) = parse_instruction(
+            "(if (result i32) (local.get 0)
+                (then (i32.const 1))
+                (else (i32.const 2)))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            emit(instruction),
+            &[
+                0x20, 0x00, // local.get 0
+                0x04, 0x7f, // if (result i32)
+                0x41, 0x01, // i32.const 1
+                0x05, // else
+                0x41, 0x02, // i32.const 2
+                0x0b, // end
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_branch_depths() {
+        let (_, instruction) = parse_instruction(
+            "(block (br_if 0 (local.get 0)) (br 0))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            emit(instruction),
+            &[
+                0x02, 0x40, // block
+                0x20, 0x00, // local.get 0
+                0x0d, 0x00, // br_if 0
+                0x0c, 0x00, // br 0
+                0x0b, // end
+            ]
+        );
+    }
+}
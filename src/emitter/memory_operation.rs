@@ -0,0 +1,83 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::{ast::MemoryOperation, opcode::ToOpcode};
+
+impl<W: Write> Emittable<MemoryOperation> for Emitter<W> {
+    /// Emits the opcode followed by the alignment, as its base 2
+    /// logarithm, and the offset.
+    fn emit_element(
+        &mut self,
+        element: MemoryOperation,
+    ) -> io::Result<usize> {
+        let mut written = self.emit_byte(element.to_opcode())?;
+        written += self
+            .emit_leb128_u32(element.align.trailing_zeros())?;
+        written += self.emit_leb128_u32(element.offset)?;
+
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::MemoryOperation, emitter::testing::emit,
+        parser::parse_memory_operation,
+    };
+
+    fn operation(input: &str) -> MemoryOperation {
+        parse_memory_operation(input).unwrap().1
+    }
+
+    #[test]
+    fn emits_natural_alignment_by_default() {
+        assert_eq!(
+            emit(operation("i32.load")),
+            [0x28, 0x02, 0x00]
+        );
+        assert_eq!(
+            emit(operation("f64.store")),
+            [0x39, 0x03, 0x00]
+        );
+        assert_eq!(
+            emit(operation("i32.load8_u")),
+            [0x2d, 0x00, 0x00]
+        );
+        assert_eq!(
+            emit(operation("i64.store16")),
+            [0x3d, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn emits_alignments_as_their_logarithm() {
+        assert_eq!(
+            emit(operation("i32.store align=4")),
+            [0x36, 0x02, 0x00]
+        );
+        assert_eq!(
+            emit(operation("i32.store align=1")),
+            [0x36, 0x00, 0x00]
+        );
+        assert_eq!(
+            emit(operation("i64.load align=8")),
+            [0x29, 0x03, 0x00]
+        );
+        assert!(
+            parse_memory_operation("i32.store align=3").is_err()
+        );
+    }
+
+    #[test]
+    fn emits_explicit_immediates() {
+        assert_eq!(
+            emit(operation("i32.load offset=4 align=2")),
+            [0x28, 0x01, 0x04]
+        );
+        assert_eq!(
+            emit(operation("i64.load32_s offset=200")),
+            [0x34, 0x02, 0xc8, 0x01]
+        );
+    }
+}
@@ -0,0 +1,764 @@
+use std::io::{self, Write};
+
+use super::{names::Names, Emittable, Emitter};
+use crate::{
+    ast::{
+        ExportKind, Function, FunctionImport, Global, Memory,
+        Module, Type, TypeDefinition,
+    },
+    opcode::opcodes::END,
+    small_string::SmallString,
+};
+
+/// Section ids, as defined by the binary format.
+pub(super) mod section_id {
+    pub const CUSTOM: u8 = 0;
+    pub const TYPE: u8 = 1;
+    pub const IMPORT: u8 = 2;
+    pub const FUNCTION: u8 = 3;
+    pub const MEMORY: u8 = 5;
+    pub const GLOBAL: u8 = 6;
+    pub const EXPORT: u8 = 7;
+    pub const CODE: u8 = 10;
+}
+
+/// Marks the start of a function type in the type section.
+const FUNCTION_TYPE: u8 = 0x60;
+/// The import kind of functions.
+const FUNCTION_IMPORT: u8 = 0x00;
+/// Marks limits with only a minimum.
+const LIMITS_MIN: u8 = 0x00;
+/// Marks limits with both a minimum and a maximum.
+const LIMITS_MIN_MAX: u8 = 0x01;
+/// Marks an immutable global.
+const CONST_GLOBAL: u8 = 0x00;
+/// Marks a mutable global.
+const VAR_GLOBAL: u8 = 0x01;
+/// The export kinds of functions, memories and globals.
+const FUNCTION_EXPORT: u8 = 0x00;
+const MEMORY_EXPORT: u8 = 0x02;
+const GLOBAL_EXPORT: u8 = 0x03;
+
+/// The signature of a function, which is what the type section
+/// actually stores.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct FunctionType {
+    pub(crate) parameters: Vec<Type>,
+    pub(crate) results: Vec<Type>,
+}
+
+impl FunctionType {
+    pub(crate) fn of(function: &Function) -> Self {
+        Self {
+            parameters: function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.type_.clone())
+                .collect(),
+            results: function.results.clone(),
+        }
+    }
+
+    fn of_definition(definition: &TypeDefinition) -> Self {
+        Self {
+            parameters: definition
+                .parameters
+                .iter()
+                .map(|parameter| parameter.type_.clone())
+                .collect(),
+            results: definition.results.clone(),
+        }
+    }
+}
+
+/// The function types of a module: the ones it defines, which
+/// keep their indices, followed by the deduplicated types of
+/// its functions that weren't defined.
+#[derive(Default)]
+pub(crate) struct TypeTable {
+    pub(crate) types: Vec<FunctionType>,
+}
+
+impl TypeTable {
+    pub(crate) fn with_definitions(
+        definitions: &[TypeDefinition],
+    ) -> Self {
+        Self {
+            types: definitions
+                .iter()
+                .map(FunctionType::of_definition)
+                .collect(),
+        }
+    }
+
+    /// Returns the index of the given type, adding it to the
+    /// table if it's not there yet.
+    pub(crate) fn index_of(
+        &mut self,
+        function_type: FunctionType,
+    ) -> u32 {
+        let position = self
+            .types
+            .iter()
+            .position(|ty| *ty == function_type);
+
+        match position {
+            Some(position) => position as u32,
+            None => {
+                self.types.push(function_type);
+                self.types.len() as u32 - 1
+            }
+        }
+    }
+}
+
+impl<W: Write> Emittable<Module> for Emitter<W> {
+    /// Emits the sections of a module, without the preamble.
+    ///
+    /// Identifiers are resolved to numerical indices first, so
+    /// an unknown identifier is an `InvalidInput` error.
+    fn emit_element(
+        &mut self,
+        mut element: Module,
+    ) -> io::Result<usize> {
+        if !element.rec_groups.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "emitting recursive type groups is not supported yet",
+            ));
+        }
+
+        element.resolve_indices().map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                error.to_string(),
+            )
+        })?;
+
+        let mut types =
+            TypeTable::with_definitions(&element.types);
+
+        let import_types: Vec<_> = element
+            .imports
+            .iter()
+            .map(|import| {
+                types.index_of(FunctionType::of(
+                    &import.signature,
+                ))
+            })
+            .collect();
+        let function_types: Vec<_> = element
+            .functions
+            .iter()
+            .map(|function| {
+                types.index_of(FunctionType::of(function))
+            })
+            .collect();
+
+        let names =
+            self.options.emit_names.then(|| Names::of(&element));
+
+        let exports = element
+            .exports()
+            .map(|export| {
+                let (name, kind, index) =
+                    export.map_err(|error| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            error.to_string(),
+                        )
+                    })?;
+
+                Ok((SmallString::from(name), kind, index))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut bytes_written = 0;
+
+        if !types.types.is_empty() {
+            bytes_written += self.emit_type_section(types)?;
+        }
+
+        if !element.imports.is_empty() {
+            let imports =
+                element.imports.into_iter().zip(import_types);
+
+            bytes_written +=
+                self.emit_import_section(imports)?;
+        }
+
+        if !element.functions.is_empty() {
+            bytes_written +=
+                self.emit_function_section(function_types)?;
+        }
+
+        if !element.memories.is_empty() {
+            bytes_written +=
+                self.emit_memory_section(element.memories)?;
+        }
+
+        if !element.globals.is_empty() {
+            bytes_written +=
+                self.emit_global_section(element.globals)?;
+        }
+
+        if !exports.is_empty() {
+            bytes_written +=
+                self.emit_export_section(exports)?;
+        }
+
+        if !element.functions.is_empty() {
+            bytes_written +=
+                self.emit_code_section(element.functions)?;
+        }
+
+        // Custom sections may go anywhere, but the name section
+        // is expected after the others
+        if let Some(names) = names {
+            bytes_written += self.emit_name_section(names)?;
+        }
+
+        Ok(bytes_written)
+    }
+}
+
+impl<W: Write> Emitter<W> {
+    fn emit_type_section(
+        &mut self,
+        types: TypeTable,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::TYPE, |section| {
+            section.emit_vector(types.types, Emitter::emit_type)
+        })
+    }
+
+    fn emit_type(
+        &mut self,
+        function_type: FunctionType,
+    ) -> io::Result<usize> {
+        let mut written = self.emit_byte(FUNCTION_TYPE)?;
+        written += self.emit_vector(
+            function_type.parameters,
+            Emitter::emit_element,
+        )?;
+        written += self.emit_vector(
+            function_type.results,
+            Emitter::emit_element,
+        )?;
+
+        Ok(written)
+    }
+
+    fn emit_import_section<I>(
+        &mut self,
+        imports: I,
+    ) -> io::Result<usize>
+    where
+        I: ExactSizeIterator<Item = (FunctionImport, u32)>,
+    {
+        self.emit_section(section_id::IMPORT, |section| {
+            section.emit_vector(imports, Emitter::emit_import)
+        })
+    }
+
+    fn emit_import(
+        &mut self,
+        (import, type_index): (FunctionImport, u32),
+    ) -> io::Result<usize> {
+        let mut written = self.emit_name(&import.namespace)?;
+        written += self.emit_name(&import.fn_name)?;
+        written += self.emit_byte(FUNCTION_IMPORT)?;
+        written += self.emit_leb128_u32(type_index)?;
+
+        Ok(written)
+    }
+
+    fn emit_function_section(
+        &mut self,
+        type_indices: Vec<u32>,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::FUNCTION, |section| {
+            section.emit_vector(
+                type_indices,
+                Emitter::emit_leb128_u32,
+            )
+        })
+    }
+
+    fn emit_memory_section(
+        &mut self,
+        memories: Vec<Memory>,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::MEMORY, |section| {
+            section.emit_vector(memories, |section, memory| {
+                let mut written = match memory.max {
+                    None => section.emit_byte(LIMITS_MIN)?,
+                    Some(_) => {
+                        section.emit_byte(LIMITS_MIN_MAX)?
+                    }
+                };
+                written +=
+                    section.emit_leb128_u32(memory.min)?;
+                if let Some(max) = memory.max {
+                    written += section.emit_leb128_u32(max)?;
+                }
+
+                Ok(written)
+            })
+        })
+    }
+
+    fn emit_global_section(
+        &mut self,
+        globals: Vec<Global>,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::GLOBAL, |section| {
+            section.emit_vector(globals, |section, global| {
+                let mutability = if global.mutable {
+                    VAR_GLOBAL
+                } else {
+                    CONST_GLOBAL
+                };
+
+                let mut written =
+                    section.emit_element(global.type_)?;
+                written += section.emit_byte(mutability)?;
+                written += section.emit_element(global.init)?;
+                written += section.emit_byte(END)?;
+
+                Ok(written)
+            })
+        })
+    }
+
+    fn emit_export_section(
+        &mut self,
+        exports: Vec<(SmallString, ExportKind, u32)>,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::EXPORT, |section| {
+            section.emit_vector(
+                exports,
+                |section, (name, kind, index)| {
+                    let kind = match kind {
+                        ExportKind::Function => FUNCTION_EXPORT,
+                        ExportKind::Memory => MEMORY_EXPORT,
+                        ExportKind::Global => GLOBAL_EXPORT,
+                    };
+
+                    let mut written =
+                        section.emit_name(&name)?;
+                    written += section.emit_byte(kind)?;
+                    written += section.emit_leb128_u32(index)?;
+
+                    Ok(written)
+                },
+            )
+        })
+    }
+
+    fn emit_code_section(
+        &mut self,
+        functions: Vec<Function>,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::CODE, |section| {
+            section.emit_vector(
+                functions,
+                Emitter::emit_function_body,
+            )
+        })
+    }
+
+    /// Emits a section with the given id, prefixed by the size
+    /// of the contents written by `emit_contents`.
+    pub(super) fn emit_section<F>(
+        &mut self,
+        id: u8,
+        emit_contents: F,
+    ) -> io::Result<usize>
+    where
+        F: FnOnce(&mut Emitter<Vec<u8>>) -> io::Result<usize>,
+    {
+        let mut section = Emitter::new(Vec::new());
+        emit_contents(&mut section)?;
+
+        self.emit_sized(id, &section.writer)
+    }
+
+    /// Emits `id`, then the length of `bytes` and then `bytes`
+    /// themselves.
+    fn emit_sized(
+        &mut self,
+        id: u8,
+        bytes: &[u8],
+    ) -> io::Result<usize> {
+        let mut written = self.emit_byte(id)?;
+        written += self.emit_leb128_u32(bytes.len() as u32)?;
+        self.emit_bytes(bytes)?;
+
+        Ok(written + bytes.len())
+    }
+
+    /// Emits the amount of elements followed by each element.
+    pub(super) fn emit_vector<I, F>(
+        &mut self,
+        elements: I,
+        mut emit_each: F,
+    ) -> io::Result<usize>
+    where
+        I: IntoIterator,
+        I::IntoIter: ExactSizeIterator,
+        F: FnMut(&mut Self, I::Item) -> io::Result<usize>,
+    {
+        let elements = elements.into_iter();
+        let mut written =
+            self.emit_leb128_u32(elements.len() as u32)?;
+
+        for element in elements {
+            written += emit_each(self, element)?;
+        }
+
+        Ok(written)
+    }
+
+    /// Emits a length-prefixed UTF-8 name.
+    pub(super) fn emit_name(
+        &mut self,
+        name: &str,
+    ) -> io::Result<usize> {
+        let written = self.emit_leb128_u32(name.len() as u32)?;
+        self.emit_bytes(name.as_bytes())?;
+
+        Ok(written + name.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        ast::Program,
+        emitter::{testing::assert_valid_wasm, Emitter},
+        parser::parse_module,
+    };
+
+    fn compile(wat: &str) -> Vec<u8> {
+        let (rest, module) = parse_module(wat).unwrap();
+        assert!(rest.is_empty());
+
+        let mut emitter = Emitter::new(Vec::new());
+        emitter
+            .emit_program(Program {
+                modules: vec![module],
+            })
+            .unwrap();
+
+        let bytes = emitter.into_inner();
+        assert_valid_wasm(&bytes);
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn emits_empty_function() {
+        let bytes = compile("(module (func))");
+
+        assert_eq!(
+            &bytes[8..],
+            &[
+                // Type section: one `[] -> []` type
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+                // Function section: one function of type 0
+                0x03, 0x02, 0x01, 0x00,
+                // Code section: one body with no locals
+                0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+            ]
+        );
+    }
+
+    #[test]
+    fn deduplicates_function_types() {
+        let bytes = compile(
+            "(module (func (param i32)) (func (param i64)) (func (param i32)))",
+        );
+
+        // Two distinct types
+        assert_eq!(
+            &bytes[8..17],
+            &[
+                0x01, 0x09, 0x02, 0x60, 0x01, 0x7f, 0x00, 0x60,
+                0x01
+            ]
+        );
+        // Function section referencing them
+        assert_eq!(
+            &bytes[19..25],
+            &[0x03, 0x04, 0x03, 0x00, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn keeps_the_indices_of_defined_types() {
+        let bytes = compile(
+            "(module
+                (type (func (param i64)))
+                (type (func (param i64)))
+                (func (param i32))
+                (func (param i64)))",
+        );
+
+        // Both definitions, then the function type missing
+        assert_eq!(
+            &bytes[8..23],
+            &[
+                0x01, 0x0d, 0x03, // Three types
+                0x60, 0x01, 0x7e, 0x00, // (param i64)
+                0x60, 0x01, 0x7e, 0x00, // (param i64)
+                0x60, 0x01, 0x7f, 0x00, // (param i32)
+            ]
+        );
+        // Function section referencing them
+        assert_eq!(
+            &bytes[23..28],
+            &[0x03, 0x03, 0x02, 0x02, 0x00]
+        );
+    }
+
+    #[test]
+    fn emits_function_results() {
+        let bytes = compile(
+            r#"(module (import "m" "f" (func (param f32) (result i32 f64))))"#,
+        );
+
+        assert_eq!(
+            &bytes[8..17],
+            &[
+                0x01, 0x07, 0x01, 0x60, 0x01, 0x7d, 0x02, 0x7f,
+                0x7c
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_function_exports() {
+        let bytes = compile(r#"(module (func (export "add")))"#);
+
+        // Between the function and the code sections
+        assert_eq!(
+            &bytes[18..27],
+            &[
+                0x07, 0x07, 0x01, // One export
+                0x03, b'a', b'd', b'd', // named "add"
+                0x00, 0x00, // of function 0
+            ],
+        );
+    }
+
+    #[test]
+    fn emits_empty_export_names() {
+        let bytes = compile(r#"(module (func (export "")))"#);
+
+        assert_eq!(
+            &bytes[18..25],
+            &[
+                0x07, 0x04, 0x01, // One export
+                0x00, // named ""
+                0x00, 0x00, // of function 0
+                0x0a, // followed by the code section
+            ],
+        );
+    }
+
+    /// The name, kind and index of every export in `bytes`.
+    fn read_exports(
+        bytes: &[u8],
+    ) -> Vec<(&str, wasmparser::ExternalKind, u32)> {
+        wasmparser::Parser::new(0)
+            .parse_all(bytes)
+            .find_map(|payload| match payload.unwrap() {
+                wasmparser::Payload::ExportSection(reader) => {
+                    Some(reader)
+                }
+                _ => None,
+            })
+            .unwrap()
+            .into_iter()
+            .map(|export| {
+                let export = export.unwrap();
+                (export.name, export.kind, export.index)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exports_account_for_imported_functions() {
+        let bytes = compile(
+            r#"(module
+                (import "env" "f" (func))
+                (func $a (export "a") (export ""))
+                (func $b (export "b"))
+            )"#,
+        );
+
+        let function = wasmparser::ExternalKind::Func;
+        assert_eq!(
+            read_exports(&bytes),
+            [
+                ("a", function, 1),
+                ("", function, 1),
+                ("b", function, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_memory_and_global_exports() {
+        let bytes = compile(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $a i32 (i32.const 0))
+                (global $b (export "b") i32 (i32.const 1))
+                (export "a" (global $a))
+            )"#,
+        );
+
+        assert_eq!(
+            read_exports(&bytes),
+            [
+                ("memory", wasmparser::ExternalKind::Memory, 0),
+                ("b", wasmparser::ExternalKind::Global, 1),
+                ("a", wasmparser::ExternalKind::Global, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_imports_and_locals() {
+        compile(
+            r#"(module
+                (import "console" "log" (func $log (param i32)))
+                (func $f (param $x i32) (local f64) (local i64))
+            )"#,
+        );
+    }
+
+    #[test]
+    fn resolves_identifiers_before_emission() {
+        let bytes = compile(
+            r#"(module
+                (func (export "add") (param $a i32) (param $b i32) (result i32)
+                    (i32.add (local.get $a) (local.get $b))))"#,
+        );
+
+        // The code section, with both locals resolved
+        assert!(bytes.ends_with(&[
+            0x0a, 0x09, 0x01, 0x07, // One body of 7 bytes
+            0x00, // No locals
+            0x20, 0x00, // local.get 0
+            0x20, 0x01, // local.get 1
+            0x6a, 0x0b, // i32.add and the end of the body
+        ]));
+    }
+
+    #[test]
+    fn resolves_calls_before_emission() {
+        let bytes = compile(
+            "(module
+                (func $first (param $x i32) (local $y i32)
+                    (local.set $y (local.get $x))
+                    (call $other (local.get $y)))
+                (func $other (param i32)))",
+        );
+
+        // The code section, with the callee resolved to 1
+        assert!(bytes.ends_with(&[
+            0x0a, 0x11, 0x02, // Two bodies
+            0x0c, 0x01, 0x01,
+            0x7f, // 12 bytes, one i32 local
+            0x20, 0x00, 0x21,
+            0x01, // local.set $y (local.get $x)
+            0x20, 0x01, // local.get $y
+            0x10, 0x01, // call $other
+            0x0b, // end
+            0x02, 0x00, 0x0b, // An empty body
+        ]));
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers() {
+        let (_, module) =
+            parse_module("(module (func (local.get $x)))")
+                .unwrap();
+
+        let error = Emitter::new(Vec::new())
+            .emit_program(Program {
+                modules: vec![module],
+            })
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(error.to_string(), "unknown local `$x`");
+    }
+
+    #[test]
+    fn refuses_rec_groups() {
+        let (_, module) =
+            parse_module("(module (rec (type (func))))")
+                .unwrap();
+
+        let error = Emitter::new(Vec::new())
+            .emit_program(Program {
+                modules: vec![module],
+            })
+            .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn emits_memories() {
+        let bytes = compile(
+            "(module
+                (memory 1)
+                (memory $heap 2 300)
+                (func (result i32) (memory.grow (memory.size))))",
+        );
+
+        // Between the function and the code sections
+        assert_eq!(
+            &bytes[19..28],
+            &[
+                0x05, 0x07, 0x02, // Two memories
+                0x00, 0x01, // At least 1 page
+                0x01, 0x02, 0xac, 0x02, // 2 to 300 pages
+            ]
+        );
+    }
+
+    #[test]
+    fn emits_globals() {
+        let bytes = compile(
+            "(module
+                (global $counter (mut i32) (i32.const 5))
+                (global f64 (f64.const 0))
+                (func (global.set $counter (i32.const 1))))",
+        );
+
+        // Between the function and the code sections
+        assert_eq!(
+            &bytes[18..38],
+            &[
+                // Two globals
+                0x06, 0x12, 0x02,
+                // (mut i32) initialized to 5
+                0x7f, 0x01, 0x41, 0x05, 0x0b,
+                // f64 initialized to 0
+                0x7c, 0x00, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x0b,
+            ]
+        );
+    }
+}
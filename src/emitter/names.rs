@@ -0,0 +1,259 @@
+use std::io::{self, Write};
+
+use super::{module::section_id, Emitter};
+use crate::{ast::Module, small_string::SmallString};
+
+/// The name of the custom section holding the names.
+const NAME: &str = "name";
+
+/// Subsection ids of the name section.
+mod subsection_id {
+    pub const FUNCTION_NAMES: u8 = 1;
+    pub const LOCAL_NAMES: u8 = 2;
+}
+
+/// An index paired with the identifier it was written as.
+type NameMap = Vec<(u32, SmallString)>;
+
+/// The identifiers of a module's functions, parameters and
+/// locals, by index. Items without an identifier are left out.
+pub(super) struct Names {
+    functions: NameMap,
+    /// The names of the parameters and locals of each function
+    /// that has any
+    locals: Vec<(u32, NameMap)>,
+}
+
+impl Names {
+    /// Collects the names of `module`, which must be taken
+    /// before its functions are consumed by the code section.
+    pub(super) fn of(module: &Module) -> Self {
+        let functions = module
+            .imports
+            .iter()
+            .map(|import| &import.signature)
+            .chain(&module.functions)
+            .zip(0..)
+            .filter_map(|(function, index)| {
+                Some((index, function.identifier.clone()?))
+            })
+            .collect();
+
+        // Imports have no locals, and their parameters can't be
+        // referred to
+        let locals = module
+            .functions
+            .iter()
+            .zip(module.imports.len() as u32..)
+            .map(|(function, index)| {
+                let parameters = function
+                    .parameters
+                    .iter()
+                    .map(|parameter| &parameter.identifier);
+                let locals = function
+                    .local_variables
+                    .iter()
+                    .map(|local| &local.identifier);
+
+                let names: NameMap = parameters
+                    .chain(locals)
+                    .zip(0..)
+                    .filter_map(|(identifier, index)| {
+                        Some((index, identifier.clone()?))
+                    })
+                    .collect();
+
+                (index, names)
+            })
+            .filter(|(_, names)| !names.is_empty())
+            .collect();
+
+        Self { functions, locals }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.locals.is_empty()
+    }
+}
+
+impl<W: Write> Emitter<W> {
+    /// Emits the `name` custom section, which debuggers and
+    /// disassemblers use to show identifiers instead of
+    /// indices. Nothing is emitted if nothing has a name.
+    ///
+    /// See <https://webassembly.github.io/spec/core/appendix/custom.html#name-section>
+    pub(super) fn emit_name_section(
+        &mut self,
+        names: Names,
+    ) -> io::Result<usize> {
+        if names.is_empty() {
+            return Ok(0);
+        }
+
+        self.emit_section(section_id::CUSTOM, |section| {
+            let mut written = section.emit_name(NAME)?;
+
+            if !names.functions.is_empty() {
+                written += section.emit_section(
+                    subsection_id::FUNCTION_NAMES,
+                    |subsection| {
+                        subsection.emit_name_map(names.functions)
+                    },
+                )?;
+            }
+
+            if !names.locals.is_empty() {
+                written += section.emit_section(
+                    subsection_id::LOCAL_NAMES,
+                    |subsection| {
+                        subsection.emit_vector(
+                            names.locals,
+                            |subsection, (index, names)| {
+                                Ok(subsection
+                                    .emit_leb128_u32(index)?
+                                    + subsection
+                                        .emit_name_map(names)?)
+                            },
+                        )
+                    },
+                )?;
+            }
+
+            Ok(written)
+        })
+    }
+
+    fn emit_name_map(
+        &mut self,
+        names: NameMap,
+    ) -> io::Result<usize> {
+        self.emit_vector(names, |map, (index, name)| {
+            Ok(map.emit_leb128_u32(index)?
+                + map.emit_name(&name)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmparser::{KnownCustom, Name, Parser, Payload};
+
+    use crate::{
+        ast::Program,
+        emitter::{
+            testing::assert_valid_wasm, EmitOptions, Emitter,
+        },
+        parser::parse_module,
+    };
+
+    const SOURCE: &str = r#"(module
+        (import "env" "log" (func $log (param i32)))
+        (func $add (param $a i32) (param i32) (result i32)
+            (local $sum i32)
+            (i32.add (local.get $a) (local.get 1)))
+        (func))"#;
+
+    fn emit(options: EmitOptions) -> Vec<u8> {
+        let (_, module) = parse_module(SOURCE).unwrap();
+        let mut emitter =
+            Emitter::with_options(Vec::new(), options);
+        emitter
+            .emit_program(Program {
+                modules: vec![module],
+            })
+            .unwrap();
+
+        let bytes = emitter.into_inner();
+        assert_valid_wasm(&bytes);
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .unwrap();
+
+        bytes
+    }
+
+    type NameList = Vec<(u32, String)>;
+
+    /// Returns the function names and the local names of each
+    /// function in the name section of `bytes`, if there's one.
+    fn read_names(
+        bytes: &[u8],
+    ) -> Option<(NameList, Vec<(u32, NameList)>)> {
+        let reader = Parser::new(0).parse_all(bytes).find_map(
+            |payload| match payload.unwrap() {
+                Payload::CustomSection(section) => match section
+                    .as_known()
+                {
+                    KnownCustom::Name(reader) => Some(reader),
+                    _ => None,
+                },
+                _ => None,
+            },
+        )?;
+
+        let naming = |naming: wasmparser::Naming| {
+            (naming.index, naming.name.to_owned())
+        };
+        let (mut functions, mut locals) = (vec![], vec![]);
+
+        for subsection in reader {
+            match subsection.unwrap() {
+                Name::Function(map) => {
+                    functions = map
+                        .into_iter()
+                        .map(|name| naming(name.unwrap()))
+                        .collect();
+                }
+                Name::Local(map) => {
+                    locals = map
+                        .into_iter()
+                        .map(|names| {
+                            let names = names.unwrap();
+                            let map = names
+                                .names
+                                .into_iter()
+                                .map(|name| {
+                                    naming(name.unwrap())
+                                })
+                                .collect();
+
+                            (names.index, map)
+                        })
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        Some((functions, locals))
+    }
+
+    #[test]
+    fn emits_names_when_enabled() {
+        let bytes = emit(EmitOptions {
+            emit_names: true,
+            ..EmitOptions::default()
+        });
+
+        let (functions, locals) = read_names(&bytes).unwrap();
+
+        assert_eq!(
+            functions,
+            [(0, "log".to_owned()), (1, "add".to_owned())]
+        );
+        assert_eq!(
+            locals,
+            [(
+                1,
+                vec![(0, "a".to_owned()), (2, "sum".to_owned())]
+            )]
+        );
+    }
+
+    #[test]
+    fn omits_names_when_disabled() {
+        let bytes = emit(EmitOptions::default());
+
+        assert_eq!(read_names(&bytes), None);
+    }
+}
@@ -0,0 +1,145 @@
+use std::io::{self, Write};
+
+use super::{module::section_id, Emitter};
+
+/// The name of the custom section holding the producers.
+const PRODUCERS: &str = "producers";
+/// The producers field listing the tools that processed the
+/// module.
+const PROCESSED_BY: &str = "processed-by";
+
+impl<W: Write> Emitter<W> {
+    /// Emits the `producers` custom section, recording that the
+    /// module was processed by `tool` at `version`.
+    ///
+    /// See <https://github.com/WebAssembly/tool-conventions/blob/main/ProducersSection.md>
+    pub fn emit_producers_section(
+        &mut self,
+        tool: &str,
+        version: &str,
+    ) -> io::Result<usize> {
+        self.emit_section(section_id::CUSTOM, |section| {
+            let mut written = section.emit_name(PRODUCERS)?;
+
+            // A single field holding a single value
+            written += section.emit_leb128_u32(1)?;
+            written += section.emit_name(PROCESSED_BY)?;
+            written += section.emit_leb128_u32(1)?;
+            written += section.emit_name(tool)?;
+            written += section.emit_name(version)?;
+
+            Ok(written)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmparser::{KnownCustom, Parser, Payload};
+
+    use crate::{
+        ast::{Module, Program},
+        emitter::{
+            testing::assert_valid_wasm, EmitOptions, Emitter,
+        },
+    };
+
+    /// Returns every `(field, tool, version)` entry of the
+    /// producers sections in `bytes`.
+    fn read_producers(
+        bytes: &[u8],
+    ) -> Vec<(String, String, String)> {
+        let mut producers = Vec::new();
+
+        for payload in Parser::new(0).parse_all(bytes) {
+            let Payload::CustomSection(section) =
+                payload.unwrap()
+            else {
+                continue;
+            };
+            let KnownCustom::Producers(reader) =
+                section.as_known()
+            else {
+                continue;
+            };
+
+            for field in reader {
+                let field = field.unwrap();
+
+                for value in field.values {
+                    let value = value.unwrap();
+                    producers.push((
+                        field.name.to_owned(),
+                        value.name.to_owned(),
+                        value.version.to_owned(),
+                    ));
+                }
+            }
+        }
+
+        producers
+    }
+
+    #[test]
+    fn round_trips_tool_and_version() {
+        let mut emitter = Emitter::new(Vec::new());
+
+        let written = emitter
+            .emit_producers_section("water", "1.2.3")
+            .unwrap();
+
+        let section = emitter.into_inner();
+        assert_eq!(written, section.len());
+        // Custom section id, followed by the section size and
+        // the length-prefixed name
+        assert_eq!(section[0], 0x00);
+        assert_eq!(&section[2..12], b"\x09producers");
+
+        let bytes =
+            [b"\0asm\x01\0\0\0".as_slice(), &section].concat();
+        assert_valid_wasm(&bytes);
+        assert_eq!(
+            read_producers(&bytes),
+            [(
+                "processed-by".to_owned(),
+                "water".to_owned(),
+                "1.2.3".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn emits_producers_only_when_enabled() {
+        let emit = |options| {
+            let mut emitter =
+                Emitter::with_options(Vec::new(), options);
+            emitter
+                .emit_program(Program {
+                    modules: vec![Module::default()],
+                })
+                .unwrap();
+
+            emitter.into_inner()
+        };
+
+        let without = emit(EmitOptions::default());
+        assert!(read_producers(&without).is_empty());
+
+        let with = emit(EmitOptions {
+            emit_producers: true,
+            ..EmitOptions::default()
+        });
+        assert_valid_wasm(&with);
+        wasmparser::Validator::new()
+            .validate_all(&with)
+            .unwrap();
+        assert_eq!(
+            read_producers(&with),
+            [(
+                "processed-by".to_owned(),
+                env!("CARGO_PKG_NAME").to_owned(),
+                env!("CARGO_PKG_VERSION").to_owned()
+            )]
+        );
+    }
+}
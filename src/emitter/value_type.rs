@@ -0,0 +1,51 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::ast::{NumericalType, Type};
+
+impl<W: Write> Emittable<Type> for Emitter<W> {
+    fn emit_element(
+        &mut self,
+        element: Type,
+    ) -> io::Result<usize> {
+        let Type::Numerical(numerical_type) = element;
+
+        let byte = match numerical_type {
+            NumericalType::Int32 => 0x7f,
+            NumericalType::Int64 => 0x7e,
+            NumericalType::Float32 => 0x7d,
+            NumericalType::Float64 => 0x7c,
+        };
+
+        self.emit_byte(byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{NumericalType, Type},
+        emitter::{Emittable, Emitter},
+    };
+
+    #[test]
+    fn emits_numerical_value_types() {
+        let mut emitter = Emitter::new(Vec::new());
+
+        for type_ in [
+            NumericalType::Int32,
+            NumericalType::Int64,
+            NumericalType::Float32,
+            NumericalType::Float64,
+        ] {
+            emitter
+                .emit_element(Type::Numerical(type_))
+                .unwrap();
+        }
+
+        assert_eq!(
+            emitter.into_inner(),
+            &[0x7f, 0x7e, 0x7d, 0x7c]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{self, Write};
+
+use super::{Emittable, Emitter};
+use crate::{ast::VariableOperation, opcode::ToOpcode};
+
+impl<W: Write> Emittable<VariableOperation> for Emitter<W> {
+    /// Emits the opcode followed by the index of the variable.
+    ///
+    /// The index must already be numerical, since identifiers
+    /// can't be resolved at this point.
+    fn emit_element(
+        &mut self,
+        element: VariableOperation,
+    ) -> io::Result<usize> {
+        let written = self.emit_byte(element.to_opcode())?;
+
+        Ok(written + self.emit_element(element.index)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        ast::{
+            Index, ScopeKind, VariableInstruction,
+            VariableOperation,
+        },
+        emitter::testing::{emit, try_emit},
+    };
+
+    #[test]
+    fn emits_local_get() {
+        let bytes = emit(VariableOperation {
+            scope: ScopeKind::Local,
+            instruction: VariableInstruction::Get,
+            index: Index::Numerical(3),
+        });
+
+        assert_eq!(bytes, [0x20, 0x03]);
+    }
+
+    #[test]
+    fn emits_global_set() {
+        let bytes = emit(VariableOperation {
+            scope: ScopeKind::Global,
+            instruction: VariableInstruction::Set,
+            index: Index::Numerical(0),
+        });
+
+        assert_eq!(bytes, [0x24, 0x00]);
+    }
+
+    #[test]
+    fn emits_multi_byte_indices() {
+        let bytes = emit(VariableOperation {
+            scope: ScopeKind::Local,
+            instruction: VariableInstruction::Tee,
+            index: Index::Numerical(300),
+        });
+
+        assert_eq!(bytes, [0x22, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn rejects_unresolved_identifiers() {
+        let error = try_emit(VariableOperation {
+            scope: ScopeKind::Local,
+            instruction: VariableInstruction::Get,
+            index: Index::Identifier("x".into()),
+        })
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
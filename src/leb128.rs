@@ -21,6 +21,13 @@ impl From<i64> for SignedLeb128 {
     }
 }
 
+impl SignedLeb128 {
+    /// The amount of bytes this integer takes once encoded.
+    pub(crate) fn encoded_len(self) -> usize {
+        encoded_len(self)
+    }
+}
+
 impl<W: Write> Emittable<SignedLeb128> for Emitter<W> {
     fn emit_element(
         &mut self,
@@ -56,41 +63,6 @@ impl<W: Write> Emittable<SignedLeb128> for Emitter<W> {
     }
 }
 
-// impl Emittable for SignedLeb128 {
-//     fn emit_to<W: Write>(
-//         &self,
-//         writer: &mut W,
-//     ) -> io::Result<usize> {
-//         let mut bytes_written = 0;
-//         let mut value = self.value;
-//         let mut is_done = false;
-
-//         while is_done.not() {
-//             // Backup the current value
-//             let bkp = value;
-
-//             value >>= 6;
-
-//             is_done = matches!(value, 0 | -1);
-//             let byte = if is_done {
-//                 bkp & !(CONTINUATION_BIT as i64)
-//             } else {
-//                 // Remove the sign bit
-//                 value >>= 1;
-
-//                 // More bytes to come, so set the continuation
-//                 // bit.
-//                 bkp | (CONTINUATION_BIT as i64)
-//             } as u8;
-
-//             writer.write_all(&[byte])?;
-//             bytes_written += 1;
-//         }
-
-//         Ok(bytes_written)
-//     }
-// }
-
 /// LEB128 encoder for unsigned integers
 pub struct UnsignedLeb128 {
     value: u64,
@@ -102,6 +74,13 @@ impl From<u64> for UnsignedLeb128 {
     }
 }
 
+impl UnsignedLeb128 {
+    /// The amount of bytes this integer takes once encoded.
+    pub(crate) fn encoded_len(self) -> usize {
+        encoded_len(self)
+    }
+}
+
 impl<W: Write> Emittable<UnsignedLeb128> for Emitter<W> {
     fn emit_element(
         &mut self,
@@ -133,6 +112,87 @@ impl<W: Write> Emittable<UnsignedLeb128> for Emitter<W> {
     }
 }
 
+/// Encodes `integer` only to count its bytes.
+fn encoded_len<T>(integer: T) -> usize
+where
+    Emitter<io::Sink>: Emittable<T>,
+{
+    Emitter::new(io::sink())
+        .emit_element(integer)
+        .expect("writing to a sink can't fail")
+}
+
+/// Errors that can happen when decoding LEB128 integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before the last byte of the integer
+    UnexpectedEof,
+    /// The encoded integer does not fit in 64 bits
+    Overflow,
+}
+
+/// Decodes an unsigned LEB128 integer from the start of `bytes`,
+/// returning it alongside the amount of bytes read.
+pub fn read_unsigned(
+    bytes: &[u8],
+) -> Result<(u64, usize), DecodeError> {
+    let mut result = 0;
+    let mut shift = 0;
+
+    for (idx, &byte) in bytes.iter().enumerate() {
+        // The tenth byte may only hold the 64th bit
+        if shift == 63 && byte > 1 {
+            return Err(DecodeError::Overflow);
+        }
+
+        result |=
+            u64::from(byte & !(CONTINUATION_BIT as u8)) << shift;
+
+        if byte & CONTINUATION_BIT as u8 == 0 {
+            return Ok((result, idx + 1));
+        }
+
+        shift += 7;
+    }
+
+    Err(DecodeError::UnexpectedEof)
+}
+
+/// Decodes a signed LEB128 integer from the start of `bytes`,
+/// returning it alongside the amount of bytes read.
+pub fn read_signed(
+    bytes: &[u8],
+) -> Result<(i64, usize), DecodeError> {
+    const SIGN_BIT: u8 = 1 << 6;
+
+    let mut result = 0;
+    let mut shift = 0;
+
+    for (idx, &byte) in bytes.iter().enumerate() {
+        // The tenth byte may only hold the sign, so it must be
+        // either all zeroes or all ones
+        if shift == 63 && byte != 0 && byte != 0x7f {
+            return Err(DecodeError::Overflow);
+        }
+
+        let low_bits =
+            i64::from(byte & !(CONTINUATION_BIT as u8));
+        result |= low_bits << shift;
+        shift += 7;
+
+        if byte & CONTINUATION_BIT as u8 == 0 {
+            // Sign-extend negative numbers
+            if shift < 64 && byte & SIGN_BIT != 0 {
+                result |= !0 << shift;
+            }
+
+            return Ok((result, idx + 1));
+        }
+    }
+
+    Err(DecodeError::UnexpectedEof)
+}
+
 fn low_bits(value: u64) -> u8 {
     // This mask has all the lower 8 bits set
     const MASK: u64 = 0xFF;
@@ -145,9 +205,120 @@ fn low_bits(value: u64) -> u8 {
 mod tests {
     use crate::{
         emitter::{Emittable, Emitter},
-        leb128::{SignedLeb128, UnsignedLeb128},
+        leb128::{
+            read_signed, read_unsigned, DecodeError,
+            SignedLeb128, UnsignedLeb128,
+        },
     };
 
+    /// A small xorshift generator, so that the property tests
+    /// below are deterministic and need no extra dependencies.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn decodes_known_encodings() {
+        assert_eq!(read_unsigned(&[0]), Ok((0, 1)));
+        assert_eq!(
+            read_unsigned(&[229, 142, 38, 0xff]),
+            Ok((624485, 3))
+        );
+        assert_eq!(read_signed(&[0x7f]), Ok((-1, 1)));
+        assert_eq!(
+            read_signed(&[192, 187, 120]),
+            Ok((-123456, 3))
+        );
+        assert_eq!(
+            read_signed(&[
+                128, 128, 128, 128, 128, 128, 128, 128, 128, 127
+            ]),
+            Ok((i64::MIN, 10))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_encodings() {
+        assert_eq!(
+            read_unsigned(&[]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        assert_eq!(
+            read_signed(&[128, 128]),
+            Err(DecodeError::UnexpectedEof)
+        );
+        assert_eq!(
+            read_unsigned(&[
+                255, 255, 255, 255, 255, 255, 255, 255, 255, 2
+            ]),
+            Err(DecodeError::Overflow)
+        );
+        assert_eq!(
+            read_signed(&[
+                255, 255, 255, 255, 255, 255, 255, 255, 255, 1
+            ]),
+            Err(DecodeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn round_trips_random_integers() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..10_000 {
+            // Shift by a random amount so that all encoded
+            // lengths get exercised
+            let raw = rng.next();
+            let value = raw >> (raw % 64);
+
+            let mut emitter = Emitter::new(Vec::new());
+            emitter
+                .emit_element(UnsignedLeb128::from(value))
+                .unwrap();
+            let bytes = emitter.into_inner();
+            assert_eq!(
+                read_unsigned(&bytes),
+                Ok((value, bytes.len()))
+            );
+
+            let value = value as i64;
+            let mut emitter = Emitter::new(Vec::new());
+            emitter
+                .emit_element(SignedLeb128::from(value))
+                .unwrap();
+            let bytes = emitter.into_inner();
+            assert_eq!(
+                read_signed(&bytes),
+                Ok((value, bytes.len()))
+            );
+        }
+    }
+
+    #[test]
+    fn never_panics_on_arbitrary_bytes() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..10_000 {
+            let len = (rng.next() % 16) as usize;
+            let bytes: Vec<u8> =
+                (0..len).map(|_| rng.next() as u8).collect();
+
+            if let Ok((_, read)) = read_unsigned(&bytes) {
+                assert!(read <= bytes.len());
+            }
+            if let Ok((_, read)) = read_signed(&bytes) {
+                assert!(read <= bytes.len());
+            }
+        }
+    }
+
     #[test]
     fn encodes_signed_leb_128() {
         let to_encode = [
@@ -187,7 +358,6 @@ mod tests {
             let mut emitter = Emitter::new(Vec::new());
 
             emitter.emit_element(encoder).unwrap();
-            // encoder.emit_to(&mut bytes).unwrap();
             assert_eq!(emitter.into_inner(), *expected);
         }
     }
@@ -229,8 +399,25 @@ mod tests {
             let mut emitter = Emitter::new(Vec::new());
 
             emitter.emit_element(encoder).unwrap();
-            // encoder.emit_to(&mut bytes).unwrap();
             assert_eq!(emitter.into_inner(), *expected);
         }
     }
+
+    #[test]
+    fn computes_encoded_lengths() {
+        let unsigned =
+            |value| UnsignedLeb128::from(value).encoded_len();
+        let signed =
+            |value| SignedLeb128::from(value).encoded_len();
+
+        assert_eq!(unsigned(0), 1);
+        assert_eq!(unsigned(127), 1);
+        assert_eq!(unsigned(128), 2);
+        assert_eq!(unsigned(u32::MAX as u64), 5);
+        assert_eq!(signed(63), 1);
+        assert_eq!(signed(64), 2);
+        assert_eq!(signed(-64), 1);
+        assert_eq!(signed(-65), 2);
+        assert_eq!(signed(i64::MIN), 10);
+    }
 }
@@ -1,6 +1,14 @@
+pub mod assembler;
 pub mod ast;
+pub mod compile;
+mod display;
 pub mod emitter;
 pub mod leb128;
+pub mod lint;
 pub mod opcode;
 pub mod parser;
+pub mod prelude;
+pub mod resolve;
 pub mod small_string;
+pub mod stats;
+pub mod validate;
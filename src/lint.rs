@@ -0,0 +1,136 @@
+//! Checks for code that is valid but likely a mistake.
+
+use crate::ast::{Function, Instruction, Opcode};
+
+/// Finds the instructions of `function` that can never run
+/// because they follow an unconditional `unreachable`, `br` or
+/// `return` within the same block.
+///
+/// Instructions are identified by their position in the order
+/// [`Instruction::walk`] visits the body in. Arguments and
+/// bodies of dead instructions aren't reported on their own.
+///
+/// An instruction with a folded argument that never returns is
+/// treated as never returning itself, so whatever follows it is
+/// dead as well.
+pub fn find_dead_code(function: &Function) -> Vec<usize> {
+    let mut dead = Vec::new();
+    let mut position = 0;
+
+    visit_sequence(&function.body, &mut position, &mut dead);
+
+    dead
+}
+
+/// Visits a sequence of instructions, returning whether it
+/// never completes.
+fn visit_sequence(
+    instructions: &[Instruction],
+    position: &mut usize,
+    dead: &mut Vec<usize>,
+) -> bool {
+    let mut diverges = false;
+
+    for instruction in instructions {
+        if diverges {
+            dead.push(*position);
+            instruction.walk(&mut |_| *position += 1);
+        } else {
+            diverges = visit(instruction, position, dead);
+        }
+    }
+
+    diverges
+}
+
+/// Visits an instruction, returning whether control never
+/// continues past it.
+fn visit(
+    instruction: &Instruction,
+    position: &mut usize,
+    dead: &mut Vec<usize>,
+) -> bool {
+    *position += 1;
+
+    let arguments_diverge =
+        visit_sequence(&instruction.arguments, position, dead);
+
+    // Branches out of a block only skip the rest of that block,
+    // so they don't make the block itself diverge
+    match &instruction.opcode {
+        Opcode::Block(block) | Opcode::Loop(block) => {
+            visit_sequence(&block.body, position, dead);
+        }
+        Opcode::If(if_block) => {
+            visit_sequence(&if_block.then, position, dead);
+            visit_sequence(&if_block.else_, position, dead);
+        }
+        _ => {}
+    }
+
+    arguments_diverge
+        || matches!(
+            instruction.opcode,
+            Opcode::Unreachable(_)
+                | Opcode::Br(_)
+                | Opcode::Return
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_dead_code;
+    use crate::parser::parse_function;
+
+    fn dead_code(input: &str) -> Vec<usize> {
+        let (_, function) = parse_function(input).unwrap();
+
+        find_dead_code(&function)
+    }
+
+    #[test]
+    fn flags_instructions_after_a_return() {
+        assert_eq!(dead_code("(func (return) (nop))"), [1]);
+        assert_eq!(
+            dead_code("(func (nop) (unreachable) (nop) (drop))"),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn accepts_live_code() {
+        assert!(dead_code("(func (nop) (return))").is_empty());
+        assert!(dead_code(
+            "(func (block (br_if 0 (i32.const 1)) (nop)) (nop))"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn flags_dead_code_within_blocks() {
+        // The `br` only skips the rest of its block
+        assert_eq!(
+            dead_code(
+                "(func
+                    (block (br 0) (drop (i32.const 1)))
+                    (if (i32.const 1)
+                        (then (nop))
+                        (else (unreachable) (nop)))
+                    (nop))"
+            ),
+            [2, 8]
+        );
+    }
+
+    #[test]
+    fn flags_code_after_diverging_arguments() {
+        assert_eq!(
+            dead_code(
+                "(func (result i32)
+                    (i32.add (return (i32.const 1)) (i32.const 2))
+                    (nop))"
+            ),
+            [3, 4]
+        );
+    }
+}
@@ -1,27 +1,237 @@
+use self::opcodes::*;
 use crate::ast::{
     ArithmeticInstruction, ArithmeticOperation,
     ComparisonInstruction, ComparisonOperation, Constant,
-    NumericalType, NumericalValue, Opcode, ScopeKind,
-    Unreachable, VariableInstruction, VariableOperation,
+    ConversionInstruction, ConversionOperation,
+    MemoryInstruction, MemoryOperation, NumericalType,
+    NumericalValue, Opcode, ScopeKind, Unreachable,
+    VariableInstruction, VariableOperation,
 };
 
+/// The byte of every opcode we know how to encode, as defined
+/// by the binary format.
+///
+/// This is the single source of truth for opcode bytes: any
+/// encoder or decoder must refer to these instead of using
+/// magic numbers.
+pub mod opcodes {
+    // Control instructions
+    pub const UNREACHABLE: u8 = 0x00;
+    pub const NOP: u8 = 0x01;
+    pub const BLOCK: u8 = 0x02;
+    pub const LOOP: u8 = 0x03;
+    pub const IF: u8 = 0x04;
+    pub const ELSE: u8 = 0x05;
+    pub const END: u8 = 0x0b;
+    pub const BR: u8 = 0x0c;
+    pub const BR_IF: u8 = 0x0d;
+    pub const RETURN: u8 = 0x0f;
+    pub const CALL: u8 = 0x10;
+    pub const CALL_INDIRECT: u8 = 0x11;
+
+    // Parametric instructions
+    pub const DROP: u8 = 0x1a;
+    pub const SELECT: u8 = 0x1b;
+
+    // Variable instructions
+    pub const LOCAL_GET: u8 = 0x20;
+    pub const LOCAL_SET: u8 = 0x21;
+    pub const LOCAL_TEE: u8 = 0x22;
+    pub const GLOBAL_GET: u8 = 0x23;
+    pub const GLOBAL_SET: u8 = 0x24;
+
+    // Memory instructions
+    pub const I32_LOAD: u8 = 0x28;
+    pub const I64_LOAD: u8 = 0x29;
+    pub const F32_LOAD: u8 = 0x2a;
+    pub const F64_LOAD: u8 = 0x2b;
+    pub const I32_LOAD8_S: u8 = 0x2c;
+    pub const I32_LOAD8_U: u8 = 0x2d;
+    pub const I32_LOAD16_S: u8 = 0x2e;
+    pub const I32_LOAD16_U: u8 = 0x2f;
+    pub const I64_LOAD8_S: u8 = 0x30;
+    pub const I64_LOAD8_U: u8 = 0x31;
+    pub const I64_LOAD16_S: u8 = 0x32;
+    pub const I64_LOAD16_U: u8 = 0x33;
+    pub const I64_LOAD32_S: u8 = 0x34;
+    pub const I64_LOAD32_U: u8 = 0x35;
+    pub const I32_STORE: u8 = 0x36;
+    pub const I64_STORE: u8 = 0x37;
+    pub const F32_STORE: u8 = 0x38;
+    pub const F64_STORE: u8 = 0x39;
+    pub const I32_STORE8: u8 = 0x3a;
+    pub const I32_STORE16: u8 = 0x3b;
+    pub const I64_STORE8: u8 = 0x3c;
+    pub const I64_STORE16: u8 = 0x3d;
+    pub const I64_STORE32: u8 = 0x3e;
+    pub const MEMORY_SIZE: u8 = 0x3f;
+    pub const MEMORY_GROW: u8 = 0x40;
+
+    // Numeric constants
+    pub const I32_CONST: u8 = 0x41;
+    pub const I64_CONST: u8 = 0x42;
+    pub const F32_CONST: u8 = 0x43;
+    pub const F64_CONST: u8 = 0x44;
+
+    // Comparisons
+    pub const I32_EQZ: u8 = 0x45;
+    pub const I32_EQ: u8 = 0x46;
+    pub const I32_NE: u8 = 0x47;
+    pub const I32_LT_S: u8 = 0x48;
+    pub const I32_LT_U: u8 = 0x49;
+    pub const I32_GT_S: u8 = 0x4a;
+    pub const I32_GT_U: u8 = 0x4b;
+    pub const I32_LE_S: u8 = 0x4c;
+    pub const I32_LE_U: u8 = 0x4d;
+    pub const I32_GE_S: u8 = 0x4e;
+    pub const I32_GE_U: u8 = 0x4f;
+    pub const I64_EQZ: u8 = 0x50;
+    pub const I64_EQ: u8 = 0x51;
+    pub const I64_NE: u8 = 0x52;
+    pub const I64_LT_S: u8 = 0x53;
+    pub const I64_LT_U: u8 = 0x54;
+    pub const I64_GT_S: u8 = 0x55;
+    pub const I64_GT_U: u8 = 0x56;
+    pub const I64_LE_S: u8 = 0x57;
+    pub const I64_LE_U: u8 = 0x58;
+    pub const I64_GE_S: u8 = 0x59;
+    pub const I64_GE_U: u8 = 0x5a;
+    pub const F32_EQ: u8 = 0x5b;
+    pub const F32_NE: u8 = 0x5c;
+    pub const F32_LT: u8 = 0x5d;
+    pub const F32_GT: u8 = 0x5e;
+    pub const F32_LE: u8 = 0x5f;
+    pub const F32_GE: u8 = 0x60;
+    pub const F64_EQ: u8 = 0x61;
+    pub const F64_NE: u8 = 0x62;
+    pub const F64_LT: u8 = 0x63;
+    pub const F64_GT: u8 = 0x64;
+    pub const F64_LE: u8 = 0x65;
+    pub const F64_GE: u8 = 0x66;
+
+    // Arithmetic
+    pub const I32_ADD: u8 = 0x6a;
+    pub const I32_SUB: u8 = 0x6b;
+    pub const I32_MUL: u8 = 0x6c;
+    pub const I32_DIV_S: u8 = 0x6d;
+    pub const I32_DIV_U: u8 = 0x6e;
+    pub const I32_REM_S: u8 = 0x6f;
+    pub const I32_REM_U: u8 = 0x70;
+    pub const I32_AND: u8 = 0x71;
+    pub const I32_OR: u8 = 0x72;
+    pub const I32_XOR: u8 = 0x73;
+    pub const I32_SHL: u8 = 0x74;
+    pub const I32_SHR_S: u8 = 0x75;
+    pub const I32_SHR_U: u8 = 0x76;
+    pub const I32_ROTL: u8 = 0x77;
+    pub const I32_ROTR: u8 = 0x78;
+    pub const I64_ADD: u8 = 0x7c;
+    pub const I64_SUB: u8 = 0x7d;
+    pub const I64_MUL: u8 = 0x7e;
+    pub const I64_DIV_S: u8 = 0x7f;
+    pub const I64_DIV_U: u8 = 0x80;
+    pub const I64_REM_S: u8 = 0x81;
+    pub const I64_REM_U: u8 = 0x82;
+    pub const I64_AND: u8 = 0x83;
+    pub const I64_OR: u8 = 0x84;
+    pub const I64_XOR: u8 = 0x85;
+    pub const I64_SHL: u8 = 0x86;
+    pub const I64_SHR_S: u8 = 0x87;
+    pub const I64_SHR_U: u8 = 0x88;
+    pub const I64_ROTL: u8 = 0x89;
+    pub const I64_ROTR: u8 = 0x8a;
+    pub const F32_ADD: u8 = 0x92;
+    pub const F32_SUB: u8 = 0x93;
+    pub const F32_MUL: u8 = 0x94;
+    pub const F32_DIV: u8 = 0x95;
+    pub const F64_ADD: u8 = 0xa0;
+    pub const F64_SUB: u8 = 0xa1;
+    pub const F64_MUL: u8 = 0xa2;
+    pub const F64_DIV: u8 = 0xa3;
+
+    // Conversion instructions
+    pub const I32_WRAP_I64: u8 = 0xa7;
+    pub const I32_TRUNC_F32_S: u8 = 0xa8;
+    pub const I32_TRUNC_F32_U: u8 = 0xa9;
+    pub const I32_TRUNC_F64_S: u8 = 0xaa;
+    pub const I32_TRUNC_F64_U: u8 = 0xab;
+    pub const I64_EXTEND_I32_S: u8 = 0xac;
+    pub const I64_EXTEND_I32_U: u8 = 0xad;
+    pub const I64_TRUNC_F32_S: u8 = 0xae;
+    pub const I64_TRUNC_F32_U: u8 = 0xaf;
+    pub const I64_TRUNC_F64_S: u8 = 0xb0;
+    pub const I64_TRUNC_F64_U: u8 = 0xb1;
+    pub const F32_CONVERT_I32_S: u8 = 0xb2;
+    pub const F32_CONVERT_I32_U: u8 = 0xb3;
+    pub const F32_CONVERT_I64_S: u8 = 0xb4;
+    pub const F32_CONVERT_I64_U: u8 = 0xb5;
+    pub const F32_DEMOTE_F64: u8 = 0xb6;
+    pub const F64_CONVERT_I32_S: u8 = 0xb7;
+    pub const F64_CONVERT_I32_U: u8 = 0xb8;
+    pub const F64_CONVERT_I64_S: u8 = 0xb9;
+    pub const F64_CONVERT_I64_U: u8 = 0xba;
+    pub const F64_PROMOTE_F32: u8 = 0xbb;
+}
+
+/// The broad group an instruction belongs to, as laid out by
+/// the binary format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpcodeCategory {
+    /// Structured control flow, branches and calls
+    Control,
+    /// `drop` and `select`
+    Parametric,
+    /// Accesses to locals and globals
+    Variable,
+    /// Loads, stores and memory management
+    Memory,
+    /// Constants and every numeric operation
+    Numeric,
+}
+
+impl TryFrom<u8> for OpcodeCategory {
+    /// The byte that does not start a known single-byte
+    /// instruction
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x00..=0x05 | 0x0b..=0x11 => Ok(Self::Control),
+            0x1a..=0x1c => Ok(Self::Parametric),
+            0x20..=0x24 => Ok(Self::Variable),
+            0x28..=0x40 => Ok(Self::Memory),
+            0x41..=0xc4 => Ok(Self::Numeric),
+            _ => Err(byte),
+        }
+    }
+}
+
+/// The category of the instruction starting with `byte`, if it
+/// is the whole opcode of a known instruction.
+///
+/// Prefixed (multi-byte) opcodes and reserved bytes have no
+/// category.
+pub fn category_of_byte(byte: u8) -> Option<OpcodeCategory> {
+    OpcodeCategory::try_from(byte).ok()
+}
+
 pub trait ToOpcode {
     fn to_opcode(&self) -> u8;
 }
 
 impl ToOpcode for Unreachable {
     fn to_opcode(&self) -> u8 {
-        0x00
+        UNREACHABLE
     }
 }
 
 impl ToOpcode for NumericalValue {
     fn to_opcode(&self) -> u8 {
         match self {
-            NumericalValue::Int32(_) => 0x41,
-            NumericalValue::Int64(_) => 0x42,
-            NumericalValue::Float32(_) => 0x43,
-            NumericalValue::Float64(_) => 0x44,
+            NumericalValue::Int32(_) => I32_CONST,
+            NumericalValue::Int64(_) => I64_CONST,
+            NumericalValue::Float32(_) => F32_CONST,
+            NumericalValue::Float64(_) => F64_CONST,
         }
     }
 }
@@ -33,59 +243,123 @@ impl ToOpcode for ArithmeticOperation {
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::Addition,
-            ) => 0x6a,
+            ) => I32_ADD,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::Subtraction,
-            ) => 0x6b,
+            ) => I32_SUB,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::Multiplication,
-            ) => 0x6c,
+            ) => I32_MUL,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::SignedDivision,
-            ) => 0x6d,
+            ) => I32_DIV_S,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::UnsignedDisivion,
-            ) => 0x6e,
+            ) => I32_DIV_U,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::SignedRemainder,
-            ) => 0x6f,
+            ) => I32_REM_S,
             (
                 NumericalType::Int32,
                 ArithmeticInstruction::UnsignedRemainder,
-            ) => 0x70,
+            ) => I32_REM_U,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::And,
+            ) => I32_AND,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::Or,
+            ) => I32_OR,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::Xor,
+            ) => I32_XOR,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::ShiftLeft,
+            ) => I32_SHL,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::SignedShiftRight,
+            ) => I32_SHR_S,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::UnsignedShiftRight,
+            ) => I32_SHR_U,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::RotateLeft,
+            ) => I32_ROTL,
+            (
+                NumericalType::Int32,
+                ArithmeticInstruction::RotateRight,
+            ) => I32_ROTR,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::Addition,
-            ) => 0x7c,
+            ) => I64_ADD,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::Subtraction,
-            ) => 0x7d,
+            ) => I64_SUB,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::Multiplication,
-            ) => 0x7e,
+            ) => I64_MUL,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::SignedDivision,
-            ) => 0x7f,
+            ) => I64_DIV_S,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::UnsignedDisivion,
-            ) => 0x80,
+            ) => I64_DIV_U,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::SignedRemainder,
-            ) => 0x81,
+            ) => I64_REM_S,
             (
                 NumericalType::Int64,
                 ArithmeticInstruction::UnsignedRemainder,
-            ) => 0x82,
+            ) => I64_REM_U,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::And,
+            ) => I64_AND,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::Or,
+            ) => I64_OR,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::Xor,
+            ) => I64_XOR,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::ShiftLeft,
+            ) => I64_SHL,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::SignedShiftRight,
+            ) => I64_SHR_S,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::UnsignedShiftRight,
+            ) => I64_SHR_U,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::RotateLeft,
+            ) => I64_ROTL,
+            (
+                NumericalType::Int64,
+                ArithmeticInstruction::RotateRight,
+            ) => I64_ROTR,
             (
                 NumericalType::Int32
                 | NumericalType::Int64,
@@ -96,35 +370,35 @@ impl ToOpcode for ArithmeticOperation {
             (
                 NumericalType::Float32,
                 ArithmeticInstruction::Addition,
-            ) => 0x92,
+            ) => F32_ADD,
             (
                 NumericalType::Float32,
                 ArithmeticInstruction::Subtraction,
-            ) => 0x93,
+            ) => F32_SUB,
             (
                 NumericalType::Float32,
                 ArithmeticInstruction::Multiplication,
-            ) => 0x94,
+            ) => F32_MUL,
             (
                 NumericalType::Float32,
                 ArithmeticInstruction::FloatDivision,
-            ) => 0x95,
+            ) => F32_DIV,
             (
                 NumericalType::Float64,
                 ArithmeticInstruction::Addition,
-            ) => 0xa0,
+            ) => F64_ADD,
             (
                 NumericalType::Float64,
                 ArithmeticInstruction::Subtraction,
-            ) => 0xa1,
+            ) => F64_SUB,
             (
                 NumericalType::Float64,
                 ArithmeticInstruction::Multiplication,
-            ) => 0xa2,
+            ) => F64_MUL,
             (
                 NumericalType::Float64,
                 ArithmeticInstruction::FloatDivision,
-            ) => 0xa3,
+            ) => F64_DIV,
             (
                 NumericalType::Float32
                 | NumericalType::Float64,
@@ -135,110 +409,245 @@ impl ToOpcode for ArithmeticOperation {
                 NumericalType::Float32 | NumericalType::Float64,
                 ArithmeticInstruction::SignedRemainder | ArithmeticInstruction::UnsignedRemainder,
             ) => unreachable!("no remainder instruction for floating numbers"),
+            (
+                NumericalType::Float32 | NumericalType::Float64,
+                ArithmeticInstruction::And
+                | ArithmeticInstruction::Or
+                | ArithmeticInstruction::Xor
+                | ArithmeticInstruction::ShiftLeft
+                | ArithmeticInstruction::SignedShiftRight
+                | ArithmeticInstruction::UnsignedShiftRight
+                | ArithmeticInstruction::RotateLeft
+                | ArithmeticInstruction::RotateRight,
+            ) => unreachable!("no bitwise instructions for floating numbers"),
+        }
+    }
+}
+
+impl ToOpcode for ConversionOperation {
+    fn to_opcode(&self) -> u8 {
+        use ConversionInstruction as Instr;
+        use NumericalType::{Float32, Float64, Int32, Int64};
+
+        let Self {
+            type_,
+            instr,
+            source,
+        } = self;
+        match (type_, instr, source) {
+            (Int32, Instr::Wrap, Int64) => I32_WRAP_I64,
+            (Int32, Instr::SignedTruncate, Float32) => {
+                I32_TRUNC_F32_S
+            }
+            (Int32, Instr::UnsignedTruncate, Float32) => {
+                I32_TRUNC_F32_U
+            }
+            (Int32, Instr::SignedTruncate, Float64) => {
+                I32_TRUNC_F64_S
+            }
+            (Int32, Instr::UnsignedTruncate, Float64) => {
+                I32_TRUNC_F64_U
+            }
+            (Int64, Instr::SignedExtend, Int32) => I64_EXTEND_I32_S,
+            (Int64, Instr::UnsignedExtend, Int32) => {
+                I64_EXTEND_I32_U
+            }
+            (Int64, Instr::SignedTruncate, Float32) => {
+                I64_TRUNC_F32_S
+            }
+            (Int64, Instr::UnsignedTruncate, Float32) => {
+                I64_TRUNC_F32_U
+            }
+            (Int64, Instr::SignedTruncate, Float64) => {
+                I64_TRUNC_F64_S
+            }
+            (Int64, Instr::UnsignedTruncate, Float64) => {
+                I64_TRUNC_F64_U
+            }
+            (Float32, Instr::SignedConvert, Int32) => {
+                F32_CONVERT_I32_S
+            }
+            (Float32, Instr::UnsignedConvert, Int32) => {
+                F32_CONVERT_I32_U
+            }
+            (Float32, Instr::SignedConvert, Int64) => {
+                F32_CONVERT_I64_S
+            }
+            (Float32, Instr::UnsignedConvert, Int64) => {
+                F32_CONVERT_I64_U
+            }
+            (Float32, Instr::Demote, Float64) => F32_DEMOTE_F64,
+            (Float64, Instr::SignedConvert, Int32) => {
+                F64_CONVERT_I32_S
+            }
+            (Float64, Instr::UnsignedConvert, Int32) => {
+                F64_CONVERT_I32_U
+            }
+            (Float64, Instr::SignedConvert, Int64) => {
+                F64_CONVERT_I64_S
+            }
+            (Float64, Instr::UnsignedConvert, Int64) => {
+                F64_CONVERT_I64_U
+            }
+            (Float64, Instr::Promote, Float32) => F64_PROMOTE_F32,
+            _ => unreachable!(
+                "no {instr:?} conversion from {source:?} to {type_:?}"
+            ),
+        }
+    }
+}
+
+impl ToOpcode for MemoryOperation {
+    fn to_opcode(&self) -> u8 {
+        use MemoryInstruction as Instr;
+        use NumericalType::{Float32, Float64, Int32, Int64};
+
+        let Self { type_, instr, .. } = self;
+        match (type_, instr) {
+            (Int32, Instr::Load) => I32_LOAD,
+            (Int64, Instr::Load) => I64_LOAD,
+            (Float32, Instr::Load) => F32_LOAD,
+            (Float64, Instr::Load) => F64_LOAD,
+            (Int32, Instr::SignedLoad { bits: 8 }) => {
+                I32_LOAD8_S
+            }
+            (Int32, Instr::UnsignedLoad { bits: 8 }) => {
+                I32_LOAD8_U
+            }
+            (Int32, Instr::SignedLoad { bits: 16 }) => {
+                I32_LOAD16_S
+            }
+            (Int32, Instr::UnsignedLoad { bits: 16 }) => {
+                I32_LOAD16_U
+            }
+            (Int64, Instr::SignedLoad { bits: 8 }) => {
+                I64_LOAD8_S
+            }
+            (Int64, Instr::UnsignedLoad { bits: 8 }) => {
+                I64_LOAD8_U
+            }
+            (Int64, Instr::SignedLoad { bits: 16 }) => {
+                I64_LOAD16_S
+            }
+            (Int64, Instr::UnsignedLoad { bits: 16 }) => {
+                I64_LOAD16_U
+            }
+            (Int64, Instr::SignedLoad { bits: 32 }) => {
+                I64_LOAD32_S
+            }
+            (Int64, Instr::UnsignedLoad { bits: 32 }) => {
+                I64_LOAD32_U
+            }
+            (Int32, Instr::Store) => I32_STORE,
+            (Int64, Instr::Store) => I64_STORE,
+            (Float32, Instr::Store) => F32_STORE,
+            (Float64, Instr::Store) => F64_STORE,
+            (Int32, Instr::NarrowStore { bits: 8 }) => {
+                I32_STORE8
+            }
+            (Int32, Instr::NarrowStore { bits: 16 }) => {
+                I32_STORE16
+            }
+            (Int64, Instr::NarrowStore { bits: 8 }) => {
+                I64_STORE8
+            }
+            (Int64, Instr::NarrowStore { bits: 16 }) => {
+                I64_STORE16
+            }
+            (Int64, Instr::NarrowStore { bits: 32 }) => {
+                I64_STORE32
+            }
+            _ => unreachable!(
+                "no {instr:?} memory access for {type_:?}"
+            ),
         }
     }
 }
 
 impl ToOpcode for ComparisonOperation {
     fn to_opcode(&self) -> u8 {
+        use ComparisonInstruction as Instr;
+
         let Self { type_, instr } = self;
         match (type_, instr) {
+            (NumericalType::Int32, Instr::EqualZero) => I32_EQZ,
+            (NumericalType::Int32, Instr::Equal) => I32_EQ,
+            (NumericalType::Int32, Instr::NotEqual) => I32_NE,
+            (NumericalType::Int32, Instr::SignedLessThan) => I32_LT_S,
+            (NumericalType::Int32, Instr::UnsignedLessThan) => I32_LT_U,
+            (NumericalType::Int32, Instr::SignedGreaterThan) => I32_GT_S,
+            (NumericalType::Int32, Instr::UnsignedGreaterThan) => {
+                I32_GT_U
+            }
+            (NumericalType::Int32, Instr::SignedLessOrEqual) => I32_LE_S,
+            (NumericalType::Int32, Instr::UnsignedLessOrEqual) => {
+                I32_LE_U
+            }
+            (NumericalType::Int32, Instr::SignedGreaterOrEqual) => {
+                I32_GE_S
+            }
             (
                 NumericalType::Int32,
-                ComparisonInstruction::Equal,
-            ) => 0x45,
-            (
-                NumericalType::Int32,
-                ComparisonInstruction::NotEqual,
-            ) => 0x47,
-            (
-                NumericalType::Int32,
-                ComparisonInstruction::GreaterThan,
-            ) => todo!(),
-            (
-                NumericalType::Int32,
-                ComparisonInstruction::LessThan,
-            ) => todo!(),
-            (
-                NumericalType::Int32,
-                ComparisonInstruction::GreaterOrEqual,
-            ) => todo!(),
-            (
-                NumericalType::Int32,
-                ComparisonInstruction::LessOrEqual,
-            ) => todo!(),
-            (
-                NumericalType::Int64,
-                ComparisonInstruction::Equal,
-            ) => 0x51,
-            (
-                NumericalType::Int64,
-                ComparisonInstruction::NotEqual,
-            ) => 0x52,
-            (
-                NumericalType::Int64,
-                ComparisonInstruction::GreaterThan,
-            ) => todo!(),
-            (
-                NumericalType::Int64,
-                ComparisonInstruction::LessThan,
-            ) => todo!(),
-            (
-                NumericalType::Int64,
-                ComparisonInstruction::GreaterOrEqual,
-            ) => todo!(),
+                Instr::UnsignedGreaterOrEqual,
+            ) => I32_GE_U,
+            (NumericalType::Int64, Instr::EqualZero) => I64_EQZ,
+            (NumericalType::Int64, Instr::Equal) => I64_EQ,
+            (NumericalType::Int64, Instr::NotEqual) => I64_NE,
+            (NumericalType::Int64, Instr::SignedLessThan) => I64_LT_S,
+            (NumericalType::Int64, Instr::UnsignedLessThan) => I64_LT_U,
+            (NumericalType::Int64, Instr::SignedGreaterThan) => I64_GT_S,
+            (NumericalType::Int64, Instr::UnsignedGreaterThan) => {
+                I64_GT_U
+            }
+            (NumericalType::Int64, Instr::SignedLessOrEqual) => I64_LE_S,
+            (NumericalType::Int64, Instr::UnsignedLessOrEqual) => {
+                I64_LE_U
+            }
+            (NumericalType::Int64, Instr::SignedGreaterOrEqual) => {
+                I64_GE_S
+            }
             (
                 NumericalType::Int64,
-                ComparisonInstruction::LessOrEqual,
-            ) => todo!(),
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::Equal,
-            ) => 0x5b,
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::NotEqual,
-            ) => 0x5c,
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::GreaterThan,
-            ) => todo!(),
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::LessThan,
-            ) => todo!(),
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::GreaterOrEqual,
-            ) => todo!(),
-            (
-                NumericalType::Float32,
-                ComparisonInstruction::LessOrEqual,
-            ) => todo!(),
-            (
-                NumericalType::Float64,
-                ComparisonInstruction::Equal,
-            ) => 0x61,
-            (
-                NumericalType::Float64,
-                ComparisonInstruction::NotEqual,
-            ) => 0x62,
-            (
-                NumericalType::Float64,
-                ComparisonInstruction::GreaterThan,
-            ) => todo!(),
-            (
-                NumericalType::Float64,
-                ComparisonInstruction::LessThan,
-            ) => todo!(),
+                Instr::UnsignedGreaterOrEqual,
+            ) => I64_GE_U,
+            (
+                NumericalType::Int32 | NumericalType::Int64,
+                Instr::GreaterThan
+                | Instr::LessThan
+                | Instr::GreaterOrEqual
+                | Instr::LessOrEqual,
+            ) => unreachable!(
+                "integer comparisons must be signed or unsigned"
+            ),
+            (NumericalType::Float32, Instr::Equal) => F32_EQ,
+            (NumericalType::Float32, Instr::NotEqual) => F32_NE,
+            (NumericalType::Float32, Instr::LessThan) => F32_LT,
+            (NumericalType::Float32, Instr::GreaterThan) => F32_GT,
+            (NumericalType::Float32, Instr::LessOrEqual) => F32_LE,
+            (NumericalType::Float32, Instr::GreaterOrEqual) => F32_GE,
+            (NumericalType::Float64, Instr::Equal) => F64_EQ,
+            (NumericalType::Float64, Instr::NotEqual) => F64_NE,
+            (NumericalType::Float64, Instr::LessThan) => F64_LT,
+            (NumericalType::Float64, Instr::GreaterThan) => F64_GT,
+            (NumericalType::Float64, Instr::LessOrEqual) => F64_LE,
+            (NumericalType::Float64, Instr::GreaterOrEqual) => F64_GE,
             (
-                NumericalType::Float64,
-                ComparisonInstruction::GreaterOrEqual,
-            ) => todo!(),
+                NumericalType::Float32 | NumericalType::Float64,
+                Instr::SignedGreaterThan
+                | Instr::UnsignedGreaterThan
+                | Instr::SignedLessThan
+                | Instr::UnsignedLessThan
+                | Instr::SignedGreaterOrEqual
+                | Instr::UnsignedGreaterOrEqual
+                | Instr::SignedLessOrEqual
+                | Instr::UnsignedLessOrEqual,
+            ) => unreachable!(
+                "no signed or unsigned comparisons for floating numbers"
+            ),
             (
-                NumericalType::Float64,
-                ComparisonInstruction::LessOrEqual,
-            ) => todo!(),
+                NumericalType::Float32 | NumericalType::Float64,
+                Instr::EqualZero,
+            ) => unreachable!("no eqz for floating numbers"),
         }
     }
 }
@@ -249,7 +658,14 @@ impl ToOpcode for Opcode {
             Opcode::Unreachable(unreachable) => {
                 unreachable.to_opcode()
             }
-            Opcode::Call(_) => 0x10,
+            Opcode::Call(_) => CALL,
+            Opcode::CallIndirect { .. } => CALL_INDIRECT,
+            Opcode::BrIf(_) => BR_IF,
+            Opcode::Br(_) => BR,
+            Opcode::Return => RETURN,
+            Opcode::Nop => NOP,
+            Opcode::Drop => DROP,
+            Opcode::Select => SELECT,
             Opcode::VariableInstruction(variable_operation) => {
                 variable_operation.to_opcode()
             }
@@ -258,6 +674,13 @@ impl ToOpcode for Opcode {
             }
             Opcode::Arithmetic(op) => op.to_opcode(),
             Opcode::Comparison(op) => op.to_opcode(),
+            Opcode::Conversion(op) => op.to_opcode(),
+            Opcode::MemoryAccess(op) => op.to_opcode(),
+            Opcode::MemorySize => MEMORY_SIZE,
+            Opcode::MemoryGrow => MEMORY_GROW,
+            Opcode::Block(_) => BLOCK,
+            Opcode::Loop(_) => LOOP,
+            Opcode::If(_) => IF,
         }
     }
 }
@@ -272,18 +695,309 @@ impl ToOpcode for VariableOperation {
 
         match (scope, instruction) {
             // local.get
-            (ScopeKind::Local, Instr::Get) => 0x20,
+            (ScopeKind::Local, Instr::Get) => LOCAL_GET,
             // local.set
-            (ScopeKind::Local, Instr::Set) => 0x21,
+            (ScopeKind::Local, Instr::Set) => LOCAL_SET,
             // local.tee
-            (ScopeKind::Local, Instr::Tee) => 0x22,
+            (ScopeKind::Local, Instr::Tee) => LOCAL_TEE,
             // global.get
-            (ScopeKind::Global, Instr::Get) => 0x23,
+            (ScopeKind::Global, Instr::Get) => GLOBAL_GET,
             // global.set
-            (ScopeKind::Global, Instr::Set) => 0x24,
+            (ScopeKind::Global, Instr::Set) => GLOBAL_SET,
             (ScopeKind::Global, Instr::Tee) => {
                 unreachable!("global.tee is not supported")
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        category_of_byte, opcodes, OpcodeCategory, ToOpcode,
+    };
+    use crate::ast::{
+        ArithmeticInstruction, ArithmeticOperation,
+        ComparisonInstruction as Instr, ComparisonOperation,
+        ConversionInstruction, ConversionOperation, Index,
+        NumericalType, Opcode,
+    };
+
+    fn comparison(type_: NumericalType, instr: Instr) -> u8 {
+        ComparisonOperation { type_, instr }.to_opcode()
+    }
+
+    fn arithmetic(
+        type_: NumericalType,
+        instr: ArithmeticInstruction,
+    ) -> u8 {
+        ArithmeticOperation { type_, instr }.to_opcode()
+    }
+
+    #[test]
+    fn opcode_table_matches_the_spec() {
+        assert_eq!(opcodes::UNREACHABLE, 0x00);
+        assert_eq!(opcodes::BLOCK, 0x02);
+        assert_eq!(opcodes::ELSE, 0x05);
+        assert_eq!(opcodes::END, 0x0b);
+        assert_eq!(opcodes::BR, 0x0c);
+        assert_eq!(opcodes::RETURN, 0x0f);
+        assert_eq!(opcodes::CALL, 0x10);
+        assert_eq!(opcodes::CALL_INDIRECT, 0x11);
+        assert_eq!(opcodes::NOP, 0x01);
+        assert_eq!(opcodes::DROP, 0x1a);
+        assert_eq!(opcodes::SELECT, 0x1b);
+        assert_eq!(opcodes::LOCAL_GET, 0x20);
+        assert_eq!(opcodes::I32_LOAD, 0x28);
+        assert_eq!(opcodes::I64_LOAD32_U, 0x35);
+        assert_eq!(opcodes::I32_STORE, 0x36);
+        assert_eq!(opcodes::I64_STORE32, 0x3e);
+        assert_eq!(opcodes::MEMORY_SIZE, 0x3f);
+        assert_eq!(opcodes::MEMORY_GROW, 0x40);
+        assert_eq!(opcodes::I32_CONST, 0x41);
+        assert_eq!(opcodes::I64_EQ, 0x51);
+        assert_eq!(opcodes::I32_ADD, 0x6a);
+        assert_eq!(opcodes::I64_REM_U, 0x82);
+        assert_eq!(opcodes::F64_DIV, 0xa3);
+    }
+
+    #[test]
+    fn maps_integer_comparisons() {
+        let instructions = [
+            Instr::Equal,
+            Instr::NotEqual,
+            Instr::SignedLessThan,
+            Instr::UnsignedLessThan,
+            Instr::SignedGreaterThan,
+            Instr::UnsignedGreaterThan,
+            Instr::SignedLessOrEqual,
+            Instr::UnsignedLessOrEqual,
+            Instr::SignedGreaterOrEqual,
+            Instr::UnsignedGreaterOrEqual,
+        ];
+
+        for (offset, instr) in
+            instructions.into_iter().enumerate()
+        {
+            let offset = offset as u8;
+
+            assert_eq!(
+                comparison(NumericalType::Int32, instr),
+                0x46 + offset
+            );
+            assert_eq!(
+                comparison(NumericalType::Int64, instr),
+                0x51 + offset
+            );
+        }
+    }
+
+    #[test]
+    fn maps_float_comparisons() {
+        let instructions = [
+            Instr::Equal,
+            Instr::NotEqual,
+            Instr::LessThan,
+            Instr::GreaterThan,
+            Instr::LessOrEqual,
+            Instr::GreaterOrEqual,
+        ];
+
+        for (offset, instr) in
+            instructions.into_iter().enumerate()
+        {
+            let offset = offset as u8;
+
+            assert_eq!(
+                comparison(NumericalType::Float32, instr),
+                0x5b + offset
+            );
+            assert_eq!(
+                comparison(NumericalType::Float64, instr),
+                0x61 + offset
+            );
+        }
+    }
+
+    #[test]
+    fn maps_bitwise_instructions() {
+        use ArithmeticInstruction as Arith;
+
+        let instructions = [
+            Arith::And,
+            Arith::Or,
+            Arith::Xor,
+            Arith::ShiftLeft,
+            Arith::SignedShiftRight,
+            Arith::UnsignedShiftRight,
+            Arith::RotateLeft,
+            Arith::RotateRight,
+        ];
+
+        for (offset, instr) in
+            instructions.into_iter().enumerate()
+        {
+            let offset = offset as u8;
+
+            assert_eq!(
+                arithmetic(NumericalType::Int32, instr),
+                0x71 + offset
+            );
+            assert_eq!(
+                arithmetic(NumericalType::Int64, instr),
+                0x83 + offset
+            );
+        }
+
+        assert_eq!(
+            arithmetic(NumericalType::Int32, Arith::And),
+            opcodes::I32_AND
+        );
+        assert_eq!(
+            arithmetic(NumericalType::Int32, Arith::ShiftLeft),
+            0x74
+        );
+        assert_eq!(
+            arithmetic(NumericalType::Int64, Arith::RotateRight),
+            opcodes::I64_ROTR
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_bitwise_float_instructions() {
+        arithmetic(
+            NumericalType::Float64,
+            ArithmeticInstruction::Xor,
+        );
+    }
+
+    #[test]
+    fn maps_conversions() {
+        use ConversionInstruction as Conv;
+        use NumericalType::{Float32, Float64, Int32, Int64};
+
+        let conversion = |type_, instr, source| {
+            ConversionOperation {
+                type_,
+                instr,
+                source,
+            }
+            .to_opcode()
+        };
+
+        assert_eq!(conversion(Int32, Conv::Wrap, Int64), 0xa7);
+        assert_eq!(
+            conversion(Int32, Conv::SignedTruncate, Float32),
+            0xa8
+        );
+        assert_eq!(
+            conversion(Int64, Conv::UnsignedTruncate, Float64),
+            0xb1
+        );
+        assert_eq!(
+            conversion(Int64, Conv::UnsignedExtend, Int32),
+            0xad
+        );
+        assert_eq!(
+            conversion(Float64, Conv::SignedConvert, Int32),
+            0xb7
+        );
+        assert_eq!(
+            conversion(Float64, Conv::Promote, Float32),
+            0xbb
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_invalid_conversions() {
+        ConversionOperation {
+            type_: NumericalType::Int64,
+            instr: ConversionInstruction::Wrap,
+            source: NumericalType::Int32,
+        }
+        .to_opcode();
+    }
+
+    #[test]
+    fn categorizes_opcode_bytes() {
+        use OpcodeCategory::*;
+
+        assert_eq!(
+            category_of_byte(opcodes::I32_CONST),
+            Some(Numeric)
+        );
+        assert_eq!(
+            category_of_byte(opcodes::LOCAL_GET),
+            Some(Variable)
+        );
+        assert_eq!(
+            category_of_byte(opcodes::CALL),
+            Some(Control)
+        );
+        assert_eq!(
+            category_of_byte(opcodes::END),
+            Some(Control)
+        );
+        assert_eq!(
+            category_of_byte(opcodes::DROP),
+            Some(Parametric)
+        );
+        assert_eq!(
+            category_of_byte(opcodes::F64_PROMOTE_F32),
+            Some(Numeric)
+        );
+        // i32.load and memory.grow
+        assert_eq!(category_of_byte(0x28), Some(Memory));
+        assert_eq!(category_of_byte(0x40), Some(Memory));
+
+        // Reserved or prefixed opcodes
+        assert_eq!(category_of_byte(0x06), None);
+        assert_eq!(category_of_byte(0xfc), None);
+        assert_eq!(OpcodeCategory::try_from(0xff), Err(0xff));
+    }
+
+    #[test]
+    fn maps_branches() {
+        let label = || Index::Identifier("loop".into());
+
+        assert_eq!(Opcode::Br(label()).to_opcode(), 0x0c);
+        assert_eq!(Opcode::BrIf(label()).to_opcode(), 0x0d);
+        assert_eq!(Opcode::Return.to_opcode(), 0x0f);
+    }
+
+    #[test]
+    fn maps_parametric_instructions() {
+        assert_eq!(Opcode::Nop.to_opcode(), 0x01);
+        assert_eq!(Opcode::Drop.to_opcode(), 0x1a);
+        assert_eq!(Opcode::Select.to_opcode(), 0x1b);
+    }
+
+    #[test]
+    fn maps_eqz() {
+        assert_eq!(
+            comparison(NumericalType::Int32, Instr::EqualZero),
+            0x45
+        );
+        assert_eq!(
+            comparison(NumericalType::Int64, Instr::EqualZero),
+            0x50
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_float_eqz() {
+        comparison(NumericalType::Float64, Instr::EqualZero);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_signed_float_comparisons() {
+        comparison(
+            NumericalType::Float32,
+            Instr::SignedLessThan,
+        );
+    }
+}
@@ -3,15 +3,19 @@
 //! Functions are mostly all public as to allow doc-tests.
 
 mod function;
+mod global;
 mod import;
 mod instruction;
+mod memory;
 mod module;
+mod type_definition;
 mod utils;
 
 use nom::error::VerboseError;
 
 pub use self::{
-    function::*, import::*, instruction::*, module::*, utils::*,
+    function::*, global::*, import::*, instruction::*,
+    memory::*, module::*, type_definition::*, utils::*,
 };
 
 /// The result of a parsing operation with added error context
@@ -1,15 +1,17 @@
 use nom::{
-    bytes::complete::tag, character::complete::multispace0,
-    combinator::opt, error::context, multi::many0,
-    sequence::preceded,
+    bytes::complete::tag, combinator::opt, error::context,
+    multi::many0, sequence::preceded,
 };
 
 use super::IResult;
 use crate::{
-    ast::{Function, Local, Parameter},
-    parser::utils::{
-        parse_identifier, parse_parenthesis_enclosed,
-        parse_string, parse_type,
+    ast::{Function, Local, Parameter, Type},
+    parser::{
+        parse_instruction,
+        utils::{
+            parse_identifier, parse_parenthesis_enclosed,
+            parse_string, parse_type, ws,
+        },
     },
     small_string::SmallString,
 };
@@ -42,33 +44,47 @@ use crate::{
 ///     },
 /// ];
 ///
-/// let function = Function { identifier: Some("add".into()), parameters, local_variables, exports: vec![] };
+/// let results = vec![Type::Numerical(NumericalType::Int32)];
+///
+/// let function = Function { identifier: Some("add".into()), parameters, results, local_variables, exports: vec![], body: vec![] };
 ///
 /// assert_eq!(
-///     parse_function("(func $add (param $number f64) (param i64) (local $l1 i32) (local f32))"),
+///     parse_function("(func $add (param $number f64) (param i64) (result i32) (local $l1 i32) (local f32))"),
 ///     Ok(("", function))
 /// );
+///
+/// // Whitespace, including tabs and newlines, is allowed between every token
+/// let single_line = r#"(func $add (export "add") (param $a i32) (param i64) (local f32))"#;
+/// let multi_line = "(\n\tfunc\n\t\t$add\n  (export\t\"add\" )\n\t(param\n $a\n\ti32 )\n(param i64)\n\t(  local\tf32)\n)";
+///
+/// assert!(parse_function(single_line).is_ok());
+/// assert_eq!(parse_function(single_line), parse_function(multi_line));
+///
+/// // The body comes last, and may mix folded and plain instructions
+/// let (_, function) = parse_function(
+///     "(func (param $a i32) (result i32) (local.get $a) i32.const 1 i32.add)"
+/// ).unwrap();
+/// assert_eq!(function.body.len(), 3);
+/// assert!(parse_function("(func (i32.const 1) (param i32))").is_err());
 /// ```
-pub fn parse_function(input: &str) -> IResult<Function> {
-    fn inner(input: &str) -> IResult<Function> {
-        let (rest, _) =
-            preceded(multispace0, tag("func"))(input)?;
+pub fn parse_function(input: &str) -> IResult<'_, Function> {
+    fn inner(input: &str) -> IResult<'_, Function> {
+        let (rest, _) = preceded(ws, tag("func"))(input)?;
 
         let (rest, identifier) =
-            preceded(multispace0, opt(parse_identifier))(rest)?;
+            preceded(ws, opt(parse_identifier))(rest)?;
 
         // TODO: WASM allows more than one `export` instructions
         // in a function, but they cannot have duplicated
         // names. Check for this either here or at a later step.
-        let (rest, exports) = many0(parse_export)(rest)?;
-        let (rest, parameters) = many0(parse_parameter)(rest)?;
-        let (rest, local_variables) = many0(parse_local)(rest)?;
+        let (rest, exports) =
+            many0(preceded(ws, parse_export))(rest)?;
+        let (rest, function) = parse_function_contents(rest)?;
 
         let function = Function {
             identifier,
-            parameters,
-            local_variables,
             exports,
+            ..function
         };
 
         Ok((rest, function))
@@ -77,6 +93,31 @@ pub fn parse_function(input: &str) -> IResult<Function> {
     parse_parenthesis_enclosed(context("function", inner))(input)
 }
 
+/// Parses the parameters, results, locals and body of a
+/// function, which come after its identifier and exports.
+///
+/// The returned function has neither an identifier nor exports.
+pub(super) fn parse_function_contents(
+    input: &str,
+) -> IResult<'_, Function> {
+    let (rest, parameters) = many0(parse_parameter)(input)?;
+    let (rest, results) = many0(parse_result)(rest)?;
+    let (rest, local_variables) = many0(parse_local)(rest)?;
+    let (rest, body) =
+        many0(preceded(ws, parse_instruction))(rest)?;
+
+    let function = Function {
+        identifier: None,
+        parameters,
+        results: results.into_iter().flatten().collect(),
+        local_variables,
+        exports: vec![],
+        body,
+    };
+
+    Ok((rest, function))
+}
+
 /// Parses an `export` definition.
 ///
 /// ```
@@ -110,15 +151,13 @@ pub fn parse_function(input: &str) -> IResult<Function> {
 /// // Wrong: extra string quote
 /// assert!(parse_export(r#"(export "valid"")"#).is_err());
 /// ```
-pub fn parse_export(input: &str) -> IResult<SmallString> {
-    fn inner(input: &str) -> IResult<SmallString> {
-        let (rest, _) =
-            preceded(multispace0, tag("export"))(input)?;
+pub fn parse_export(input: &str) -> IResult<'_, SmallString> {
+    fn inner(input: &str) -> IResult<'_, SmallString> {
+        let (rest, _) = preceded(ws, tag("export"))(input)?;
 
-        let (rest, name) =
-            preceded(multispace0, parse_string)(rest)?;
+        let (rest, name) = preceded(ws, parse_string)(rest)?;
 
-        Ok((rest, name.into()))
+        Ok((rest, SmallString::new(&name)))
     }
 
     parse_parenthesis_enclosed(context("export", inner))(input)
@@ -146,14 +185,12 @@ pub fn parse_export(input: &str) -> IResult<SmallString> {
 /// assert_eq!(parse_parameter("( param $number f64)"), Ok(("", named_f64)));
 /// ```
 // TODO: handle cases such as (param f32 f32)
-pub fn parse_parameter(input: &str) -> IResult<Parameter> {
-    fn inner(input: &str) -> IResult<Parameter> {
-        let (rest, _) =
-            preceded(multispace0, tag("param"))(input)?;
+pub fn parse_parameter(input: &str) -> IResult<'_, Parameter> {
+    fn inner(input: &str) -> IResult<'_, Parameter> {
+        let (rest, _) = preceded(ws, tag("param"))(input)?;
         let (rest, identifier) =
-            opt(preceded(multispace0, parse_identifier))(rest)?;
-        let (rest, type_) =
-            preceded(multispace0, parse_type)(rest)?;
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let (rest, type_) = preceded(ws, parse_type)(rest)?;
 
         let parameter = Parameter { identifier, type_ };
 
@@ -161,11 +198,45 @@ pub fn parse_parameter(input: &str) -> IResult<Parameter> {
     }
 
     preceded(
-        multispace0,
+        ws,
         parse_parenthesis_enclosed(context("parameter", inner)),
     )(input)
 }
 
+/// Parses the result types of a function.
+///
+/// A single `result` may declare any number of types,
+/// including none.
+///
+/// Handles leading whitespace.
+///
+/// ```
+/// use water::ast::{Type, NumericalType};
+/// use water::parser::parse_result;
+///
+/// let i32_type = Type::Numerical(NumericalType::Int32);
+/// let f64_type = Type::Numerical(NumericalType::Float64);
+///
+/// assert_eq!(parse_result("(result i32)"), Ok(("", vec![i32_type.clone()])));
+/// assert_eq!(parse_result(" ( result i32 f64 )"), Ok(("", vec![i32_type, f64_type])));
+/// assert_eq!(parse_result("(result)"), Ok(("", vec![])));
+///
+/// // Wrong: results can't be named
+/// assert!(parse_result("(result $r i32)").is_err());
+/// ```
+pub fn parse_result(input: &str) -> IResult<'_, Vec<Type>> {
+    fn inner(input: &str) -> IResult<'_, Vec<Type>> {
+        let (rest, _) = preceded(ws, tag("result"))(input)?;
+
+        many0(preceded(ws, parse_type))(rest)
+    }
+
+    preceded(
+        ws,
+        parse_parenthesis_enclosed(context("result", inner)),
+    )(input)
+}
+
 /// Parses a local variable definition.
 ///
 /// ```
@@ -186,14 +257,12 @@ pub fn parse_parameter(input: &str) -> IResult<Parameter> {
 /// assert_eq!(parse_local("(local f32)"), Ok(("", anonymous_f32)));
 /// assert_eq!(parse_local("( local $number i64)"), Ok(("", named_i64)));
 /// ```
-pub fn parse_local(input: &str) -> IResult<Local> {
-    fn inner(input: &str) -> IResult<Local> {
-        let (rest, _) =
-            preceded(multispace0, tag("local"))(input)?;
+pub fn parse_local(input: &str) -> IResult<'_, Local> {
+    fn inner(input: &str) -> IResult<'_, Local> {
+        let (rest, _) = preceded(ws, tag("local"))(input)?;
         let (rest, identifier) =
-            opt(preceded(multispace0, parse_identifier))(rest)?;
-        let (rest, type_) =
-            preceded(multispace0, parse_type)(rest)?;
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let (rest, type_) = preceded(ws, parse_type)(rest)?;
 
         let local = Local { identifier, type_ };
 
@@ -201,7 +270,7 @@ pub fn parse_local(input: &str) -> IResult<Local> {
     }
 
     preceded(
-        multispace0,
+        ws,
         parse_parenthesis_enclosed(context("local", inner)),
     )(input)
 }
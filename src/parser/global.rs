@@ -0,0 +1,99 @@
+use nom::{
+    branch::alt,
+    combinator::{cut, opt},
+    error::context,
+    multi::many0,
+    sequence::preceded,
+    Parser,
+};
+
+use super::IResult;
+use crate::{
+    ast::{Global, Type},
+    parser::{
+        parse_export, parse_instruction,
+        utils::{
+            keyword, parse_identifier,
+            parse_parenthesis_enclosed, parse_type, ws,
+        },
+    },
+};
+
+/// Parses a global definition, made of an optional identifier,
+/// its inline exports, its type and the instruction it's
+/// initialized with.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Global, Instruction, NumericalType, Opcode, Type};
+/// use water::parser::parse_global;
+///
+/// let (_, counter) = parse_global("(global $counter (mut i32) (i32.const 0))").unwrap();
+///
+/// assert_eq!(counter.identifier, Some("counter".into()));
+/// assert_eq!(counter.type_, Type::Numerical(NumericalType::Int32));
+/// assert!(counter.mutable);
+/// assert!(matches!(counter.init.opcode, Opcode::Constant(_)));
+///
+/// // Immutable globals just name their type
+/// let (_, seven) = parse_global("(global f64 f64.const 7)").unwrap();
+///
+/// assert_eq!(seven.identifier, None);
+/// assert_eq!(seven.type_, Type::Numerical(NumericalType::Float64));
+/// assert!(!seven.mutable);
+///
+/// // Globals may be exported inline
+/// let (_, answer) = parse_global(r#"(global $answer (export "answer") i32 (i32.const 42))"#).unwrap();
+///
+/// assert_eq!(answer.exports, ["answer".into()]);
+///
+/// // Wrong: missing initializer
+/// assert!(parse_global("(global $g i32)").is_err());
+/// // Wrong: missing type
+/// assert!(parse_global("(global $g (i32.const 0))").is_err());
+/// // Wrong: more than one instruction
+/// assert!(parse_global("(global i32 (i32.const 0) (i32.const 1))").is_err());
+/// ```
+pub fn parse_global(input: &str) -> IResult<'_, Global> {
+    fn inner(input: &str) -> IResult<'_, Global> {
+        let (rest, _) = keyword("global")(input)?;
+        let (rest, identifier) =
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let (rest, exports) =
+            many0(preceded(ws, parse_export))(rest)?;
+        let (rest, (type_, mutable)) = preceded(
+            ws,
+            cut(context("global type", parse_global_type)),
+        )(rest)?;
+        let (rest, init) = preceded(
+            ws,
+            cut(context("initializer", parse_instruction)),
+        )(rest)?;
+
+        let global = Global {
+            identifier,
+            exports,
+            type_,
+            mutable,
+            init,
+        };
+
+        Ok((rest, global))
+    }
+
+    parse_parenthesis_enclosed(context("global", inner))(input)
+}
+
+/// Parses the type of a global, either `(mut T)` for mutable
+/// globals or just `T` for immutable ones.
+fn parse_global_type(input: &str) -> IResult<'_, (Type, bool)> {
+    alt((
+        parse_parenthesis_enclosed(preceded(
+            keyword("mut"),
+            preceded(ws, parse_type),
+        ))
+        .map(|type_| (type_, true)),
+        parse_type.map(|type_| (type_, false)),
+    ))(input)
+}
@@ -1,14 +1,21 @@
 use nom::{
-    bytes::complete::tag, character::complete::multispace0,
-    error::context, sequence::preceded,
+    bytes::complete::tag,
+    combinator::opt,
+    error::context,
+    multi::many0,
+    sequence::{pair, preceded},
 };
 
-use super::IResult;
+use super::{function::parse_function_contents, IResult};
 use crate::{
-    ast::FunctionImport,
+    ast::{Function, FunctionImport},
     parser::{
-        parse_function, parse_parenthesis_enclosed, parse_string,
+        parse_export, parse_function, parse_identifier,
+        parse_parenthesis_enclosed, parse_string,
+        utils::{failure, keyword},
+        ws,
     },
+    small_string::SmallString,
 };
 
 /// Parses a function import.
@@ -25,32 +32,43 @@ use crate::{
 ///         identifier: Some("log".into()),
 ///         parameters: vec![Parameter { identifier: None, type_: Type::Numerical(NumericalType::Float32)}; 2],
 ///         exports: vec![],
+///         results: vec![],
 ///         local_variables: vec![],
+///         body: vec![],
 ///     }
 /// };
 ///
 /// assert_eq!(parse_function_import(import_wat), Ok(("", parsed_import)));
+///
+/// // Wrong: imported functions have no body, so they can't have locals
+/// let with_local = r#"(import "a" "b" (func $f (local i32)))"#;
+/// assert!(matches!(parse_function_import(with_local), Err(nom::Err::Failure(_))));
+///
+/// // Wrong: imported functions can't be exported inline
+/// let with_export = r#"(import "a" "b" (func $f (export "f")))"#;
+/// assert!(matches!(parse_function_import(with_export), Err(nom::Err::Failure(_))));
+///
+/// // Wrong: imported functions have no body
+/// let with_body = r#"(import "a" "b" (func $f (result i32) (i32.const 1)))"#;
+/// assert!(matches!(parse_function_import(with_body), Err(nom::Err::Failure(_))));
 /// ```
 pub fn parse_function_import(
     input: &str,
-) -> IResult<FunctionImport> {
-    fn inner(input: &str) -> IResult<FunctionImport> {
-        let (rest, _) =
-            preceded(multispace0, tag("import"))(input)?;
+) -> IResult<'_, FunctionImport> {
+    fn inner(input: &str) -> IResult<'_, FunctionImport> {
+        let (rest, _) = preceded(ws, tag("import"))(input)?;
         let (rest, namespace) =
-            preceded(multispace0, parse_string)(rest)?;
-        let (rest, fn_name) =
-            preceded(multispace0, parse_string)(rest)?;
-        let (rest, function) =
-            preceded(multispace0, parse_function)(rest)?;
+            preceded(ws, parse_string)(rest)?;
+        let (rest, fn_name) = preceded(ws, parse_string)(rest)?;
+        let (rest, _) = ws(rest)?;
+        let signature_start = rest;
+        let (rest, function) = parse_function(rest)?;
 
-        // TODO: transform into nom errors
-        assert!(function.exports.is_empty());
-        assert!(function.local_variables.is_empty());
+        check_signature(signature_start, &function)?;
 
         let fn_import = FunctionImport {
-            namespace: namespace.into(),
-            fn_name: fn_name.into(),
+            namespace: SmallString::new(&namespace),
+            fn_name: SmallString::new(&fn_name),
             signature: function,
         };
 
@@ -61,3 +79,110 @@ pub fn parse_function_import(
         input,
     )
 }
+
+/// Parses a function import written as a function definition,
+/// such as `(func $log (import "console" "log") (param i32))`.
+///
+/// ```
+/// use water::parser::{parse_function_import, parse_inline_function_import};
+///
+/// let (_, inline) = parse_inline_function_import(
+///     r#"(func $log (import "console" "log") (param i32))"#
+/// ).unwrap();
+/// let (_, regular) = parse_function_import(
+///     r#"(import "console" "log" (func $log (param i32)))"#
+/// ).unwrap();
+///
+/// assert_eq!(inline, regular);
+/// assert!(parse_inline_function_import(r#"(func (import "a" "b"))"#).is_ok());
+///
+/// // Wrong: a regular function definition
+/// assert!(parse_inline_function_import("(func $f (param i32))").is_err());
+///
+/// // Wrong: imported functions have no body
+/// let with_body = r#"(func (import "a" "b") (result i32) (i32.const 1))"#;
+/// assert!(matches!(parse_inline_function_import(with_body), Err(nom::Err::Failure(_))));
+///
+/// // Wrong: imported functions can't be exported inline
+/// let with_export = r#"(func (export "f") (import "a" "b"))"#;
+/// assert!(matches!(parse_inline_function_import(with_export), Err(nom::Err::Failure(_))));
+/// ```
+pub fn parse_inline_function_import(
+    input: &str,
+) -> IResult<'_, FunctionImport> {
+    fn inner(input: &str) -> IResult<'_, FunctionImport> {
+        let (rest, _) = keyword("func")(input)?;
+        let (rest, identifier) =
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let exports_start = rest;
+        let (rest, exports) =
+            many0(preceded(ws, parse_export))(rest)?;
+        let (rest, (namespace, fn_name)) = preceded(
+            ws,
+            parse_parenthesis_enclosed(preceded(
+                keyword("import"),
+                pair(
+                    preceded(ws, parse_string),
+                    preceded(ws, parse_string),
+                ),
+            )),
+        )(rest)?;
+
+        if !exports.is_empty() {
+            return failure(
+                exports_start,
+                "imported function may not declare exports",
+            );
+        }
+
+        let signature_start = rest;
+        let (rest, function) = parse_function_contents(rest)?;
+
+        check_signature(signature_start, &function)?;
+
+        let fn_import = FunctionImport {
+            namespace: SmallString::new(&namespace),
+            fn_name: SmallString::new(&fn_name),
+            signature: Function {
+                identifier,
+                ..function
+            },
+        };
+
+        Ok((rest, fn_import))
+    }
+
+    parse_parenthesis_enclosed(context("function import", inner))(
+        input,
+    )
+}
+
+/// Fails if the signature of an imported function declares
+/// anything only a definition may have.
+fn check_signature<'a>(
+    input: &'a str,
+    function: &Function,
+) -> IResult<'a, ()> {
+    if !function.exports.is_empty() {
+        return failure(
+            input,
+            "imported function may not declare exports",
+        );
+    }
+
+    if !function.local_variables.is_empty() {
+        return failure(
+            input,
+            "imported function may not declare locals",
+        );
+    }
+
+    if !function.body.is_empty() {
+        return failure(
+            input,
+            "imported function may not have a body",
+        );
+    }
+
+    Ok((input, ()))
+}
@@ -4,33 +4,90 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{
-        i32 as parse_i32, i64 as parse_i64, multispace0,
+        char, i32 as parse_i32, i64 as parse_i64,
     },
-    combinator::value,
+    combinator::{cut, opt, value, verify},
     error::context,
     multi::many0,
-    number::complete::double as parse_f64,
-    sequence::preceded,
+    number::complete::{
+        double as parse_f64, float as parse_f32,
+    },
+    sequence::{preceded, separated_pair, terminated},
     Parser,
 };
 
 use super::{
-    utils::{parse_index, parse_numerical_type},
+    parse_result,
+    utils::{
+        failure, keyword, keyword_end, parse_float_special,
+        parse_hex_float, parse_hex_integer, parse_identifier,
+        parse_index, parse_numerical_type, ws,
+    },
     IResult,
 };
 use crate::{
     ast::{
-        Constant, Index, Instruction, NumericalType,
-        NumericalValue, Opcode, ScopeKind, Unreachable,
-        VariableInstruction, VariableOperation,
+        ArithmeticInstruction, ArithmeticOperation, Block,
+        ComparisonInstruction, ComparisonOperation, Constant,
+        ConversionInstruction, ConversionOperation, IfBlock,
+        Index, Instruction, MemoryInstruction, MemoryOperation,
+        NumericalType, NumericalValue, Opcode, ScopeKind, Type,
+        Unreachable, VariableInstruction, VariableOperation,
     },
     parser::utils::parse_parenthesis_enclosed,
+    small_string::SmallString,
 };
 
-pub fn parse_instruction(input: &str) -> IResult<Instruction> {
+/// Parses an instruction, either plain (`local.get $x`) or
+/// folded (`(local.set $x (i32.const 5))`).
+///
+/// The arguments of a folded instruction are parsed recursively,
+/// so any instruction may be nested within another.
+///
+/// ```
+/// use water::ast::{Constant, Index, Instruction, NumericalValue, Opcode, ScopeKind, VariableInstruction, VariableOperation};
+/// use water::parser::parse_instruction;
+///
+/// let local = |instruction, name: &str| Opcode::VariableInstruction(VariableOperation {
+///     scope: ScopeKind::Local,
+///     instruction,
+///     index: Index::Identifier(name.into()),
+/// });
+/// let leaf = |opcode| Instruction { opcode, arguments: vec![] };
+/// let four = Opcode::Constant(Constant { value: NumericalValue::Int32(4) });
+///
+/// assert_eq!(
+///     parse_instruction("(local.set $x (call $f (local.get $y) (i32.const 4)))"),
+///     Ok(("", Instruction {
+///         opcode: local(VariableInstruction::Set, "x"),
+///         arguments: vec![Instruction {
+///             opcode: Opcode::Call(Index::Identifier("f".into())),
+///             arguments: vec![
+///                 leaf(local(VariableInstruction::Get, "y")),
+///                 leaf(four.clone()),
+///             ],
+///         }],
+///     }))
+/// );
+///
+/// // A folded instruction without arguments
+/// assert_eq!(parse_instruction("(i32.const 4)"), Ok(("", leaf(four.clone()))));
+/// assert_eq!(parse_instruction("(i32.const 4) (i32.const 5)"), Ok((" (i32.const 5)", leaf(four))));
+///
+/// // Wrong: constants don't take any operands
+/// assert!(matches!(
+///     parse_instruction("(i32.const 5 (i32.const 6))"),
+///     Err(nom::Err::Failure(_))
+/// ));
+/// // Wrong: `i32.add` only takes two
+/// assert!(parse_instruction("(i32.add (i32.const 1) (i32.const 2) (i32.const 3))").is_err());
+/// ```
+pub fn parse_instruction(
+    input: &str,
+) -> IResult<'_, Instruction> {
     fn parse_plain_instruction(
         input: &str,
-    ) -> IResult<Instruction> {
+    ) -> IResult<'_, Instruction> {
         let (rest, opcode) = parse_opcode(input)?;
 
         let instr = Instruction {
@@ -41,38 +98,185 @@ pub fn parse_instruction(input: &str) -> IResult<Instruction> {
         Ok((rest, instr))
     }
 
+    alt((parse_plain_instruction, parse_folded_instruction))(
+        input,
+    )
+}
+
+/// Parses a folded instruction, such as `(i32.const 5)` or
+/// `(local.set $x (i32.const 5))`.
+fn parse_folded_instruction(
+    input: &str,
+) -> IResult<'_, Instruction> {
     fn parse_instruction_with_arguments(
         input: &str,
-    ) -> IResult<Instruction> {
+    ) -> IResult<'_, Instruction> {
         let (rest, opcode) = parse_opcode(input)?;
 
-        let (rest, arguments) = many0(preceded(
-            multispace0,
-            parse_parenthesis_enclosed(parse_instruction),
-        ))(rest)?;
+        // Arguments are always folded themselves, so that the
+        // plain form can't consume the opcode of an argument
+        // that has arguments of its own
+        let (rest, arguments) =
+            many0(preceded(ws, parse_folded_instruction))(rest)?;
 
         let instr = Instruction { opcode, arguments };
 
         Ok((rest, instr))
     }
 
+    let (rest, instr) = parse_parenthesis_enclosed(alt((
+        parse_folded_block,
+        parse_instruction_with_arguments,
+    )))(input)?;
+
+    // Opcodes with a known arity can't take more operands than
+    // they pop, e.g. `(i32.const 5 (i32.const 6))`
+    let opcode = &instr.opcode;
+    if opcode.has_fixed_arity()
+        && instr.arguments.len() > opcode.operand_count()
+    {
+        return failure(
+            input,
+            "too many operands for instruction",
+        );
+    }
+
+    Ok((rest, instr))
+}
+
+/// Parses a folded `block`, `loop` or `if`, without the
+/// enclosing parenthesis.
+///
+/// Unlike in the flat form, the closing parenthesis delimits
+/// the body, so there's no `end`.
+fn parse_folded_block(input: &str) -> IResult<'_, Instruction> {
+    fn parse_folded_if(input: &str) -> IResult<'_, Instruction> {
+        let (rest, (label, block_type)) =
+            preceded(keyword("if"), parse_block_header)(input)?;
+        // The condition is folded in before the arms
+        let (rest, arguments) =
+            many0(preceded(ws, parse_folded_instruction))(rest)?;
+        let (rest, then) = context(
+            "then",
+            cut(preceded(ws, parse_arm("then"))),
+        )(rest)?;
+        let (rest, else_) =
+            opt(preceded(ws, parse_arm("else")))(rest)?;
+
+        let if_block = IfBlock {
+            label,
+            block_type,
+            then,
+            else_: else_.unwrap_or_default(),
+        };
+        let instr = Instruction {
+            opcode: Opcode::If(if_block),
+            arguments,
+        };
+
+        Ok((rest, instr))
+    }
+
+    fn parse_arm<'a>(
+        name: &'static str,
+    ) -> impl FnMut(&'a str) -> IResult<'a, Vec<Instruction>>
+    {
+        parse_parenthesis_enclosed(preceded(
+            keyword(name),
+            parse_instructions,
+        ))
+    }
+
+    let block = |name| {
+        preceded(keyword(name), parse_block_header)
+            .and(parse_instructions)
+            .map(|((label, block_type), body)| Block {
+                label,
+                block_type,
+                body,
+            })
+    };
+
     alt((
-        parse_plain_instruction,
-        parse_parenthesis_enclosed(
-            parse_instruction_with_arguments,
-        ),
-    ))(input)
+        block("block").map(Opcode::Block),
+        block("loop").map(Opcode::Loop),
+    ))
+    .map(|opcode| Instruction {
+        opcode,
+        arguments: Vec::new(),
+    })
+    .or(parse_folded_if)
+    .parse(input)
 }
 
-pub fn parse_opcode(input: &str) -> IResult<Opcode> {
+/// Parses the opcode of an instruction alongside its
+/// immediates, such as `local.get $x` or `i32.const 5`.
+///
+/// Does not eat leading whitespace.
+///
+/// Mnemonics are case-sensitive: WAT keywords are always
+/// lowercase.
+///
+/// ```
+/// use water::ast::Opcode;
+/// use water::parser::{parse_instruction, parse_opcode};
+///
+/// assert!(parse_opcode("i32.const 5").is_ok());
+/// assert!(parse_opcode("local.get $x").is_ok());
+/// assert_eq!(parse_opcode("nop"), Ok(("", Opcode::Nop)));
+/// assert_eq!(parse_opcode("drop"), Ok(("", Opcode::Drop)));
+/// assert_eq!(parse_opcode("select"), Ok(("", Opcode::Select)));
+/// assert_eq!(parse_opcode("memory.size"), Ok(("", Opcode::MemorySize)));
+/// assert_eq!(parse_opcode("memory.grow"), Ok(("", Opcode::MemoryGrow)));
+/// assert!(parse_opcode("nope").is_err());
+///
+/// // `select` takes its operands and condition folded in
+/// let (_, select) = parse_instruction(
+///     "(select (local.get $a) (local.get $b) (local.get $cond))"
+/// ).unwrap();
+/// assert_eq!(select.opcode, Opcode::Select);
+/// assert_eq!(select.arguments.len(), 3);
+///
+/// // Wrong: uppercase or mixed-case mnemonics
+/// assert!(parse_opcode("I32.CONST 5").is_err());
+/// assert!(parse_opcode("i32.Const 5").is_err());
+/// assert!(parse_opcode("Local.Get $x").is_err());
+/// assert!(parse_opcode("LOCAL.get $x").is_err());
+/// assert!(parse_opcode("i32.EQ").is_err());
+/// assert!(parse_opcode("F64.lt").is_err());
+/// assert!(parse_opcode("Call 0").is_err());
+/// assert!(parse_opcode("UNREACHABLE").is_err());
+/// ```
+pub fn parse_opcode(input: &str) -> IResult<'_, Opcode> {
     alt((
         parse_variable_instruction
             .map(Opcode::VariableInstruction),
         parse_const
             .map(|value| Constant { value })
             .map(Opcode::Constant),
+        parse_comparison.map(Opcode::Comparison),
+        parse_arithmetic.map(Opcode::Arithmetic),
+        parse_conversion.map(Opcode::Conversion),
+        parse_memory_operation.map(Opcode::MemoryAccess),
+        value(Opcode::MemorySize, keyword("memory.size")),
+        value(Opcode::MemoryGrow, keyword("memory.grow")),
         parse_unreachable.map(Opcode::Unreachable),
+        context("call_indirect", parse_call_indirect).map(
+            |(type_index, table_index)| Opcode::CallIndirect {
+                type_index,
+                table_index,
+            },
+        ),
         context("call", parse_call).map(Opcode::Call),
+        context("br_if", parse_br_if).map(Opcode::BrIf),
+        context("br", parse_br).map(Opcode::Br),
+        value(Opcode::Return, keyword("return")),
+        value(Opcode::Nop, keyword("nop")),
+        value(Opcode::Drop, keyword("drop")),
+        value(Opcode::Select, keyword("select")),
+        parse_block.map(Opcode::Block),
+        parse_loop.map(Opcode::Loop),
+        parse_if.map(Opcode::If),
     ))(input)
 }
 
@@ -89,8 +293,53 @@ pub fn parse_opcode(input: &str) -> IResult<Opcode> {
 /// assert_eq!(parse_const("i64.const -5"), Ok(("", NumericalValue::Int64(-5))));
 /// assert_eq!(parse_const("f64.const 5.5"), Ok(("", NumericalValue::Float64(5.5))));
 /// assert_eq!(parse_const("f32.const 2E-3"), Ok(("", NumericalValue::Float32(0.002))));
+///
+/// // Floats don't need a decimal point
+/// assert_eq!(parse_const("f32.const 5"), Ok(("", NumericalValue::Float32(5.0))));
+/// assert_eq!(parse_const("f64.const 10"), Ok(("", NumericalValue::Float64(10.0))));
+/// assert_eq!(parse_const("f64.const -3"), Ok(("", NumericalValue::Float64(-3.0))));
+///
+/// // WAT has no octal literals: leading zeros are just decimal
+/// assert_eq!(parse_const("i32.const 007"), Ok(("", NumericalValue::Int32(7))));
+/// assert_eq!(parse_const("i32.const 0"), Ok(("", NumericalValue::Int32(0))));
+///
+/// // Hex literals are bit patterns, so they may wrap around
+/// assert_eq!(parse_const("i32.const 0xff"), Ok(("", NumericalValue::Int32(255))));
+/// assert_eq!(parse_const("i32.const -0x10"), Ok(("", NumericalValue::Int32(-16))));
+/// assert_eq!(parse_const("i32.const 0xffffffff"), Ok(("", NumericalValue::Int32(-1))));
+/// assert_eq!(parse_const("i32.const 0x80000000"), Ok(("", NumericalValue::Int32(i32::MIN))));
+/// assert_eq!(parse_const("i64.const 0x7fffffffffffffff"), Ok(("", NumericalValue::Int64(i64::MAX))));
+/// assert_eq!(parse_const("i64.const -0x1"), Ok(("", NumericalValue::Int64(-1))));
+/// assert!(parse_const("i32.const 0x1ffffffff").is_err());
+///
+/// // f32 constants are rounded straight to the nearest f32
+/// let f32_bits = |input| match parse_const(input) {
+///     Ok(("", NumericalValue::Float32(float))) => float.to_bits(),
+///     other => panic!("unexpected {other:?}"),
+/// };
+/// assert_eq!(f32_bits("f32.const 3.4028235e38"), 0x7f7f_ffff);
+/// assert_eq!(f32_bits("f32.const 1e-45"), 0x0000_0001);
+/// assert_eq!(f32_bits("f32.const 0.1"), 0x3dcc_cccd);
+///
+/// // Hex floats are exact up to rounding to the float type
+/// assert_eq!(parse_const("f64.const -0x1.8p-2"), Ok(("", NumericalValue::Float64(-0.375))));
+/// assert_eq!(parse_const("f32.const 0x0.1p4"), Ok(("", NumericalValue::Float32(1.0))));
+/// assert_eq!(f32_bits("f32.const 0x1p-149"), 0x0000_0001);
+///
+/// // So are infinities and NaNs, NaN payloads included
+/// let f64_bits = |input| match parse_const(input) {
+///     Ok(("", NumericalValue::Float64(float))) => float.to_bits(),
+///     other => panic!("unexpected {other:?}"),
+/// };
+/// assert_eq!(f64_bits("f64.const inf"), f64::INFINITY.to_bits());
+/// assert_eq!(f64_bits("f64.const -inf"), f64::NEG_INFINITY.to_bits());
+/// assert_eq!(f64_bits("f64.const nan"), 0x7ff8_0000_0000_0000);
+/// assert_eq!(f64_bits("f64.const nan:0x4000000000000"), 0x7ff4_0000_0000_0000);
+/// assert_eq!(f32_bits("f32.const -inf"), 0xff80_0000);
+/// assert_eq!(f32_bits("f32.const nan"), 0x7fc0_0000);
+/// assert_eq!(f32_bits("f32.const nan:0x1"), 0x7f80_0001);
 /// ```
-pub fn parse_const(input: &str) -> IResult<NumericalValue> {
+pub fn parse_const(input: &str) -> IResult<'_, NumericalValue> {
     // Parse the numerical type of this instruction: i32, i64,
     // f32 or f64
     let (rest, numerical_type) = parse_numerical_type(input)?;
@@ -99,35 +348,638 @@ pub fn parse_const(input: &str) -> IResult<NumericalValue> {
 
     match numerical_type {
         NumericalType::Int32 => {
-            let (rest, int32) =
-                preceded(multispace0, parse_i32)(rest)?;
+            let (rest, int32) = preceded(
+                ws,
+                alt((
+                    parse_hex_integer(32).map(|int| int as i32),
+                    parse_i32,
+                )),
+            )(rest)?;
 
             Ok((rest, NumericalValue::Int32(int32)))
         }
         NumericalType::Int64 => {
-            let (rest, int64) =
-                preceded(multispace0, parse_i64)(rest)?;
+            let (rest, int64) = preceded(
+                ws,
+                alt((parse_hex_integer(64), parse_i64)),
+            )(rest)?;
 
             Ok((rest, NumericalValue::Int64(int64)))
         }
         NumericalType::Float32 => {
-            let (rest, float64) =
-                preceded(multispace0, parse_f64)(rest)?;
+            // Parsed straight into an f32, since going through
+            // an f64 first could round twice
+            let (rest, float32) = preceded(
+                ws,
+                alt((
+                    parse_float_special(32)
+                        .map(|bits| f32::from_bits(bits as u32)),
+                    parse_hex_float(32)
+                        .map(|bits| f32::from_bits(bits as u32)),
+                    parse_f32,
+                )),
+            )(rest)?;
 
-            // TODO: parsing f32.const as f64 and then casting to
-            // f32 is a hack and we should switch to using
-            // `nom::number::complete::f32`
-            Ok((rest, NumericalValue::Float32(float64 as f32)))
+            Ok((rest, NumericalValue::Float32(float32)))
         }
         NumericalType::Float64 => {
-            let (rest, float64) =
-                preceded(multispace0, parse_f64)(rest)?;
+            let (rest, float64) = preceded(
+                ws,
+                alt((
+                    parse_float_special(64).map(f64::from_bits),
+                    parse_hex_float(64).map(f64::from_bits),
+                    parse_f64,
+                )),
+            )(rest)?;
 
             Ok((rest, NumericalValue::Float64(float64)))
         }
     }
 }
 
+/// Parses a comparison operation, such as `i32.lt_s` or
+/// `f64.ge`.
+///
+/// Integer types only have the signed (`_s`) and unsigned (`_u`)
+/// forms of the ordered comparisons, while floating types only
+/// have the form without a suffix.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{ComparisonInstruction, ComparisonOperation, NumericalType};
+/// use water::parser::parse_comparison;
+///
+/// assert_eq!(
+///     parse_comparison("i32.lt_s"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Int32,
+///         instr: ComparisonInstruction::SignedLessThan,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_comparison("i64.ge_u"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Int64,
+///         instr: ComparisonInstruction::UnsignedGreaterOrEqual,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_comparison("f64.gt"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Float64,
+///         instr: ComparisonInstruction::GreaterThan,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_comparison("f32.ne"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Float32,
+///         instr: ComparisonInstruction::NotEqual,
+///     }))
+/// );
+///
+/// // `eqz` compares its single operand against zero
+/// assert_eq!(
+///     parse_comparison("i32.eqz"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Int32,
+///         instr: ComparisonInstruction::EqualZero,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_comparison("i64.eqz"),
+///     Ok(("", ComparisonOperation {
+///         type_: NumericalType::Int64,
+///         instr: ComparisonInstruction::EqualZero,
+///     }))
+/// );
+///
+/// // Wrong: integers need a signedness, floats can't have one
+/// assert!(parse_comparison("i32.lt").is_err());
+/// assert!(parse_comparison("f32.lt_s").is_err());
+/// // Wrong: `eqz` is integer-only
+/// assert!(parse_comparison("f32.eqz").is_err());
+/// ```
+pub fn parse_comparison(
+    input: &str,
+) -> IResult<'_, ComparisonOperation> {
+    use ComparisonInstruction as Instr;
+
+    let parse_instr = alt((
+        value(Instr::EqualZero, tag("eqz")),
+        value(Instr::Equal, tag("eq")),
+        value(Instr::NotEqual, tag("ne")),
+        value(Instr::SignedGreaterThan, tag("gt_s")),
+        value(Instr::UnsignedGreaterThan, tag("gt_u")),
+        value(Instr::GreaterThan, tag("gt")),
+        value(Instr::SignedLessThan, tag("lt_s")),
+        value(Instr::UnsignedLessThan, tag("lt_u")),
+        value(Instr::LessThan, tag("lt")),
+        value(Instr::SignedGreaterOrEqual, tag("ge_s")),
+        value(Instr::UnsignedGreaterOrEqual, tag("ge_u")),
+        value(Instr::GreaterOrEqual, tag("ge")),
+        value(Instr::SignedLessOrEqual, tag("le_s")),
+        value(Instr::UnsignedLessOrEqual, tag("le_u")),
+        value(Instr::LessOrEqual, tag("le")),
+    ));
+
+    let (rest, (type_, instr)) = context(
+        "comparison",
+        verify(
+            terminated(
+                separated_pair(
+                    parse_numerical_type,
+                    char('.'),
+                    parse_instr,
+                ),
+                keyword_end,
+            ),
+            |(type_, instr)| is_valid_comparison(type_, instr),
+        ),
+    )(input)?;
+
+    Ok((rest, ComparisonOperation { type_, instr }))
+}
+
+/// Whether the given comparison exists for the given type.
+fn is_valid_comparison(
+    type_: &NumericalType,
+    instr: &ComparisonInstruction,
+) -> bool {
+    use ComparisonInstruction as Instr;
+
+    let is_float = matches!(
+        type_,
+        NumericalType::Float32 | NumericalType::Float64
+    );
+
+    match instr {
+        Instr::Equal | Instr::NotEqual => true,
+        Instr::GreaterThan
+        | Instr::LessThan
+        | Instr::GreaterOrEqual
+        | Instr::LessOrEqual => is_float,
+        _ => !is_float,
+    }
+}
+
+/// Parses an arithmetic operation, such as `i32.add` or
+/// `f64.div`.
+///
+/// Integer types only have the signed (`_s`) and unsigned (`_u`)
+/// forms of division and remainder, while floating types only
+/// have `div`. Bitwise and shift operations are integer-only.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{ArithmeticInstruction, ArithmeticOperation, NumericalType};
+/// use water::parser::{parse_arithmetic, parse_instruction};
+///
+/// assert_eq!(
+///     parse_arithmetic("i32.add"),
+///     Ok(("", ArithmeticOperation {
+///         type_: NumericalType::Int32,
+///         instr: ArithmeticInstruction::Addition,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_arithmetic("f64.div"),
+///     Ok(("", ArithmeticOperation {
+///         type_: NumericalType::Float64,
+///         instr: ArithmeticInstruction::FloatDivision,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_arithmetic("i64.mul"),
+///     Ok(("", ArithmeticOperation {
+///         type_: NumericalType::Int64,
+///         instr: ArithmeticInstruction::Multiplication,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_arithmetic("i64.rem_u"),
+///     Ok(("", ArithmeticOperation {
+///         type_: NumericalType::Int64,
+///         instr: ArithmeticInstruction::UnsignedRemainder,
+///     }))
+/// );
+/// assert!(parse_instruction("(i32.add (i32.const 1) (i32.const 2))").is_ok());
+///
+/// let bitwise = |input| parse_arithmetic(input).map(|(_, op)| op.instr);
+/// assert_eq!(bitwise("i32.and"), Ok(ArithmeticInstruction::And));
+/// assert_eq!(bitwise("i64.xor"), Ok(ArithmeticInstruction::Xor));
+/// assert_eq!(bitwise("i32.shr_s"), Ok(ArithmeticInstruction::SignedShiftRight));
+/// assert_eq!(bitwise("i64.rotl"), Ok(ArithmeticInstruction::RotateLeft));
+/// // Wrong: bitwise operations are integer-only
+/// assert!(parse_arithmetic("f32.and").is_err());
+/// assert!(parse_arithmetic("f64.shl").is_err());
+///
+/// // Wrong: integers need a signedness, floats can't have one
+/// assert!(parse_arithmetic("i32.div").is_err());
+/// assert!(parse_arithmetic("f32.div_s").is_err());
+/// assert!(parse_arithmetic("f64.rem_u").is_err());
+/// // Wrong: not a full mnemonic
+/// assert!(parse_arithmetic("i32.addx").is_err());
+/// ```
+pub fn parse_arithmetic(
+    input: &str,
+) -> IResult<'_, ArithmeticOperation> {
+    use ArithmeticInstruction as Instr;
+
+    let parse_instr = alt((
+        value(Instr::Addition, tag("add")),
+        value(Instr::Subtraction, tag("sub")),
+        value(Instr::Multiplication, tag("mul")),
+        value(Instr::SignedDivision, tag("div_s")),
+        value(Instr::UnsignedDisivion, tag("div_u")),
+        value(Instr::FloatDivision, tag("div")),
+        value(Instr::SignedRemainder, tag("rem_s")),
+        value(Instr::UnsignedRemainder, tag("rem_u")),
+        value(Instr::And, tag("and")),
+        value(Instr::Or, tag("or")),
+        value(Instr::Xor, tag("xor")),
+        value(Instr::ShiftLeft, tag("shl")),
+        value(Instr::SignedShiftRight, tag("shr_s")),
+        value(Instr::UnsignedShiftRight, tag("shr_u")),
+        value(Instr::RotateLeft, tag("rotl")),
+        value(Instr::RotateRight, tag("rotr")),
+    ));
+
+    let (rest, (type_, instr)) = context(
+        "arithmetic",
+        verify(
+            terminated(
+                separated_pair(
+                    parse_numerical_type,
+                    char('.'),
+                    parse_instr,
+                ),
+                keyword_end,
+            ),
+            |(type_, instr)| is_valid_arithmetic(type_, instr),
+        ),
+    )(input)?;
+
+    Ok((rest, ArithmeticOperation { type_, instr }))
+}
+
+/// Whether the given arithmetic operation exists for the given
+/// type.
+fn is_valid_arithmetic(
+    type_: &NumericalType,
+    instr: &ArithmeticInstruction,
+) -> bool {
+    use ArithmeticInstruction as Instr;
+
+    let is_float = matches!(
+        type_,
+        NumericalType::Float32 | NumericalType::Float64
+    );
+
+    match instr {
+        Instr::Addition
+        | Instr::Subtraction
+        | Instr::Multiplication => true,
+        Instr::FloatDivision => is_float,
+        // Division, remainder and every bitwise operation
+        _ => !is_float,
+    }
+}
+
+/// Parses a conversion operation, such as `i32.wrap_i64` or
+/// `f64.convert_i32_s`.
+///
+/// The mnemonic starts with the type converted to and names
+/// the type of the operand after the operation.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{ConversionInstruction, ConversionOperation, NumericalType};
+/// use water::parser::{parse_conversion, parse_opcode};
+///
+/// assert_eq!(
+///     parse_conversion("i32.wrap_i64"),
+///     Ok(("", ConversionOperation {
+///         type_: NumericalType::Int32,
+///         instr: ConversionInstruction::Wrap,
+///         source: NumericalType::Int64,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_conversion("f64.convert_i32_s"),
+///     Ok(("", ConversionOperation {
+///         type_: NumericalType::Float64,
+///         instr: ConversionInstruction::SignedConvert,
+///         source: NumericalType::Int32,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_conversion("i64.extend_i32_u"),
+///     Ok(("", ConversionOperation {
+///         type_: NumericalType::Int64,
+///         instr: ConversionInstruction::UnsignedExtend,
+///         source: NumericalType::Int32,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_conversion("i32.trunc_f32_s"),
+///     Ok(("", ConversionOperation {
+///         type_: NumericalType::Int32,
+///         instr: ConversionInstruction::SignedTruncate,
+///         source: NumericalType::Float32,
+///     }))
+/// );
+///
+/// // `const` is not mistaken for a conversion
+/// assert!(parse_opcode("i32.const 1").is_ok());
+///
+/// // Wrong: these conversions don't exist
+/// assert!(parse_conversion("i64.wrap_i32").is_err());
+/// assert!(parse_conversion("f32.convert_f64_s").is_err());
+/// assert!(parse_conversion("i32.extend_i32_s").is_err());
+/// // Wrong: missing or superfluous signedness
+/// assert!(parse_conversion("i32.trunc_f32").is_err());
+/// assert!(parse_conversion("f64.promote_f32_s").is_err());
+/// ```
+pub fn parse_conversion(
+    input: &str,
+) -> IResult<'_, ConversionOperation> {
+    use ConversionInstruction as Instr;
+
+    // Operations which come in a signed and an unsigned form,
+    // such as `trunc_f32_s` and `trunc_f32_u`
+    let signed = |name, signed, unsigned| {
+        preceded(
+            tag(name),
+            preceded(char('_'), parse_numerical_type),
+        )
+        .and(alt((
+            value(signed, tag("_s")),
+            value(unsigned, tag("_u")),
+        )))
+        .map(|(source, instr)| (instr, source))
+    };
+    let unsigned = |name, instr| {
+        preceded(
+            tag(name),
+            preceded(char('_'), parse_numerical_type),
+        )
+        .map(move |source| (instr, source))
+    };
+
+    let parse_instr = alt((
+        unsigned("wrap", Instr::Wrap),
+        signed(
+            "extend",
+            Instr::SignedExtend,
+            Instr::UnsignedExtend,
+        ),
+        signed(
+            "trunc",
+            Instr::SignedTruncate,
+            Instr::UnsignedTruncate,
+        ),
+        signed(
+            "convert",
+            Instr::SignedConvert,
+            Instr::UnsignedConvert,
+        ),
+        unsigned("demote", Instr::Demote),
+        unsigned("promote", Instr::Promote),
+    ));
+
+    let (rest, (type_, (instr, source))) = context(
+        "conversion",
+        verify(
+            terminated(
+                separated_pair(
+                    parse_numerical_type,
+                    char('.'),
+                    parse_instr,
+                ),
+                keyword_end,
+            ),
+            |(type_, (instr, source))| {
+                is_valid_conversion(type_, instr, source)
+            },
+        ),
+    )(input)?;
+
+    let operation = ConversionOperation {
+        type_,
+        instr,
+        source,
+    };
+
+    Ok((rest, operation))
+}
+
+/// Whether the given conversion exists between the given types.
+fn is_valid_conversion(
+    type_: &NumericalType,
+    instr: &ConversionInstruction,
+    source: &NumericalType,
+) -> bool {
+    use ConversionInstruction as Instr;
+    use NumericalType::{Float32, Float64, Int32, Int64};
+
+    let is_float = |type_: &NumericalType| {
+        matches!(type_, Float32 | Float64)
+    };
+
+    match instr {
+        Instr::Wrap => (type_, source) == (&Int32, &Int64),
+        Instr::SignedExtend | Instr::UnsignedExtend => {
+            (type_, source) == (&Int64, &Int32)
+        }
+        Instr::SignedTruncate | Instr::UnsignedTruncate => {
+            !is_float(type_) && is_float(source)
+        }
+        Instr::SignedConvert | Instr::UnsignedConvert => {
+            is_float(type_) && !is_float(source)
+        }
+        Instr::Demote => (type_, source) == (&Float32, &Float64),
+        Instr::Promote => {
+            (type_, source) == (&Float64, &Float32)
+        }
+    }
+}
+
+/// Parses a memory access, such as `i32.load` or
+/// `i64.store8 offset=4 align=1`, alongside its immediates.
+///
+/// The offset defaults to 0 and the alignment to the width of
+/// the access.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{MemoryInstruction, MemoryOperation, NumericalType};
+/// use water::parser::parse_memory_operation;
+///
+/// assert_eq!(
+///     parse_memory_operation("i32.load"),
+///     Ok(("", MemoryOperation {
+///         type_: NumericalType::Int32,
+///         instr: MemoryInstruction::Load,
+///         offset: 0,
+///         align: 4,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_memory_operation("i32.load offset=4 align=2"),
+///     Ok(("", MemoryOperation {
+///         type_: NumericalType::Int32,
+///         instr: MemoryInstruction::Load,
+///         offset: 4,
+///         align: 2,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_memory_operation("i64.load16_s align=1"),
+///     Ok(("", MemoryOperation {
+///         type_: NumericalType::Int64,
+///         instr: MemoryInstruction::SignedLoad { bits: 16 },
+///         offset: 0,
+///         align: 1,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_memory_operation("f64.store offset=16"),
+///     Ok(("", MemoryOperation {
+///         type_: NumericalType::Float64,
+///         instr: MemoryInstruction::Store,
+///         offset: 16,
+///         align: 8,
+///     }))
+/// );
+/// assert_eq!(
+///     parse_memory_operation("i64.store32").map(|(_, operation)| operation.instr),
+///     Ok(MemoryInstruction::NarrowStore { bits: 32 })
+/// );
+///
+/// // Wrong: floats have no narrow accesses
+/// assert!(parse_memory_operation("f32.load8_s").is_err());
+/// // Wrong: not narrower than the type
+/// assert!(parse_memory_operation("i32.load32_u").is_err());
+/// // Wrong: narrow loads need their signedness
+/// assert!(parse_memory_operation("i32.load8").is_err());
+/// // The offset comes before the alignment, so this one is left over
+/// let (rest, _) = parse_memory_operation("i32.load align=4 offset=4").unwrap();
+/// assert_eq!(rest, " offset=4");
+/// // Wrong: alignments are powers of two
+/// assert!(parse_memory_operation("i32.load align=3").is_err());
+/// assert!(parse_memory_operation("i32.load align=0").is_err());
+/// ```
+pub fn parse_memory_operation(
+    input: &str,
+) -> IResult<'_, MemoryOperation> {
+    use MemoryInstruction as Instr;
+
+    let bits = || {
+        alt((
+            value(8, tag("8")),
+            value(16, tag("16")),
+            value(32, tag("32")),
+        ))
+    };
+
+    let parse_instr = alt((
+        preceded(
+            tag("load"),
+            opt(bits().and(alt((
+                value(true, tag("_s")),
+                value(false, tag("_u")),
+            )))),
+        )
+        .map(|narrow| match narrow {
+            Some((bits, true)) => Instr::SignedLoad { bits },
+            Some((bits, false)) => Instr::UnsignedLoad { bits },
+            None => Instr::Load,
+        }),
+        preceded(tag("store"), opt(bits())).map(|bits| {
+            bits.map_or(Instr::Store, |bits| {
+                Instr::NarrowStore { bits }
+            })
+        }),
+    ));
+
+    let (rest, (type_, instr)) = context(
+        "memory access",
+        verify(
+            terminated(
+                separated_pair(
+                    parse_numerical_type,
+                    char('.'),
+                    parse_instr,
+                ),
+                keyword_end,
+            ),
+            |(type_, instr)| {
+                is_valid_memory_access(type_, instr)
+            },
+        ),
+    )(input)?;
+
+    let immediate = |name| {
+        preceded(
+            ws,
+            preceded(
+                tag(name),
+                cut(context(
+                    name,
+                    nom::character::complete::u32,
+                )),
+            ),
+        )
+    };
+
+    let (rest, offset) = opt(immediate("offset="))(rest)?;
+    let align_start = rest;
+    let (rest, align) = opt(immediate("align="))(rest)?;
+
+    let mut operation = MemoryOperation {
+        type_,
+        instr,
+        offset: offset.unwrap_or(0),
+        align: 0,
+    };
+    operation.align = align.unwrap_or(operation.width());
+
+    if !operation.align.is_power_of_two() {
+        return failure(
+            align_start,
+            "alignment must be a power of two",
+        );
+    }
+
+    Ok((rest, operation))
+}
+
+/// Whether the given memory access exists for the given type.
+fn is_valid_memory_access(
+    type_: &NumericalType,
+    instr: &MemoryInstruction,
+) -> bool {
+    use MemoryInstruction as Instr;
+
+    match instr {
+        Instr::Load | Instr::Store => true,
+        Instr::SignedLoad { bits }
+        | Instr::UnsignedLoad { bits }
+        | Instr::NarrowStore { bits } => match type_ {
+            NumericalType::Int32 => *bits < 32,
+            NumericalType::Int64 => *bits < 64,
+            NumericalType::Float32 | NumericalType::Float64 => {
+                false
+            }
+        },
+    }
+}
+
 /// Parses a `call` instruction alongside its index.
 ///
 /// Does not eat leading whitespace.
@@ -143,35 +995,193 @@ pub fn parse_const(input: &str) -> IResult<NumericalValue> {
 /// assert!(parse_instruction("(call 5").is_err());
 /// assert_eq!(parse_call("call $func"), Ok(("", Index::Identifier("func".into()))));
 /// ```
-pub fn parse_call(input: &str) -> IResult<Index> {
+pub fn parse_call(input: &str) -> IResult<'_, Index> {
     let (rest, _) = tag("call")(input)?;
 
     preceded(
-        multispace0,
+        ws,
         context("numerical index or identifier", parse_index),
     )(rest)
 }
 
+/// Parses a `call_indirect` instruction alongside its type
+/// index and table index, in that order. The table index comes
+/// first in the text and defaults to 0 when omitted.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Index, Opcode};
+/// use water::parser::{parse_call_indirect, parse_instruction};
+///
+/// assert_eq!(
+///     parse_call_indirect("call_indirect (type $sig)"),
+///     Ok(("", (Index::Identifier("sig".into()), Index::Numerical(0))))
+/// );
+/// assert_eq!(
+///     parse_call_indirect("call_indirect $table (type 0)"),
+///     Ok(("", (Index::Numerical(0), Index::Identifier("table".into()))))
+/// );
+///
+/// let (_, instruction) = parse_instruction(
+///     "(call_indirect (type 0) (i32.const 1) (local.get $pointer))"
+/// ).unwrap();
+/// assert_eq!(instruction.opcode, Opcode::CallIndirect {
+///     type_index: Index::Numerical(0),
+///     table_index: Index::Numerical(0),
+/// });
+/// assert_eq!(instruction.arguments.len(), 2);
+///
+/// // Wrong: the type is required
+/// assert!(parse_call_indirect("call_indirect").is_err());
+/// assert!(parse_call_indirect("call_indirect (type)").is_err());
+/// ```
+pub fn parse_call_indirect(
+    input: &str,
+) -> IResult<'_, (Index, Index)> {
+    let (rest, _) = keyword("call_indirect")(input)?;
+    let (rest, table_index) =
+        opt(preceded(ws, parse_index))(rest)?;
+    let (rest, type_index) = preceded(
+        ws,
+        cut(context(
+            "type use",
+            parse_parenthesis_enclosed(preceded(
+                keyword("type"),
+                preceded(
+                    ws,
+                    context(
+                        "numerical index or identifier",
+                        parse_index,
+                    ),
+                ),
+            )),
+        )),
+    )(rest)?;
+
+    Ok((
+        rest,
+        (
+            type_index,
+            table_index.unwrap_or(Index::Numerical(0)),
+        ),
+    ))
+}
+
+/// Parses a `br_if` instruction alongside its label.
+///
+/// Does not eat leading whitespace.
+///
+/// In the folded form, the label immediate comes first and is
+/// followed by the condition operand.
+///
+/// ```
+/// use water::ast::{Index, Instruction, Opcode, VariableInstruction, VariableOperation, ScopeKind};
+/// use water::parser::{parse_br_if, parse_instruction};
+///
+/// assert_eq!(parse_br_if("br_if 0"), Ok(("", Index::Numerical(0))));
+/// assert_eq!(parse_br_if("br_if $loop"), Ok(("", Index::Identifier("loop".into()))));
+///
+/// let condition = Instruction {
+///     opcode: Opcode::VariableInstruction(VariableOperation {
+///         scope: ScopeKind::Local,
+///         instruction: VariableInstruction::Get,
+///         index: Index::Identifier("x".into()),
+///     }),
+///     arguments: vec![],
+/// };
+///
+/// assert_eq!(
+///     parse_instruction("(br_if 0 (local.get $x))"),
+///     Ok(("", Instruction {
+///         opcode: Opcode::BrIf(Index::Numerical(0)),
+///         arguments: vec![condition],
+///     }))
+/// );
+///
+/// assert_eq!(
+///     parse_instruction("br_if 0"),
+///     Ok(("", Instruction {
+///         opcode: Opcode::BrIf(Index::Numerical(0)),
+///         arguments: vec![],
+///     }))
+/// );
+/// ```
+pub fn parse_br_if(input: &str) -> IResult<'_, Index> {
+    let (rest, _) = tag("br_if")(input)?;
+
+    preceded(
+        ws,
+        context("label index or identifier", parse_index),
+    )(rest)
+}
+
+/// Parses a `br` instruction alongside its label.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Index, Instruction, Opcode};
+/// use water::parser::{parse_br, parse_instruction};
+///
+/// assert_eq!(parse_br("br 0"), Ok(("", Index::Numerical(0))));
+/// assert_eq!(parse_br("br $exit"), Ok(("", Index::Identifier("exit".into()))));
+///
+/// // Not to be confused with `br_if`
+/// assert!(parse_br("br_if 0").is_err());
+///
+/// let returned = Instruction { opcode: Opcode::Return, arguments: vec![] };
+///
+/// assert_eq!(parse_instruction("return"), Ok(("", returned.clone())));
+/// assert_eq!(parse_instruction("(return)"), Ok(("", returned)));
+/// assert!(parse_instruction("returned").is_err());
+/// ```
+pub fn parse_br(input: &str) -> IResult<'_, Index> {
+    let (rest, _) = keyword("br")(input)?;
+
+    preceded(
+        ws,
+        context("label index or identifier", parse_index),
+    )(rest)
+}
+
 /// Parses an instruction for direct variable access.
 ///
 /// Does not eat leading whitespace.
 ///
 /// ```
 /// use water::ast::{ScopeKind, VariableInstruction, VariableOperation, Opcode, Index};
-/// use water::parser::parse_variable_instruction;
+/// use water::parser::{parse_opcode, parse_variable_instruction};
+///
+/// let set_idx = VariableOperation {
+///     scope: ScopeKind::Local,
+///     instruction: VariableInstruction::Set,
+///     index: Index::Identifier("idx".into()),
+/// };
+///
+/// assert_eq!(parse_variable_instruction("local.set $idx"), Ok(("", set_idx.clone())));
 ///
+/// // As an opcode, the operation is wrapped in `Opcode::VariableInstruction`
 /// assert_eq!(
-///     parse_variable_instruction("local.set $idx"),
+///     parse_opcode("local.set $idx"),
+///     Ok(("", Opcode::VariableInstruction(set_idx)))
+/// );
+///
+/// assert_eq!(
+///     parse_variable_instruction("global.get $g"),
 ///     Ok(("", VariableOperation {
-///         scope: ScopeKind::Local,
-///         instruction: VariableInstruction::Set,
-///         index: Index::Identifier("idx".into()),
+///         scope: ScopeKind::Global,
+///         instruction: VariableInstruction::Get,
+///         index: Index::Identifier("g".into()),
 ///     }))
 /// );
+///
+/// // Wrong: globals can't be teed
+/// assert!(parse_variable_instruction("global.tee $g").is_err());
 /// ```
 pub fn parse_variable_instruction(
     input: &str,
-) -> IResult<VariableOperation> {
+) -> IResult<'_, VariableOperation> {
     let (rest, scope) = alt((
         value(ScopeKind::Global, tag("global")),
         value(ScopeKind::Local, tag("local")),
@@ -191,8 +1201,7 @@ pub fn parse_variable_instruction(
         }
     };
 
-    let (rest, index) =
-        preceded(multispace0, parse_index)(rest)?;
+    let (rest, index) = preceded(ws, parse_index)(rest)?;
 
     let operation = VariableOperation {
         scope,
@@ -203,8 +1212,268 @@ pub fn parse_variable_instruction(
     Ok((rest, operation))
 }
 
+/// Parses a flat `block`, which is closed by `end`.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Block, Constant, Instruction, NumericalType, NumericalValue, Opcode, Type};
+/// use water::parser::{parse_block, parse_instruction};
+///
+/// let empty = Block { label: None, block_type: None, body: vec![] };
+///
+/// assert_eq!(parse_block("block end"), Ok(("", empty.clone())));
+/// // An empty `result` is the same as none
+/// assert_eq!(parse_block("block (result) end"), Ok(("", empty.clone())));
+/// assert_eq!(
+///     parse_instruction("(block)"),
+///     Ok(("", Instruction { opcode: Opcode::Block(empty), arguments: vec![] }))
+/// );
+///
+/// let one = Instruction {
+///     opcode: Opcode::Constant(Constant { value: NumericalValue::Int32(1) }),
+///     arguments: vec![],
+/// };
+/// let labelled = Block {
+///     label: Some("b".into()),
+///     block_type: Some(Type::Numerical(NumericalType::Int32)),
+///     body: vec![one],
+/// };
+///
+/// assert_eq!(
+///     parse_block("block $b (result i32) i32.const 1 end $b"),
+///     Ok(("", labelled.clone()))
+/// );
+/// assert_eq!(
+///     parse_instruction("(block $b (result i32) (i32.const 1))"),
+///     Ok(("", Instruction { opcode: Opcode::Block(labelled), arguments: vec![] }))
+/// );
+///
+/// // Wrong: never ends
+/// assert!(parse_block("block i32.const 1").is_err());
+/// // Wrong: `end` must repeat the label of the block
+/// assert!(parse_block("block $a end $b").is_err());
+/// // Wrong: more than one result needs a type index
+/// assert!(parse_block("block (result i32 i32) end").is_err());
+/// ```
+pub fn parse_block(input: &str) -> IResult<'_, Block> {
+    parse_flat_block("block")(input)
+}
+
+/// Parses a flat `loop`, which is closed by `end`.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Block, Index, Instruction, Opcode};
+/// use water::parser::{parse_instruction, parse_loop};
+///
+/// let branch = Instruction { opcode: Opcode::BrIf(Index::Numerical(0)), arguments: vec![] };
+/// let looped = Block { label: None, block_type: None, body: vec![branch] };
+///
+/// assert_eq!(parse_loop("loop br_if 0 end"), Ok(("", looped.clone())));
+/// assert_eq!(
+///     parse_instruction("(loop br_if 0)"),
+///     Ok(("", Instruction { opcode: Opcode::Loop(looped), arguments: vec![] }))
+/// );
+/// ```
+pub fn parse_loop(input: &str) -> IResult<'_, Block> {
+    parse_flat_block("loop")(input)
+}
+
+/// Parses a flat `if`, whose optional `else` arm and the block
+/// itself are closed by `end`.
+///
+/// The folded form, where the condition is given as an argument
+/// and the arms are wrapped in `(then ...)` and `(else ...)`, is
+/// handled by [`parse_instruction`].
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Constant, IfBlock, Index, Instruction, NumericalType, NumericalValue, Opcode, ScopeKind, Type, VariableInstruction, VariableOperation};
+/// use water::parser::{parse_if, parse_instruction};
+///
+/// let constant = |value| Instruction {
+///     opcode: Opcode::Constant(Constant { value: NumericalValue::Int32(value) }),
+///     arguments: vec![],
+/// };
+/// let if_else = IfBlock {
+///     label: None,
+///     block_type: Some(Type::Numerical(NumericalType::Int32)),
+///     then: vec![constant(1)],
+///     else_: vec![constant(2)],
+/// };
+///
+/// assert_eq!(
+///     parse_if("if (result i32) i32.const 1 else i32.const 2 end"),
+///     Ok(("", if_else.clone()))
+/// );
+///
+/// let condition = Instruction {
+///     opcode: Opcode::VariableInstruction(VariableOperation {
+///         scope: ScopeKind::Local,
+///         instruction: VariableInstruction::Get,
+///         index: Index::Identifier("c".into()),
+///     }),
+///     arguments: vec![],
+/// };
+///
+/// assert_eq!(
+///     parse_instruction(
+///         "(if (result i32) (local.get $c) (then (i32.const 1)) (else (i32.const 2)))"
+///     ),
+///     Ok(("", Instruction {
+///         opcode: Opcode::If(if_else),
+///         arguments: vec![condition],
+///     }))
+/// );
+///
+/// // The condition comes after the block type and before the
+/// // arms, and only it lands in the arguments
+/// let (_, folded) = parse_instruction(
+///     "(if (result i32) (i32.const 1) (then (i32.const 2)) (else (i32.const 3)))"
+/// ).unwrap();
+/// assert_eq!(folded.arguments, vec![constant(1)]);
+/// let Opcode::If(arms) = folded.opcode else { unreachable!() };
+/// assert_eq!((arms.then, arms.else_), (vec![constant(2)], vec![constant(3)]));
+///
+/// // The `else` arm is optional
+/// let (_, without_else) = parse_if("if $l i32.const 1 end $l").unwrap();
+/// assert_eq!(without_else.then, vec![constant(1)]);
+/// assert!(without_else.else_.is_empty());
+///
+/// // Wrong: a folded `if` needs a `then` arm
+/// assert!(parse_instruction("(if (local.get $c))").is_err());
+/// // Wrong: the block type goes before the condition
+/// assert!(parse_instruction("(if (local.get $c) (result i32) (then (i32.const 1)))").is_err());
+/// // Wrong: `else` must repeat the label of the block
+/// assert!(parse_if("if $a else $b end").is_err());
+/// ```
+pub fn parse_if(input: &str) -> IResult<'_, IfBlock> {
+    let (rest, (label, block_type)) =
+        preceded(keyword("if"), parse_block_header)(input)?;
+    let (rest, then) = parse_instructions(rest)?;
+    let (rest, else_) = opt(preceded(
+        |input| parse_block_delimiter(input, "else", &label),
+        parse_instructions,
+    ))(rest)?;
+    let (rest, _) = context(
+        "end",
+        cut(|input| parse_block_delimiter(input, "end", &label)),
+    )(rest)?;
+
+    let if_block = IfBlock {
+        label,
+        block_type,
+        then,
+        else_: else_.unwrap_or_default(),
+    };
+
+    Ok((rest, if_block))
+}
+
+/// Builds a parser for a flat block opened by `name`, such as
+/// `block` or `loop`.
+fn parse_flat_block<'a>(
+    name: &'static str,
+) -> impl FnMut(&'a str) -> IResult<'a, Block> {
+    move |input| {
+        let (rest, (label, block_type)) =
+            preceded(keyword(name), parse_block_header)(input)?;
+        let (rest, body) = parse_instructions(rest)?;
+        let (rest, _) = context(
+            "end",
+            cut(|input| {
+                parse_block_delimiter(input, "end", &label)
+            }),
+        )(rest)?;
+
+        let block = Block {
+            label,
+            block_type,
+            body,
+        };
+
+        Ok((rest, block))
+    }
+}
+
+/// Parses the optional label and block type that follow
+/// `block`, `loop` and `if`.
+fn parse_block_header(
+    input: &str,
+) -> IResult<'_, (Option<SmallString>, Option<Type>)> {
+    let (rest, label) =
+        opt(preceded(ws, parse_identifier))(input)?;
+    let (rest, results) = opt(parse_result)(rest)?;
+
+    let block_type = match results {
+        None => None,
+        Some(mut results) if results.len() <= 1 => results.pop(),
+        Some(_) => {
+            return failure(
+                rest,
+                "blocks may only declare a single result",
+            )
+        }
+    };
+
+    Ok((rest, (label, block_type)))
+}
+
+/// Parses `else` or `end`, which may be followed by the label of
+/// the block they belong to.
+fn parse_block_delimiter<'a>(
+    input: &'a str,
+    name: &'static str,
+    label: &Option<SmallString>,
+) -> IResult<'a, ()> {
+    let (rest, _) = preceded(ws, keyword(name))(input)?;
+    let (rest, repeated) =
+        opt(preceded(ws, parse_identifier))(rest)?;
+
+    match repeated {
+        Some(repeated) if label.as_ref() != Some(&repeated) => {
+            failure(rest, "label does not match the block's")
+        }
+        _ => Ok((rest, ())),
+    }
+}
+
+/// Parses a sequence of plain or folded instructions, such as
+/// the body of a block.
+fn parse_instructions(
+    input: &str,
+) -> IResult<'_, Vec<Instruction>> {
+    many0(preceded(ws, parse_instruction))(input)
+}
+
 /// Parses the `unreachable` instruction
-pub fn parse_unreachable(input: &str) -> IResult<Unreachable> {
+///
+/// `unreachable` accepts any number of folded operands, which
+/// end up in the instruction's `arguments`.
+///
+/// ```
+/// use water::ast::{Constant, Instruction, NumericalValue, Opcode, Unreachable};
+/// use water::parser::parse_instruction;
+///
+/// let constant = |value| Instruction {
+///     opcode: Opcode::Constant(Constant { value: NumericalValue::Int32(value) }),
+///     arguments: vec![],
+/// };
+///
+/// assert_eq!(
+///     parse_instruction("(unreachable (i32.const 1) (i32.const 2))"),
+///     Ok(("", Instruction {
+///         opcode: Opcode::Unreachable(Unreachable),
+///         arguments: vec![constant(1), constant(2)],
+///     }))
+/// );
+/// ```
+pub fn parse_unreachable(
+    input: &str,
+) -> IResult<'_, Unreachable> {
     let (rest, _) = tag("unreachable")(input)?;
 
     Ok((rest, Unreachable))
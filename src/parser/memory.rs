@@ -0,0 +1,74 @@
+use nom::{
+    character::complete::u32 as parse_u32,
+    combinator::{cut, opt},
+    error::context,
+    multi::many0,
+    sequence::preceded,
+};
+
+use super::IResult;
+use crate::{
+    ast::Memory,
+    parser::{
+        parse_export,
+        utils::{
+            keyword, parse_identifier,
+            parse_parenthesis_enclosed, ws,
+        },
+    },
+};
+
+/// Parses a memory definition, made of an optional identifier,
+/// its inline exports and the minimum and optional maximum
+/// amount of pages.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::Memory;
+/// use water::parser::parse_memory;
+///
+/// assert_eq!(
+///     parse_memory("(memory 1)"),
+///     Ok(("", Memory { identifier: None, exports: vec![], min: 1, max: None }))
+/// );
+/// assert_eq!(
+///     parse_memory("(memory $heap 1 10)"),
+///     Ok(("", Memory { identifier: Some("heap".into()), exports: vec![], min: 1, max: Some(10) }))
+/// );
+/// assert_eq!(
+///     parse_memory(r#"(memory (export "mem") 1)"#),
+///     Ok(("", Memory { identifier: None, exports: vec!["mem".into()], min: 1, max: None }))
+/// );
+///
+/// // Wrong: missing minimum
+/// assert!(parse_memory("(memory)").is_err());
+/// assert!(parse_memory("(memory $heap)").is_err());
+/// // Wrong: page counts must fit in an u32
+/// assert!(parse_memory("(memory 4294967296)").is_err());
+/// ```
+pub fn parse_memory(input: &str) -> IResult<'_, Memory> {
+    fn inner(input: &str) -> IResult<'_, Memory> {
+        let (rest, _) = keyword("memory")(input)?;
+        let (rest, identifier) =
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let (rest, exports) =
+            many0(preceded(ws, parse_export))(rest)?;
+        let (rest, min) = preceded(
+            ws,
+            cut(context("minimum page count", parse_u32)),
+        )(rest)?;
+        let (rest, max) = opt(preceded(ws, parse_u32))(rest)?;
+
+        let memory = Memory {
+            identifier,
+            exports,
+            min,
+            max,
+        };
+
+        Ok((rest, memory))
+    }
+
+    parse_parenthesis_enclosed(context("memory", inner))(input)
+}
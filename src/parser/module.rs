@@ -1,13 +1,43 @@
 use nom::{
-    bytes::complete::tag, character::complete::multispace0,
-    error::context, sequence::preceded,
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{cut, value},
+    error::context,
+    multi::many0,
+    sequence::{preceded, terminated},
+    Parser,
 };
 
 use super::IResult;
 use crate::{
-    ast::Module, parser::utils::parse_parenthesis_enclosed,
+    ast::{
+        Export, ExportKind, Function, FunctionImport, Global,
+        Index, Memory, Module, RecGroup, TypeDefinition,
+    },
+    parser::{
+        parse_function, parse_function_import, parse_global,
+        parse_inline_function_import, parse_memory,
+        parse_rec_group, parse_type_definition,
+        utils::{
+            keyword, parse_index, parse_parenthesis_enclosed,
+            parse_string, ws,
+        },
+    },
+    small_string::SmallString,
 };
 
+/// A single field within a module, such as a function
+/// definition.
+enum ModuleField {
+    Function(Function),
+    Import(FunctionImport),
+    Export(Export),
+    Global(Global),
+    Type(TypeDefinition),
+    RecGroup(Vec<TypeDefinition>),
+    Memory(Memory),
+}
+
 /// Parses a WebAssembly Text Format module.
 ///
 /// Eats leading whitespace before and after the first
@@ -18,21 +48,239 @@ use crate::{
 ///
 /// assert!(parse_module("(module)").is_ok());
 /// assert!(parse_module("\n  (module)").is_ok());
+/// assert!(parse_module("(module ;; hi\n)").is_ok());
+/// assert!(parse_module("(module (; hi ;) (func (; no name ;) (param i32)))").is_ok());
+///
+/// let (_, module) = parse_module(
+///     r#"(module (func $a) (import "e" "f" (func $g (param i32))))"#
+/// ).unwrap();
+///
+/// assert_eq!(module.functions.len(), 1);
+/// assert_eq!(module.functions[0].identifier, Some("a".into()));
+/// assert_eq!(module.imports.len(), 1);
+/// assert_eq!(module.imports[0].signature.identifier, Some("g".into()));
+///
+/// let (_, module) = parse_module(
+///     r#"(module (func $f) (export "f" (func $f)) (export "g" (func 0)))"#
+/// ).unwrap();
+///
+/// assert_eq!(module.exports.len(), 2);
+///
+/// let (_, module) = parse_module(
+///     "(module (global $a (mut i32) (i32.const 0)) (func) (global i64 (i64.const 1)))"
+/// ).unwrap();
+///
+/// assert_eq!(module.globals.len(), 2);
+/// assert!(module.globals[0].mutable);
+///
+/// let (_, module) = parse_module(
+///     "(module (type $t (func (param i32))) (func) (type (func)))"
+/// ).unwrap();
+///
+/// assert_eq!(module.types.len(), 2);
+/// assert_eq!(module.types[0].identifier, Some("t".into()));
+///
+/// let (_, module) = parse_module(
+///     "(module (type (func)) (rec (type $a (func)) (type $b (func))))"
+/// ).unwrap();
+///
+/// assert_eq!(module.types.len(), 3);
+/// assert_eq!(module.types[2].identifier, Some("b".into()));
+/// assert_eq!(module.rec_groups[0].start, 1);
+/// assert_eq!(module.rec_groups[0].count, 2);
+///
+/// let (_, module) = parse_module("(module (memory 1 10))").unwrap();
+///
+/// assert_eq!(module.memories.len(), 1);
+/// assert_eq!(module.memories[0].max, Some(10));
 ///
 /// assert!(parse_module(" (   module").is_err());
 /// assert!(parse_module("module)").is_err());
 /// assert!(parse_module("(mod)").is_err());
 /// ```
-pub fn parse_module(input: &str) -> IResult<Module> {
-    fn inner(input: &str) -> IResult<Module> {
-        let (rest, _) =
-            preceded(multispace0, tag("module"))(input)?;
+pub fn parse_module(input: &str) -> IResult<'_, Module> {
+    fn inner(input: &str) -> IResult<'_, Module> {
+        let (rest, _) = preceded(ws, tag("module"))(input)?;
 
-        Ok((rest, Module {}))
+        parse_module_fields(rest)
     }
 
     preceded(
-        multispace0,
+        ws,
         parse_parenthesis_enclosed(context("module", inner)),
     )(input)
 }
+
+/// Parses a sequence of module fields that is not wrapped in
+/// `(module ...)`, which WAT treats as an implicit module.
+///
+/// Eats leading and trailing whitespace.
+///
+/// ```
+/// use water::parser::parse_module_fields;
+///
+/// let (rest, module) = parse_module_fields("(func $a)\n(func $b (param i32))\n").unwrap();
+///
+/// assert!(rest.is_empty());
+/// assert_eq!(module.functions.len(), 2);
+/// assert_eq!(module.functions[1].identifier, Some("b".into()));
+///
+/// // An empty input is an empty module
+/// assert_eq!(parse_module_fields(""), Ok(("", Default::default())));
+///
+/// // Comments may go anywhere whitespace can
+/// let (rest, module) = parse_module_fields(
+///     "(func $a) ;; the first function\n(; a (; nested ;) comment ;)\n(func $b)"
+/// ).unwrap();
+///
+/// assert!(rest.is_empty());
+/// assert_eq!(module.functions.len(), 2);
+/// ```
+pub fn parse_module_fields(input: &str) -> IResult<'_, Module> {
+    let (rest, fields) = terminated(
+        many0(preceded(ws, parse_module_field)),
+        ws,
+    )(input)?;
+
+    let mut module = Module::default();
+    for field in fields {
+        match field {
+            ModuleField::Function(function) => {
+                module.functions.push(function)
+            }
+            ModuleField::Import(import) => {
+                module.imports.push(import)
+            }
+            ModuleField::Export(export) => {
+                module.exports.push(export)
+            }
+            ModuleField::Global(global) => {
+                module.globals.push(global)
+            }
+            ModuleField::Type(definition) => {
+                module.types.push(definition)
+            }
+            ModuleField::RecGroup(definitions) => {
+                module.rec_groups.push(RecGroup {
+                    start: module.types.len() as u32,
+                    count: definitions.len() as u32,
+                });
+                module.types.extend(definitions);
+            }
+            ModuleField::Memory(memory) => {
+                module.memories.push(memory)
+            }
+        }
+    }
+
+    Ok((rest, module))
+}
+
+/// Parses any of the fields a module may contain.
+///
+/// Does not eat leading whitespace.
+fn parse_module_field(input: &str) -> IResult<'_, ModuleField> {
+    alt((
+        // Tried first, since the definition of a function
+        // can't tell an inline import apart from a syntax error
+        parse_inline_function_import.map(ModuleField::Import),
+        parse_function.map(ModuleField::Function),
+        parse_function_import.map(ModuleField::Import),
+        parse_module_export.map(ModuleField::Export),
+        parse_global.map(ModuleField::Global),
+        parse_type_definition.map(ModuleField::Type),
+        parse_rec_group.map(ModuleField::RecGroup),
+        parse_memory.map(ModuleField::Memory),
+    ))(input)
+}
+
+/// Parses an export declared at module level, such as
+/// `(export "add" (func $add))`.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{Export, ExportKind, Index};
+/// use water::parser::parse_module_export;
+///
+/// assert_eq!(
+///     parse_module_export(r#"(export "add" (func $add))"#),
+///     Ok(("", Export {
+///         name: "add".into(),
+///         kind: ExportKind::Function,
+///         index: Index::Identifier("add".into()),
+///     }))
+/// );
+/// assert_eq!(
+///     parse_module_export(r#"( export ""  ( func 3 ) )"#),
+///     Ok(("", Export {
+///         name: "".into(),
+///         kind: ExportKind::Function,
+///         index: Index::Numerical(3),
+///     }))
+/// );
+///
+/// // Wrong: the inline form has no target
+/// assert!(parse_module_export(r#"(export "add")"#).is_err());
+/// assert_eq!(
+///     parse_module_export(r#"(export "counter" (global $counter))"#),
+///     Ok(("", Export {
+///         name: "counter".into(),
+///         kind: ExportKind::Global,
+///         index: Index::Identifier("counter".into()),
+///     }))
+/// );
+/// assert_eq!(
+///     parse_module_export(r#"(export "mem" (memory 0))"#),
+///     Ok(("", Export {
+///         name: "mem".into(),
+///         kind: ExportKind::Memory,
+///         index: Index::Numerical(0),
+///     }))
+/// );
+/// // Wrong: tables can't be exported yet
+/// assert!(parse_module_export(r#"(export "t" (table 0))"#).is_err());
+/// // Wrong: missing index
+/// assert!(parse_module_export(r#"(export "f" (func))"#).is_err());
+/// ```
+pub fn parse_module_export(input: &str) -> IResult<'_, Export> {
+    fn inner(input: &str) -> IResult<'_, Export> {
+        let (rest, _) = keyword("export")(input)?;
+        let (rest, name) = preceded(ws, parse_string)(rest)?;
+        let (rest, (kind, index)) = preceded(
+            ws,
+            parse_parenthesis_enclosed(parse_export_target),
+        )(rest)?;
+
+        let export = Export {
+            name: SmallString::new(&name),
+            kind,
+            index,
+        };
+
+        Ok((rest, export))
+    }
+
+    parse_parenthesis_enclosed(context("export", inner))(input)
+}
+
+/// Parses what an export refers to, such as `func $f`, without
+/// its parenthesis.
+fn parse_export_target(
+    input: &str,
+) -> IResult<'_, (ExportKind, Index)> {
+    let (rest, kind) = alt((
+        value(ExportKind::Function, keyword("func")),
+        value(ExportKind::Memory, keyword("memory")),
+        value(ExportKind::Global, keyword("global")),
+    ))(input)?;
+    let (rest, index) = preceded(
+        ws,
+        cut(context(
+            "numerical index or identifier",
+            parse_index,
+        )),
+    )(rest)?;
+
+    Ok((rest, (kind, index)))
+}
@@ -0,0 +1,141 @@
+use nom::{
+    combinator::{cut, opt},
+    error::context,
+    multi::many0,
+    sequence::{preceded, terminated},
+};
+
+use super::{function::parse_function_contents, IResult};
+use crate::{
+    ast::{Parameter, Type, TypeDefinition},
+    parser::utils::{
+        failure, keyword, parse_identifier,
+        parse_parenthesis_enclosed, ws,
+    },
+};
+
+/// Parses a type definition, made of an optional identifier and
+/// the function type it names.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::ast::{NumericalType, Type};
+/// use water::parser::parse_type_definition;
+///
+/// let (_, binop) = parse_type_definition(
+///     "(type $binop (func (param i32) (param $b i32) (result i32)))"
+/// ).unwrap();
+///
+/// assert_eq!(binop.identifier, Some("binop".into()));
+/// assert_eq!(binop.parameters.len(), 2);
+/// assert_eq!(binop.parameters[1].identifier, Some("b".into()));
+/// assert_eq!(binop.results, [Type::Numerical(NumericalType::Int32)]);
+///
+/// let (_, producer) =
+///     parse_type_definition("(type (func (result i32)))").unwrap();
+///
+/// assert!(producer.parameters.is_empty());
+/// assert_eq!(producer.results, [Type::Numerical(NumericalType::Int32)]);
+///
+/// let (_, empty) = parse_type_definition("( type ( func ) )").unwrap();
+///
+/// assert_eq!(empty.identifier, None);
+/// assert!(empty.parameters.is_empty() && empty.results.is_empty());
+///
+/// // Wrong: missing function type
+/// assert!(parse_type_definition("(type $t)").is_err());
+/// // Wrong: function types have no locals
+/// assert!(parse_type_definition("(type (func (local i32)))").is_err());
+/// // Wrong: function types have no body
+/// assert!(parse_type_definition("(type (func (nop)))").is_err());
+/// ```
+pub fn parse_type_definition(
+    input: &str,
+) -> IResult<'_, TypeDefinition> {
+    fn inner(input: &str) -> IResult<'_, TypeDefinition> {
+        let (rest, _) = keyword("type")(input)?;
+        let (rest, identifier) =
+            opt(preceded(ws, parse_identifier))(rest)?;
+        let (rest, (parameters, results)) = preceded(
+            ws,
+            cut(context(
+                "function type",
+                parse_parenthesis_enclosed(parse_function_type),
+            )),
+        )(rest)?;
+
+        let definition = TypeDefinition {
+            identifier,
+            parameters,
+            results,
+        };
+
+        Ok((rest, definition))
+    }
+
+    parse_parenthesis_enclosed(context("type", inner))(input)
+}
+
+/// Parses a recursive type group of the GC proposal, returning
+/// the type definitions it holds.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::parser::parse_rec_group;
+///
+/// let (_, types) = parse_rec_group(
+///     "(rec (type $even (func (param i32))) (type $odd (func)))"
+/// ).unwrap();
+///
+/// assert_eq!(types.len(), 2);
+/// assert_eq!(types[0].identifier, Some("even".into()));
+/// assert_eq!(types[1].identifier, Some("odd".into()));
+///
+/// let (_, types) = parse_rec_group("( rec )").unwrap();
+///
+/// assert!(types.is_empty());
+///
+/// // Wrong: only type definitions may be grouped
+/// assert!(parse_rec_group("(rec (func))").is_err());
+/// ```
+pub fn parse_rec_group(
+    input: &str,
+) -> IResult<'_, Vec<TypeDefinition>> {
+    fn inner(input: &str) -> IResult<'_, Vec<TypeDefinition>> {
+        let (rest, _) = keyword("rec")(input)?;
+
+        terminated(
+            many0(preceded(ws, parse_type_definition)),
+            ws,
+        )(rest)
+    }
+
+    parse_parenthesis_enclosed(context("rec", inner))(input)
+}
+
+/// Parses the `func` form of a type definition, its parameters
+/// and its results.
+fn parse_function_type(
+    input: &str,
+) -> IResult<'_, (Vec<Parameter>, Vec<Type>)> {
+    let (rest, _) = keyword("func")(input)?;
+    let (rest, function) = parse_function_contents(rest)?;
+
+    if !function.local_variables.is_empty() {
+        return failure(
+            input,
+            "function type may not declare locals",
+        );
+    }
+
+    if !function.body.is_empty() {
+        return failure(
+            input,
+            "function type may not have a body",
+        );
+    }
+
+    Ok((rest, (function.parameters, function.results)))
+}
@@ -1,10 +1,15 @@
+use std::borrow::Cow;
+
 use nom::{
     branch::alt,
-    bytes::complete::{escaped, tag, take_while1},
-    character::complete::{char, multispace0, none_of},
-    combinator::{cut, value},
-    error::{context, VerboseError},
-    sequence::{delimited, preceded},
+    bytes::complete::{tag, take_while1},
+    character::complete::{char, one_of, satisfy},
+    combinator::{cut, not, opt, value},
+    error::{
+        context, ContextError, ErrorKind, ParseError,
+        VerboseError, VerboseErrorKind,
+    },
+    sequence::{delimited, preceded, terminated},
     Parser,
 };
 
@@ -14,11 +19,129 @@ use crate::{
     small_string::SmallString,
 };
 
-pub fn parse_string(input: &str) -> IResult<&str> {
-    let esc = escaped(none_of("\\\""), '\\', tag("\""));
-    let esc_or_empty = alt((esc, tag("")));
+/// Parses a quoted string, decoding its escape sequences.
+///
+/// Only allocates when the string actually contains escapes.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use water::parser::parse_string;
+///
+/// let (_, plain) = parse_string(r#""log""#).unwrap();
+/// assert!(matches!(plain, Cow::Borrowed("log")));
+///
+/// let (_, escaped) = parse_string(r#""a\"b""#).unwrap();
+/// assert!(matches!(escaped, Cow::Owned(ref s) if s == "a\"b"));
+///
+/// let decode = |input| parse_string(input).unwrap().1;
+/// assert_eq!(decode(r#""line\nbreak""#), "line\nbreak");
+/// assert_eq!(decode(r#""tab\there""#), "tab\there");
+/// assert_eq!(decode(r#""carriage\rreturn""#), "carriage\rreturn");
+/// assert_eq!(decode(r#""back\\slash""#), "back\\slash");
+/// assert_eq!(decode(r#""single\'quote""#), "single'quote");
+/// assert_eq!(decode(r#""hex\41\4a""#), "hexAJ");
+/// // Multi-byte UTF-8 sequences may be written byte by byte
+/// assert_eq!(decode(r#""\c3\a9t\c3\a9""#), "été");
+///
+/// // Wrong: unknown escape
+/// assert!(matches!(parse_string(r#""\q""#), Err(nom::Err::Failure(_))));
+/// // Wrong: a hex escape needs two digits
+/// assert!(matches!(parse_string(r#""\4""#), Err(nom::Err::Failure(_))));
+/// // Wrong: decodes to invalid UTF-8
+/// assert!(matches!(parse_string(r#""\ff""#), Err(nom::Err::Failure(_))));
+/// // Wrong: unterminated
+/// assert!(parse_string(r#""abc"#).is_err());
+/// ```
+pub fn parse_string(input: &str) -> IResult<'_, Cow<'_, str>> {
+    let (contents, _) = char('"')(input)?;
+
+    // Only holds something once the first escape is found
+    let mut decoded: Option<Vec<u8>> = None;
+    let mut chars = contents.char_indices();
+
+    while let Some((idx, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                let rest = &contents[idx + 1..];
+
+                let string = match decoded {
+                    None => Cow::Borrowed(&contents[..idx]),
+                    Some(bytes) => {
+                        match String::from_utf8(bytes) {
+                            Ok(string) => Cow::Owned(string),
+                            Err(_) => return failure(
+                                input,
+                                "strings must be valid UTF-8",
+                            ),
+                        }
+                    }
+                };
+
+                return Ok((rest, string));
+            }
+            '\\' => {
+                let bytes = decoded.get_or_insert_with(|| {
+                    contents.as_bytes()[..idx].to_vec()
+                });
+
+                let byte = match chars.next().map(|(_, ch)| ch) {
+                    Some('n') => b'\n',
+                    Some('t') => b'\t',
+                    Some('r') => b'\r',
+                    Some('"') => b'"',
+                    Some('\'') => b'\'',
+                    Some('\\') => b'\\',
+                    Some(high) if high.is_ascii_hexdigit() => {
+                        match chars.next().map(|(_, ch)| ch) {
+                            Some(low)
+                                if low.is_ascii_hexdigit() =>
+                            {
+                                hex_value(high) << 4
+                                    | hex_value(low)
+                            }
+                            _ => return failure(
+                                &contents[idx..],
+                                "hex escapes need two digits",
+                            ),
+                        }
+                    }
+                    _ => {
+                        return failure(
+                            &contents[idx..],
+                            "invalid escape sequence",
+                        )
+                    }
+                };
 
-    delimited(tag("\""), esc_or_empty, tag("\""))(input)
+                bytes.push(byte);
+            }
+            ch => {
+                if let Some(bytes) = &mut decoded {
+                    let mut buf = [0; 4];
+                    bytes.extend(
+                        ch.encode_utf8(&mut buf).as_bytes(),
+                    );
+                }
+            }
+        }
+    }
+
+    // Ran out of input before the closing quote
+    let end = &contents[contents.len()..];
+    let error = VerboseError::add_context(
+        end,
+        "closing quote",
+        VerboseError::from_error_kind(end, ErrorKind::Char),
+    );
+
+    Err(nom::Err::Error(error))
+}
+
+/// The value of an ASCII hex digit.
+fn hex_value(digit: char) -> u8 {
+    digit.to_digit(16).unwrap_or_default() as u8
 }
 
 /// Parses an identifier. WebAssembly Text Format identifiers
@@ -33,7 +156,9 @@ pub fn parse_string(input: &str) -> IResult<&str> {
 /// assert_eq!(parse_identifier("$idx"), Ok(("", SmallString::new("idx"))));
 /// assert_eq!(parse_identifier("$asd_aa? a"), Ok((" a", SmallString::new("asd_aa?"))));
 /// ```
-pub fn parse_identifier(input: &str) -> IResult<SmallString> {
+pub fn parse_identifier(
+    input: &str,
+) -> IResult<'_, SmallString> {
     let (rest, identifier) = context(
         "identifier",
         preceded(
@@ -48,7 +173,7 @@ pub fn parse_identifier(input: &str) -> IResult<SmallString> {
 /// Parses a WASM type.
 ///
 /// Does not eat leading whitespace.
-pub fn parse_type(input: &str) -> IResult<Type> {
+pub fn parse_type(input: &str) -> IResult<'_, Type> {
     context(
         "type",
         alt((parse_numerical_type.map(Type::Numerical),)),
@@ -60,7 +185,7 @@ pub fn parse_type(input: &str) -> IResult<Type> {
 /// Does not eat leading whitespace.
 pub fn parse_numerical_type(
     input: &str,
-) -> IResult<NumericalType> {
+) -> IResult<'_, NumericalType> {
     alt((
         value(NumericalType::Int32, tag("i32")),
         value(NumericalType::Int64, tag("i64")),
@@ -80,33 +205,515 @@ pub fn parse_numerical_type(
 ///
 /// assert_eq!(parse_index("$var"), Ok(("", Index::Identifier("var".into()))));
 /// assert_eq!(parse_index("5"), Ok(("", Index::Numerical(5))));
+///
+/// // Short identifiers are kept inline
+/// let (_, index) = parse_index("$var").unwrap();
+/// assert!(matches!(index, Index::Identifier(id) if !id.is_in_heap()));
+///
+/// // Indices are plain decimals, so exponents are not allowed
+/// assert!(parse_index("1e2").is_err());
+/// assert!(water::parser::parse_instruction("local.get 1e2").is_err());
 /// ```
-pub fn parse_index(input: &str) -> IResult<Index> {
+pub fn parse_index(input: &str) -> IResult<'_, Index> {
     alt((
-        parse_identifier
-            .map(SmallString::new)
-            .map(Index::Identifier),
-        nom::character::complete::i64.map(Index::Numerical),
+        parse_identifier.map(Index::Identifier),
+        parse_numerical_index
+            .map(|index| Index::Numerical(index.into())),
     ))(input)
 }
 
+/// Parses a numerical index, which must fit in an `u32`.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::parser::{parse_instruction, parse_numerical_index};
+///
+/// assert_eq!(parse_numerical_index("42"), Ok(("", 42)));
+///
+/// // Wrong: indices are never negative
+/// assert!(parse_numerical_index("-1").is_err());
+/// assert!(parse_instruction("call -1").is_err());
+/// assert!(parse_instruction("br_if -1").is_err());
+/// assert!(parse_instruction("local.get -1").is_err());
+///
+/// // Wrong: does not fit in 32 bits
+/// assert!(parse_numerical_index("4294967296").is_err());
+/// ```
+pub fn parse_numerical_index(input: &str) -> IResult<'_, u32> {
+    let not_negative = context(
+        "index must be non-negative",
+        cut(not(char('-'))),
+    );
+    // Ensures that `1e2` is rejected instead of parsed as `1`
+    let not_followed_by_exponent = context(
+        "index must be a plain decimal",
+        cut(not(one_of("eE."))),
+    );
+
+    delimited(
+        not_negative,
+        nom::character::complete::u32,
+        not_followed_by_exponent,
+    )(input)
+}
+
+/// Builds a parser for a hexadecimal integer literal, such as
+/// `0xff` or `-0x10`, that fits in a signed or unsigned integer
+/// of `bits` bits.
+///
+/// Like in the spec, the result is the two's-complement bit
+/// pattern of the literal interpreted as a signed integer, so
+/// values above the signed range wrap around into the negatives.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::parser::parse_hex_integer;
+///
+/// assert_eq!(parse_hex_integer(32)("0xff"), Ok(("", 255)));
+/// assert_eq!(parse_hex_integer(32)("0xFF"), Ok(("", 255)));
+/// assert_eq!(parse_hex_integer(32)("-0x10"), Ok(("", -16)));
+/// assert_eq!(parse_hex_integer(32)("0x7fffffff"), Ok(("", i32::MAX.into())));
+/// assert_eq!(parse_hex_integer(32)("0xffffffff"), Ok(("", -1)));
+/// assert_eq!(parse_hex_integer(32)("-0x80000000"), Ok(("", i32::MIN.into())));
+/// assert_eq!(parse_hex_integer(64)("0xffffffffffffffff"), Ok(("", -1)));
+/// assert_eq!(parse_hex_integer(64)("-0x8000000000000000"), Ok(("", i64::MIN)));
+///
+/// // Wrong: not hexadecimal
+/// assert!(parse_hex_integer(32)("255").is_err());
+/// // Wrong: out of range
+/// assert!(parse_hex_integer(32)("0x100000000").is_err());
+/// assert!(parse_hex_integer(32)("-0x80000001").is_err());
+/// ```
+pub fn parse_hex_integer<'a>(
+    bits: u32,
+) -> impl FnMut(&'a str) -> IResult<'a, i64> {
+    move |input| {
+        let (rest, negative) = opt(char('-'))
+            .map(|sign| sign.is_some())
+            .parse(input)?;
+        let (rest, _) = tag("0x")(rest)?;
+        let (rest, digits) = cut(context(
+            "hexadecimal digits",
+            take_while1(|ch: char| ch.is_ascii_hexdigit()),
+        ))(rest)?;
+
+        let out_of_range =
+            || failure(input, "integer constant out of range");
+
+        let Ok(magnitude) = u128::from_str_radix(digits, 16)
+        else {
+            return out_of_range();
+        };
+        let limit = if negative {
+            1 << (bits - 1)
+        } else {
+            (1 << bits) - 1
+        };
+        if magnitude > limit {
+            return out_of_range();
+        }
+
+        let value = if negative {
+            -(magnitude as i128)
+        } else if magnitude >= 1 << (bits - 1) {
+            // Wraps around into the signed range
+            magnitude as i128 - (1 << bits)
+        } else {
+            magnitude as i128
+        };
+
+        Ok((rest, value as i64))
+    }
+}
+
+/// Builds a parser for a hexadecimal float literal, such as
+/// `0x1.8p-2` or `-0xAp+3`, yielding the bit pattern of the
+/// nearest float of `bits` bits (32 or 64).
+///
+/// Both the fractional part and the binary exponent are
+/// optional. Values too large for the float type are a failure,
+/// while values too small for it round to zero.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::parser::parse_hex_float;
+///
+/// let f64_bits = |input| parse_hex_float(64)(input).map(|(_, bits)| bits);
+/// assert_eq!(f64_bits("0x1p0"), Ok(1.0f64.to_bits()));
+/// assert_eq!(f64_bits("-0x1.8p-2"), Ok(0xbfd8_0000_0000_0000));
+/// assert_eq!(f64_bits("0x0.1p4"), Ok(0x3ff0_0000_0000_0000));
+/// assert_eq!(f64_bits("+0xA.8P+1"), Ok(21.0f64.to_bits()));
+/// assert_eq!(f64_bits("0x10"), Ok(16.0f64.to_bits()));
+/// assert_eq!(f64_bits("-0x0p0"), Ok((-0.0f64).to_bits()));
+/// assert_eq!(f64_bits("0x1p-1074"), Ok(0x0000_0000_0000_0001));
+/// assert_eq!(f64_bits("0x1p-1080"), Ok(0));
+///
+/// let f32_bits = |input| parse_hex_float(32)(input).map(|(_, bits)| bits);
+/// assert_eq!(f32_bits("-0x1.8p-2"), Ok(0xbec0_0000));
+/// assert_eq!(f32_bits("0x1.fffffep127"), Ok(0x7f7f_ffff));
+/// // Ties round to even
+/// assert_eq!(f32_bits("0x1.000001p0"), Ok(0x3f80_0000));
+/// assert_eq!(f32_bits("0x1.000003p0"), Ok(0x3f80_0002));
+///
+/// // Wrong: not hexadecimal
+/// assert!(f64_bits("1.5").is_err());
+/// // Wrong: missing exponent digits
+/// assert!(f64_bits("0x1p").is_err());
+/// // Wrong: out of range
+/// assert!(f32_bits("0x1p128").is_err());
+/// ```
+pub fn parse_hex_float<'a>(
+    bits: u32,
+) -> impl FnMut(&'a str) -> IResult<'a, u64> {
+    let hex_digits =
+        || take_while1(|ch: char| ch.is_ascii_hexdigit());
+
+    move |input| {
+        let (rest, sign) = opt(one_of("+-"))(input)?;
+        let (rest, _) = tag("0x")(rest)?;
+        let (rest, integer) = cut(context(
+            "hexadecimal digits",
+            hex_digits(),
+        ))(rest)?;
+        let (rest, fraction) =
+            opt(preceded(char('.'), opt(hex_digits())))(rest)?;
+        let (rest, exponent) = opt(preceded(
+            one_of("pP"),
+            cut(context(
+                "binary exponent",
+                nom::character::complete::i64,
+            )),
+        ))(rest)?;
+
+        let fraction = fraction.flatten().unwrap_or_default();
+
+        let mut significand: u128 = 0;
+        // Clamped, since anything past it under or overflows
+        // all the same
+        let mut exponent =
+            exponent.unwrap_or(0).clamp(-100_000, 100_000);
+        let mut inexact = false;
+
+        for (position, digit) in
+            integer.chars().chain(fraction.chars()).enumerate()
+        {
+            let is_fraction = position >= integer.len();
+
+            if significand >> 120 == 0 {
+                significand = significand << 4
+                    | u128::from(hex_value(digit));
+
+                if is_fraction {
+                    exponent -= 4;
+                }
+            } else {
+                // Too many digits to be kept, but they still
+                // matter for rounding
+                inexact |= digit != '0';
+
+                if !is_fraction {
+                    exponent += 4;
+                }
+            }
+        }
+
+        if inexact {
+            // A sticky bit below every bit that can be kept
+            significand = significand << 1 | 1;
+            exponent -= 1;
+        }
+
+        let (mantissa_bits, exponent_bits) = match bits {
+            32 => (23, 8),
+            64 => (52, 11),
+            _ => unreachable!("floats have 32 or 64 bits"),
+        };
+
+        let Some(magnitude) = round_float(
+            significand,
+            exponent,
+            mantissa_bits,
+            exponent_bits,
+        ) else {
+            return failure(
+                input,
+                "float constant out of range",
+            );
+        };
+
+        let sign_bit =
+            u64::from(sign == Some('-')) << (bits - 1);
+
+        Ok((rest, sign_bit | magnitude))
+    }
+}
+
+/// Builds a parser for the special float values `inf`, `nan`
+/// and `nan:0x...`, optionally signed, yielding the bit pattern
+/// of a float of `bits` bits (32 or 64).
+///
+/// A bare `nan` is the canonical NaN, with only the top mantissa
+/// bit set, while `nan:0x...` sets the mantissa to the given
+/// payload, which must be non-zero and fit in it.
+///
+/// Does not eat leading whitespace.
+///
+/// ```
+/// use water::parser::parse_float_special;
+///
+/// let f64_bits = |input| parse_float_special(64)(input).map(|(_, bits)| bits);
+/// assert_eq!(f64_bits("inf"), Ok(f64::INFINITY.to_bits()));
+/// assert_eq!(f64_bits("-inf"), Ok(f64::NEG_INFINITY.to_bits()));
+/// assert_eq!(f64_bits("nan"), Ok(0x7ff8_0000_0000_0000));
+/// assert_eq!(f64_bits("-nan"), Ok(0xfff8_0000_0000_0000));
+/// assert_eq!(f64_bits("nan:0x4000000000000"), Ok(0x7ff4_0000_0000_0000));
+///
+/// let f32_bits = |input| parse_float_special(32)(input).map(|(_, bits)| bits);
+/// assert_eq!(f32_bits("+inf"), Ok(u64::from(f32::INFINITY.to_bits())));
+/// assert_eq!(f32_bits("nan"), Ok(0x7fc0_0000));
+/// assert_eq!(f32_bits("nan:0x200000"), Ok(0x7fa0_0000));
+///
+/// // Wrong: a zero payload would be an infinity
+/// assert!(f32_bits("nan:0x0").is_err());
+/// // Wrong: the payload doesn't fit in an f32's mantissa
+/// assert!(f32_bits("nan:0x800000").is_err());
+/// // Wrong: not a special value
+/// assert!(f64_bits("infinity").is_err());
+/// assert!(f64_bits("1.5").is_err());
+/// ```
+pub fn parse_float_special<'a>(
+    bits: u32,
+) -> impl FnMut(&'a str) -> IResult<'a, u64> {
+    let mantissa_bits = match bits {
+        32 => 23,
+        64 => 52,
+        _ => unreachable!("floats have 32 or 64 bits"),
+    };
+    let mantissa_mask = (1u64 << mantissa_bits) - 1;
+    // All exponent bits set
+    let infinity = !mantissa_mask & ((1 << (bits - 1)) - 1);
+    let canonical_nan = infinity | 1 << (mantissa_bits - 1);
+
+    move |input| {
+        let (rest, sign) = opt(one_of("+-"))(input)?;
+
+        let (rest, payload) = opt(tag("nan:0x"))(rest)?;
+
+        let (rest, magnitude) = if payload.is_some() {
+            let (rest, digits) = cut(context(
+                "hexadecimal digits",
+                terminated(
+                    take_while1(|ch: char| {
+                        ch.is_ascii_hexdigit()
+                    }),
+                    keyword_end,
+                ),
+            ))(rest)?;
+
+            match u64::from_str_radix(digits, 16) {
+                Ok(payload)
+                    if payload != 0
+                        && payload & !mantissa_mask == 0 =>
+                {
+                    (rest, infinity | payload)
+                }
+                _ => {
+                    return failure(
+                        input,
+                        "NaN payload out of range",
+                    )
+                }
+            }
+        } else {
+            alt((
+                value(infinity, keyword("inf")),
+                value(canonical_nan, keyword("nan")),
+            ))(rest)?
+        };
+
+        let sign_bit =
+            u64::from(sign == Some('-')) << (bits - 1);
+
+        Ok((rest, sign_bit | magnitude))
+    }
+}
+
+/// Rounds `significand * 2^exponent` to the nearest float with
+/// the given layout, ties to even, returning its bit pattern
+/// without the sign. `None` if it doesn't fit.
+fn round_float(
+    significand: u128,
+    exponent: i64,
+    mantissa_bits: u32,
+    exponent_bits: u32,
+) -> Option<u64> {
+    if significand == 0 {
+        return Some(0);
+    }
+
+    let bias = (1 << (exponent_bits - 1)) - 1;
+    let min_exponent = 1 - bias;
+
+    // The exponent of the most significant bit, and of the
+    // least significant bit the float can hold at that scale
+    let top =
+        exponent + 127 - i64::from(significand.leading_zeros());
+    let unit = top.max(min_exponent) - i64::from(mantissa_bits);
+
+    let mantissa = if exponent >= unit {
+        // No bits are lost, and at most `mantissa_bits + 1`
+        // bits are set
+        significand << (exponent - unit)
+    } else {
+        let shift = unit - exponent;
+        if shift > 128 {
+            0
+        } else {
+            let kept = significand
+                .checked_shr(shift as u32)
+                .unwrap_or(0);
+            let dropped = significand
+                - kept.checked_shl(shift as u32).unwrap_or(0);
+            let half = 1u128 << (shift - 1);
+
+            let round_up = dropped > half
+                || (dropped == half && kept & 1 == 1);
+
+            kept + u128::from(round_up)
+        }
+    };
+
+    // Past the smallest normal exponent, the implicit bit of
+    // the mantissa carries into the exponent field
+    let biased = (unit
+        - (min_exponent - i64::from(mantissa_bits)))
+        as u128;
+    let bits = (biased << mantissa_bits) + mantissa;
+
+    let infinity = ((1 << exponent_bits) - 1) << mantissa_bits;
+    (bits < infinity).then_some(bits as u64)
+}
+
+/// Eats whitespace, line comments and block comments, which
+/// WAT allows anywhere between tokens. Block comments may be
+/// nested.
+///
+/// Never fails, unless a block comment is left unterminated.
+///
+/// ```
+/// use water::parser::ws;
+///
+/// assert_eq!(ws("  \n\t(func)"), Ok(("(func)", ())));
+/// assert_eq!(ws(";; a line comment\n(func)"), Ok(("(func)", ())));
+/// assert_eq!(ws(";; ends the input"), Ok(("", ())));
+/// assert_eq!(ws("(; a (; b ;) c ;) (func)"), Ok(("(func)", ())));
+/// assert_eq!(ws(" ;; one\n (; two ;) ;; three\n)"), Ok((")", ())));
+/// assert_eq!(ws("(func)"), Ok(("(func)", ())));
+///
+/// // Wrong: the outer comment is never closed
+/// assert!(matches!(ws("(; a (; b ;) c"), Err(nom::Err::Failure(_))));
+/// ```
+pub fn ws(input: &str) -> IResult<'_, ()> {
+    let mut rest = input;
+
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', '\r', '\n']);
+
+        if let Some(comment) = rest.strip_prefix(";;") {
+            rest = match comment.find('\n') {
+                Some(idx) => &comment[idx + 1..],
+                None => &comment[comment.len()..],
+            };
+        } else if rest.starts_with("(;") {
+            (rest, _) = block_comment(rest)?;
+        } else {
+            return Ok((rest, ()));
+        }
+    }
+}
+
+/// Eats a block comment, along with the comments nested in it.
+fn block_comment(input: &str) -> IResult<'_, ()> {
+    let mut rest = input;
+    let mut depth = 0_usize;
+
+    loop {
+        if let Some(after) = rest.strip_prefix("(;") {
+            depth += 1;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(";)") {
+            depth -= 1;
+            rest = after;
+
+            if depth == 0 {
+                return Ok((rest, ()));
+            }
+        } else {
+            let mut chars = rest.chars();
+
+            if chars.next().is_none() {
+                return failure(
+                    input,
+                    "unterminated block comment",
+                );
+            }
+
+            rest = chars.as_str();
+        }
+    }
+}
+
 // Based on https://github.com/Geal/nom/blob/761ab0a24fccb4c560367b583b608fbae5f31647/examples/s_expression.rs#L155
 pub fn parse_parenthesis_enclosed<'a, T, F>(
     inner: F,
-) -> impl FnMut(&'a str) -> IResult<T>
+) -> impl FnMut(&'a str) -> IResult<'a, T>
 where
     F: Parser<&'a str, T, VerboseError<&'a str>>,
 {
     delimited(
         char('('),
-        preceded(multispace0, inner),
+        preceded(ws, inner),
         context(
             "closing parenthesis",
-            cut(preceded(multispace0, char(')'))),
+            cut(preceded(ws, char(')'))),
         ),
     )
 }
 
+/// Builds an unrecoverable parsing error at `input`, described
+/// by `context`.
+pub(crate) fn failure<'a, T>(
+    input: &'a str,
+    context: &'static str,
+) -> IResult<'a, T> {
+    let error = VerboseError {
+        errors: vec![(
+            input,
+            VerboseErrorKind::Context(context),
+        )],
+    };
+
+    Err(nom::Err::Failure(error))
+}
+
+/// Parses `keyword`, as long as it's not just the start of a
+/// longer keyword.
+pub(crate) fn keyword<'a>(
+    keyword: &'static str,
+) -> impl FnMut(&'a str) -> IResult<'a, &'a str> {
+    terminated(tag(keyword), keyword_end)
+}
+
+/// Succeeds without consuming input if the next character could
+/// not continue a keyword, e.g. ensures that `i32.eq` does not
+/// match the start of `i32.eqz`.
+pub(crate) fn keyword_end(input: &str) -> IResult<'_, ()> {
+    not(satisfy(is_acceptable_identifier_character))(input)
+}
+
 fn is_acceptable_identifier_character(ch: char) -> bool {
     ch.is_ascii_alphanumeric()
         || matches!(
@@ -0,0 +1,22 @@
+//! Re-exports of the most commonly used parsers, AST types and
+//! the [`Emitter`].
+//!
+//! ```
+//! use water::prelude::*;
+//!
+//! let (rest, _module) = parse_module("(module)").unwrap();
+//! assert!(rest.is_empty());
+//!
+//! let (_, instruction) = parse_instruction("(i32.const 5)").unwrap();
+//! assert!(instruction.arguments.is_empty());
+//! ```
+
+pub use crate::{
+    ast::{
+        Function, FunctionImport, Index, Instruction, Local,
+        Module, NumericalType, NumericalValue, Opcode,
+        Parameter, Program, Type,
+    },
+    emitter::{Emittable, Emitter},
+    parser::{parse_function, parse_instruction, parse_module},
+};
@@ -0,0 +1,931 @@
+//! Resolution of symbolic indices, such as `$x` in
+//! `local.get $x`, into the numerical indices WASM's binary
+//! format requires.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    ast::{
+        ExportKind, Function, Index, Instruction, Module,
+        Opcode, ScopeKind,
+    },
+    small_string::SmallString,
+};
+
+/// The index space an identifier belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentifierKind {
+    Function,
+    /// Parameters and locals, which share a single index space
+    Local,
+    Global,
+    Label,
+    Type,
+    Table,
+    Memory,
+}
+
+impl fmt::Display for IdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            IdentifierKind::Function => "function",
+            IdentifierKind::Local => "local",
+            IdentifierKind::Global => "global",
+            IdentifierKind::Label => "label",
+            IdentifierKind::Type => "type",
+            IdentifierKind::Table => "table",
+            IdentifierKind::Memory => "memory",
+        };
+
+        f.write_str(kind)
+    }
+}
+
+impl From<ExportKind> for IdentifierKind {
+    fn from(kind: ExportKind) -> Self {
+        match kind {
+            ExportKind::Function => IdentifierKind::Function,
+            ExportKind::Memory => IdentifierKind::Memory,
+            ExportKind::Global => IdentifierKind::Global,
+        }
+    }
+}
+
+/// Returned when an identifier can't be resolved to an index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// An index refers to an identifier that was never defined
+    Unknown {
+        kind: IdentifierKind,
+        identifier: SmallString,
+    },
+    /// The same identifier was defined twice in one index space
+    Duplicate {
+        kind: IdentifierKind,
+        identifier: SmallString,
+    },
+    /// A numerical index doesn't fit in an u32
+    OutOfRange { kind: IdentifierKind, index: i64 },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Unknown { kind, identifier } => {
+                write!(f, "unknown {kind} `${identifier}`")
+            }
+            ResolveError::Duplicate { kind, identifier } => {
+                write!(f, "duplicate {kind} `${identifier}`")
+            }
+            ResolveError::OutOfRange { kind, index } => {
+                write!(f, "{kind} index {index} is out of range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// Maps the identifiers of a single index space to their
+/// indices.
+#[derive(Debug)]
+pub struct SymbolTable {
+    kind: IdentifierKind,
+    indices: HashMap<SmallString, u32>,
+    /// The amount of entries, named or not
+    len: u32,
+}
+
+impl SymbolTable {
+    /// Builds a table from the identifiers of an index space, in
+    /// index order. Unnamed entries still take up an index.
+    pub fn new<'a>(
+        kind: IdentifierKind,
+        identifiers: impl IntoIterator<
+            Item = Option<&'a SmallString>,
+        >,
+    ) -> Result<Self, ResolveError> {
+        let mut table = Self {
+            kind,
+            indices: HashMap::new(),
+            len: 0,
+        };
+
+        for identifier in identifiers {
+            table.push(identifier)?;
+        }
+
+        Ok(table)
+    }
+
+    /// Appends an entry to the index space, returning its
+    /// index.
+    pub(crate) fn push(
+        &mut self,
+        identifier: Option<&SmallString>,
+    ) -> Result<u32, ResolveError> {
+        if let Some(identifier) = identifier {
+            if self.indices.contains_key(identifier) {
+                return Err(ResolveError::Duplicate {
+                    kind: self.kind,
+                    identifier: identifier.clone(),
+                });
+            }
+
+            self.indices.insert(identifier.clone(), self.len);
+        }
+        self.len += 1;
+
+        Ok(self.len - 1)
+    }
+
+    /// Removes the last entry, which was pushed as
+    /// `identifier`.
+    pub(crate) fn pop(
+        &mut self,
+        identifier: Option<&SmallString>,
+    ) {
+        if let Some(identifier) = identifier {
+            self.indices.remove(identifier);
+        }
+        self.len -= 1;
+    }
+
+    /// The index `identifier` was defined at.
+    pub fn index_of(
+        &self,
+        identifier: &str,
+    ) -> Result<u32, ResolveError> {
+        self.indices.get(identifier).copied().ok_or_else(|| {
+            ResolveError::Unknown {
+                kind: self.kind,
+                identifier: identifier.into(),
+            }
+        })
+    }
+
+    /// Rewrites `index` into a numerical one, if it's an
+    /// identifier.
+    fn resolve(
+        &self,
+        index: &mut Index,
+    ) -> Result<(), ResolveError> {
+        if let Index::Identifier(identifier) = index {
+            *index = Index::Numerical(
+                self.index_of(identifier)?.into(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The module-level index spaces that function bodies and
+/// exports refer to.
+pub(crate) struct Symbols {
+    pub(crate) functions: SymbolTable,
+    globals: SymbolTable,
+    types: SymbolTable,
+    tables: SymbolTable,
+    memories: SymbolTable,
+}
+
+impl Symbols {
+    /// Builds the index spaces of `module`.
+    pub(crate) fn of(
+        module: &Module,
+    ) -> Result<Self, ResolveError> {
+        let imported = module
+            .imports
+            .iter()
+            .map(|import| import.signature.identifier.as_ref());
+        let defined = module
+            .functions
+            .iter()
+            .map(|function| function.identifier.as_ref());
+
+        Ok(Self {
+            functions: SymbolTable::new(
+                IdentifierKind::Function,
+                imported.chain(defined),
+            )?,
+            globals: SymbolTable::new(
+                IdentifierKind::Global,
+                module
+                    .globals
+                    .iter()
+                    .map(|global| global.identifier.as_ref()),
+            )?,
+            types: SymbolTable::new(
+                IdentifierKind::Type,
+                module.types.iter().map(|definition| {
+                    definition.identifier.as_ref()
+                }),
+            )?,
+            // Tables can't be declared yet
+            tables: SymbolTable::new(
+                IdentifierKind::Table,
+                None,
+            )?,
+            memories: SymbolTable::new(
+                IdentifierKind::Memory,
+                module
+                    .memories
+                    .iter()
+                    .map(|memory| memory.identifier.as_ref()),
+            )?,
+        })
+    }
+
+    /// Resolves the body of `function`.
+    ///
+    /// If `defer_calls` is set, calls to functions that aren't
+    /// defined yet are left as they are instead of being an
+    /// error. Returns whether any was.
+    pub(crate) fn resolve_function(
+        &self,
+        function: &mut Function,
+        defer_calls: bool,
+    ) -> Result<bool, ResolveError> {
+        let mut scope =
+            self.scope(locals_of(function)?, defer_calls);
+        resolve_instructions(&mut function.body, &mut scope)?;
+
+        Ok(scope.deferred_calls == Some(true))
+    }
+
+    /// Resolves `instruction`, which is about to be appended to
+    /// the body of `function`, outside of any block. Calls are
+    /// deferred as in [`Symbols::resolve_function`].
+    pub(crate) fn resolve_appended(
+        &self,
+        function: &Function,
+        instruction: &mut Instruction,
+        defer_calls: bool,
+    ) -> Result<bool, ResolveError> {
+        let mut scope =
+            self.scope(locals_of(function)?, defer_calls);
+        resolve_instruction(instruction, &mut scope)?;
+
+        Ok(scope.deferred_calls == Some(true))
+    }
+
+    fn scope(
+        &self,
+        locals: SymbolTable,
+        defer_calls: bool,
+    ) -> Scope<'_> {
+        Scope {
+            symbols: self,
+            locals,
+            labels: vec![],
+            deferred_calls: defer_calls.then_some(false),
+        }
+    }
+}
+
+impl Module {
+    /// Rewrites every [`Index::Identifier`] within the bodies of
+    /// this module's functions, the initializers of its globals
+    /// and its module-level exports into an
+    /// [`Index::Numerical`].
+    ///
+    /// Imported functions come first in the function index
+    /// space, and parameters come before locals. Labels resolve
+    /// to their depth relative to the branch using them.
+    ///
+    /// Stops at the first identifier that can't be resolved,
+    /// which may leave the module partially resolved.
+    pub fn resolve_indices(
+        &mut self,
+    ) -> Result<(), ResolveError> {
+        let symbols = Symbols::of(self)?;
+
+        for global in &mut self.globals {
+            // Initializers run outside of any function
+            let mut scope = symbols.scope(
+                SymbolTable::new(IdentifierKind::Local, None)?,
+                false,
+            );
+
+            resolve_instruction(&mut global.init, &mut scope)?;
+        }
+
+        for function in &mut self.functions {
+            symbols.resolve_function(function, false)?;
+        }
+
+        for export in &mut self.exports {
+            let table = match export.kind {
+                ExportKind::Function => &symbols.functions,
+                ExportKind::Memory => &symbols.memories,
+                ExportKind::Global => &symbols.globals,
+            };
+
+            table.resolve(&mut export.index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Every export of this module, as its name, kind and index
+    /// within the index space of that kind. Inline exports come
+    /// first, grouped by kind, followed by the ones declared at
+    /// module level.
+    ///
+    /// Imported functions come first in the function index
+    /// space, so their count offsets the index of every defined
+    /// function.
+    ///
+    /// Module-level exports whose identifier isn't defined, or
+    /// whose index doesn't fit in an u32, are an error.
+    pub fn exports(
+        &self,
+    ) -> impl Iterator<
+        Item = Result<(&str, ExportKind, u32), ResolveError>,
+    > {
+        let functions = self
+            .functions
+            .iter()
+            .map(|function| &function.exports)
+            .zip(self.imports.len() as u32..)
+            .map(|(names, index)| {
+                (names, ExportKind::Function, index)
+            });
+        let memories = self
+            .memories
+            .iter()
+            .map(|memory| &memory.exports)
+            .zip(0..)
+            .map(|(names, index)| {
+                (names, ExportKind::Memory, index)
+            });
+        let globals = self
+            .globals
+            .iter()
+            .map(|global| &global.exports)
+            .zip(0..)
+            .map(|(names, index)| {
+                (names, ExportKind::Global, index)
+            });
+
+        let inline = functions
+            .chain(memories)
+            .chain(globals)
+            .flat_map(|(names, kind, index)| {
+                names.iter().map(move |name| {
+                    Ok((name.as_str(), kind, index))
+                })
+            });
+
+        let standalone = self.exports.iter().map(|export| {
+            let index = match &export.index {
+                Index::Numerical(index) => u32::try_from(*index)
+                    .map_err(|_| ResolveError::OutOfRange {
+                        kind: export.kind.into(),
+                        index: *index,
+                    })?,
+                Index::Identifier(identifier) => {
+                    self.index_of(export.kind, identifier)?
+                }
+            };
+
+            Ok((export.name.as_str(), export.kind, index))
+        });
+
+        inline.chain(standalone)
+    }
+
+    /// The position of the item of the given kind named
+    /// `identifier` in its index space.
+    fn index_of(
+        &self,
+        kind: ExportKind,
+        identifier: &str,
+    ) -> Result<u32, ResolveError> {
+        let identifiers: Vec<_> = match kind {
+            ExportKind::Function => {
+                let imported = self
+                    .imports
+                    .iter()
+                    .map(|import| &import.signature.identifier);
+                let defined = self
+                    .functions
+                    .iter()
+                    .map(|function| &function.identifier);
+
+                imported.chain(defined).collect()
+            }
+            ExportKind::Memory => self
+                .memories
+                .iter()
+                .map(|memory| &memory.identifier)
+                .collect(),
+            ExportKind::Global => self
+                .globals
+                .iter()
+                .map(|global| &global.identifier)
+                .collect(),
+        };
+
+        identifiers
+            .into_iter()
+            .position(|name| name.as_deref() == Some(identifier))
+            .map(|index| index as u32)
+            .ok_or_else(|| ResolveError::Unknown {
+                kind: kind.into(),
+                identifier: identifier.into(),
+            })
+    }
+}
+
+/// The symbols visible from within a function body.
+struct Scope<'a> {
+    symbols: &'a Symbols,
+    locals: SymbolTable,
+    /// The labels of the blocks enclosing the current
+    /// instruction, innermost last.
+    labels: Vec<Option<SmallString>>,
+    /// Whether a call to an unknown function was left
+    /// unresolved, or `None` if that's an error
+    deferred_calls: Option<bool>,
+}
+
+/// The parameters and locals of `function`, which share a
+/// single index space.
+fn locals_of(
+    function: &Function,
+) -> Result<SymbolTable, ResolveError> {
+    let parameters = function
+        .parameters
+        .iter()
+        .map(|parameter| parameter.identifier.as_ref());
+    let locals = function
+        .local_variables
+        .iter()
+        .map(|local| local.identifier.as_ref());
+
+    SymbolTable::new(
+        IdentifierKind::Local,
+        parameters.chain(locals),
+    )
+}
+
+fn resolve_instructions(
+    instructions: &mut [Instruction],
+    scope: &mut Scope,
+) -> Result<(), ResolveError> {
+    instructions.iter_mut().try_for_each(|instruction| {
+        resolve_instruction(instruction, scope)
+    })
+}
+
+fn resolve_instruction(
+    instruction: &mut Instruction,
+    scope: &mut Scope,
+) -> Result<(), ResolveError> {
+    // Folded arguments run before the instruction itself, so
+    // they're outside of any block it opens
+    resolve_instructions(&mut instruction.arguments, scope)?;
+
+    match &mut instruction.opcode {
+        Opcode::Call(index) => {
+            match (
+                scope.symbols.functions.resolve(index),
+                &mut scope.deferred_calls,
+            ) {
+                (
+                    Err(ResolveError::Unknown { .. }),
+                    Some(deferred),
+                ) => {
+                    *deferred = true;
+                    Ok(())
+                }
+                (resolved, _) => resolved,
+            }
+        }
+        Opcode::CallIndirect {
+            type_index,
+            table_index,
+        } => {
+            scope.symbols.types.resolve(type_index)?;
+            scope.symbols.tables.resolve(table_index)
+        }
+        Opcode::VariableInstruction(operation) => {
+            let table = match operation.scope {
+                ScopeKind::Local => &scope.locals,
+                ScopeKind::Global => &scope.symbols.globals,
+            };
+
+            table.resolve(&mut operation.index)
+        }
+        Opcode::Br(index) | Opcode::BrIf(index) => {
+            resolve_label(index, &scope.labels)
+        }
+        Opcode::Block(block) | Opcode::Loop(block) => {
+            scope.labels.push(block.label.clone());
+            let resolved =
+                resolve_instructions(&mut block.body, scope);
+            scope.labels.pop();
+
+            resolved
+        }
+        Opcode::If(if_block) => {
+            scope.labels.push(if_block.label.clone());
+            let resolved =
+                resolve_instructions(&mut if_block.then, scope)
+                    .and_then(|()| {
+                        resolve_instructions(
+                            &mut if_block.else_,
+                            scope,
+                        )
+                    });
+            scope.labels.pop();
+
+            resolved
+        }
+        Opcode::Return
+        | Opcode::Nop
+        | Opcode::Drop
+        | Opcode::Select
+        | Opcode::Constant(_)
+        | Opcode::Arithmetic(_)
+        | Opcode::Comparison(_)
+        | Opcode::Conversion(_)
+        | Opcode::MemoryAccess(_)
+        | Opcode::MemorySize
+        | Opcode::MemoryGrow
+        | Opcode::Unreachable(_) => Ok(()),
+    }
+}
+
+/// Rewrites a label into its depth, where the innermost
+/// enclosing block is at depth 0.
+fn resolve_label(
+    index: &mut Index,
+    labels: &[Option<SmallString>],
+) -> Result<(), ResolveError> {
+    let Index::Identifier(identifier) = index else {
+        return Ok(());
+    };
+
+    let depth = labels
+        .iter()
+        .rev()
+        .position(|label| label.as_ref() == Some(identifier))
+        .ok_or_else(|| ResolveError::Unknown {
+            kind: IdentifierKind::Label,
+            identifier: identifier.clone(),
+        })?;
+
+    *index = Index::Numerical(depth as i64);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IdentifierKind, ResolveError};
+    use crate::{
+        ast::{ExportKind, Index, Instruction, Module, Opcode},
+        parser::parse_module,
+    };
+
+    fn parse(input: &str) -> Module {
+        let (_, module) = parse_module(input).unwrap();
+
+        module
+    }
+
+    fn indices(instructions: &[Instruction]) -> Vec<Index> {
+        let mut indices = vec![];
+
+        for instruction in instructions {
+            instruction.walk(&mut |instruction| {
+                match &instruction.opcode {
+                    Opcode::Call(index)
+                    | Opcode::Br(index)
+                    | Opcode::BrIf(index) => {
+                        indices.push(index.clone())
+                    }
+                    Opcode::VariableInstruction(operation) => {
+                        indices.push(operation.index.clone())
+                    }
+                    _ => {}
+                }
+            });
+        }
+
+        indices
+    }
+
+    #[test]
+    fn resolves_locals_and_functions() {
+        let mut module = parse(
+            r#"(module
+                (import "env" "log" (func $log (param i32)))
+                (func $other (param i32))
+                (func $main (param $a i32) (param i32) (param $b i32)
+                    (local $x i32) (local i32) (local $y i32)
+                    (local.set $x (local.get $b))
+                    (local.set $y (local.get 1))
+                    (call $other (local.get $a))
+                    (call $log (local.get $y))
+                    (call 0 (local.get $x))))"#,
+        );
+
+        module.resolve_indices().unwrap();
+
+        let numerical: Vec<_> = [3, 2, 5, 1, 1, 0, 0, 5, 0, 3]
+            .into_iter()
+            .map(Index::Numerical)
+            .collect();
+
+        assert_eq!(
+            indices(&module.functions[1].body),
+            numerical
+        );
+    }
+
+    #[test]
+    fn resolves_calls_to_later_functions() {
+        let mut module = parse(
+            "(module
+                (func $a (call $b))
+                (func $b))",
+        );
+
+        module.resolve_indices().unwrap();
+
+        assert_eq!(
+            module.functions[0].body[0].opcode,
+            Opcode::Call(Index::Numerical(1))
+        );
+    }
+
+    #[test]
+    fn imports_come_first_in_the_function_index_space() {
+        let mut module = parse(
+            r#"(module
+                (func $main (call $log (i32.const 1)) (call $main))
+                (import "env" "log" (func $log (param i32)))
+                (func $helper (call $print (i32.const 2)))
+                (func $print (import "env" "print") (param i32))
+                (export "main" (func $main)))"#,
+        );
+
+        assert_eq!(module.imports.len(), 2);
+        assert_eq!(module.functions.len(), 2);
+
+        module.resolve_indices().unwrap();
+
+        let numerical = |indices: &[i64]| -> Vec<Index> {
+            indices
+                .iter()
+                .copied()
+                .map(Index::Numerical)
+                .collect()
+        };
+        assert_eq!(
+            indices(&module.functions[0].body),
+            numerical(&[0, 2])
+        );
+        assert_eq!(
+            indices(&module.functions[1].body),
+            numerical(&[1])
+        );
+        assert_eq!(module.exports[0].index, Index::Numerical(2));
+    }
+
+    #[test]
+    fn resolves_type_indices() {
+        let mut module = parse(
+            "(module
+                (type $unop (func (param i32) (result i32)))
+                (type $binop (func (param i32) (param i32) (result i32)))
+                (func (call_indirect (type $binop) (i32.const 0))))",
+        );
+
+        module.resolve_indices().unwrap();
+
+        assert_eq!(
+            module.functions[0].body[0].opcode,
+            Opcode::CallIndirect {
+                type_index: Index::Numerical(1),
+                table_index: Index::Numerical(0),
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_globals() {
+        let mut module = parse(
+            "(module
+                (global $base i32 (i32.const 8))
+                (global $counter (mut i32) (global.get $base))
+                (func (global.set $counter (global.get 0))))",
+        );
+
+        module.resolve_indices().unwrap();
+
+        assert_eq!(
+            indices(std::slice::from_ref(
+                &module.globals[1].init
+            )),
+            [Index::Numerical(0)]
+        );
+        assert_eq!(
+            indices(&module.functions[0].body),
+            [Index::Numerical(1), Index::Numerical(0)]
+        );
+    }
+
+    #[test]
+    fn resolves_labels_to_depths() {
+        let mut module = parse(
+            "(module (func
+                (block $outer
+                    (loop $inner
+                        (br_if $inner (i32.const 1))
+                        (br $outer)
+                        (if $cond (i32.const 0)
+                            (then (br $inner))
+                            (else (br 0)))))))",
+        );
+
+        module.resolve_indices().unwrap();
+
+        let numerical: Vec<_> = [0, 1, 1, 0]
+            .into_iter()
+            .map(Index::Numerical)
+            .collect();
+
+        assert_eq!(
+            indices(&module.functions[0].body),
+            numerical
+        );
+    }
+
+    #[test]
+    fn resolves_module_level_exports() {
+        let mut module = parse(
+            r#"(module
+                (import "env" "log" (func $log))
+                (func $f)
+                (export "f" (func $f))
+                (export "log" (func 0)))"#,
+        );
+
+        module.resolve_indices().unwrap();
+
+        let exported: Vec<_> = module
+            .exports
+            .iter()
+            .map(|export| export.index.clone())
+            .collect();
+        assert_eq!(
+            exported,
+            [Index::Numerical(1), Index::Numerical(0)]
+        );
+
+        let mut module = parse(
+            r#"(module (func $f) (export "g" (func $g)))"#,
+        );
+
+        assert_eq!(
+            module.resolve_indices(),
+            Err(ResolveError::Unknown {
+                kind: IdentifierKind::Function,
+                identifier: "g".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn exports_are_indexed_after_imports() {
+        let (_, module) = parse_module(
+            r#"(module
+                (func $a (export "a") (export "first"))
+                (import "env" "f" (func $f))
+                (func $b)
+                (func $c (export "c")))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            module.exports().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                ("a", ExportKind::Function, 1),
+                ("first", ExportKind::Function, 1),
+                ("c", ExportKind::Function, 3),
+            ])
+        );
+
+        let (_, module) = parse_module(
+            r#"(module
+                (import "env" "f" (func $f))
+                (func $a (export "inline"))
+                (export "a" (func $a))
+                (export "f" (func 0))
+                (export "missing" (func $nope)))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            module.exports().collect::<Vec<_>>(),
+            vec![
+                Ok(("inline", ExportKind::Function, 1)),
+                Ok(("a", ExportKind::Function, 1)),
+                Ok(("f", ExportKind::Function, 0)),
+                Err(ResolveError::Unknown {
+                    kind: IdentifierKind::Function,
+                    identifier: "nope".into(),
+                }),
+            ]
+        );
+        assert_eq!(Module::default().exports().count(), 0);
+    }
+
+    #[test]
+    fn exports_every_kind() {
+        let (_, module) = parse_module(
+            r#"(module
+                (memory $heap (export "memory") 1)
+                (global $zero i32 (i32.const 0))
+                (global $counter (export "count") (mut i32)
+                    (i32.const 0))
+                (func $main (export "main"))
+                (export "counter" (global $counter))
+                (export "heap" (memory $heap)))"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            module.exports().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                ("main", ExportKind::Function, 0),
+                ("memory", ExportKind::Memory, 0),
+                ("count", ExportKind::Global, 1),
+                ("counter", ExportKind::Global, 1),
+                ("heap", ExportKind::Memory, 0),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers() {
+        let cases = [
+            ("(local.get $nope)", IdentifierKind::Local),
+            ("(global.get $nope)", IdentifierKind::Global),
+            ("(call $nope)", IdentifierKind::Function),
+            ("(br $nope)", IdentifierKind::Label),
+            (
+                "(call_indirect (type $nope))",
+                IdentifierKind::Type,
+            ),
+            (
+                "(call_indirect $nope (type 0))",
+                IdentifierKind::Table,
+            ),
+        ];
+
+        for (instruction, kind) in cases {
+            let mut module = parse(&format!(
+                "(module (func (param $a i32) {instruction}))"
+            ));
+
+            let error = module.resolve_indices().unwrap_err();
+
+            assert_eq!(
+                error,
+                ResolveError::Unknown {
+                    kind,
+                    identifier: "nope".into(),
+                }
+            );
+            assert_eq!(
+                error.to_string(),
+                format!("unknown {kind} `$nope`")
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_identifiers() {
+        let mut module = parse(
+            "(module (func (param $a i32) (local $a i32)))",
+        );
+
+        assert_eq!(
+            module.resolve_indices(),
+            Err(ResolveError::Duplicate {
+                kind: IdentifierKind::Local,
+                identifier: "a".into(),
+            })
+        );
+
+        let mut module = parse("(module (func $f) (func $f))");
+
+        assert!(module.resolve_indices().is_err());
+    }
+}
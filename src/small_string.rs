@@ -1,7 +1,8 @@
 //! A cheaply-clonable String type
 // Taken from github.com/vrmiguel/ceceio
 use std::{
-    borrow::Borrow, fmt, hash::Hash, ops::Deref, rc::Rc, str,
+    borrow::Borrow, collections::HashMap, fmt, hash::Hash,
+    ops::Deref, rc::Rc, str,
 };
 
 pub const INLINE_CAP: usize = 22;
@@ -16,9 +17,10 @@ pub enum SmallString {
 impl Hash for SmallString {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
-            SmallString::Inlined { len, buf } => {
-                unsafe { buf.get_unchecked(0..*len as usize) }
-                    .hash(state);
+            // Must hash the same way `str` does, since we
+            // implement `Borrow<str>`
+            SmallString::Inlined { .. } => {
+                self.as_str().hash(state);
             }
             SmallString::Heap(rc) => {
                 // Cold branch since identifiers tend to be
@@ -67,8 +69,14 @@ impl SmallString {
         matches!(self, Self::Heap(_))
     }
 
-    pub fn new<S: AsRef<str>>(input: S) -> Self {
-        let string = input.as_ref();
+    /// Copies `string`, inline if it's short enough and to the
+    /// heap otherwise.
+    ///
+    /// A `&SmallString` derefs to `&str`, so it's accepted here
+    /// too, but it would be copied all over again. Clone it
+    /// instead, or use `From<&SmallString>`, which shares the
+    /// heap allocation.
+    pub fn new(string: &str) -> Self {
         let bytes = string.as_bytes();
 
         if bytes.len() > INLINE_CAP {
@@ -78,11 +86,12 @@ impl SmallString {
         }
     }
 
+    /// The contents of this string. `Display`, `AsRef<str>`
+    /// and `Deref` all go through this.
     pub fn as_str(&self) -> &str {
         match self {
             // Safety: SmallString::Inlined can only be created
-            // from `AsRef<str>`, so we'll
-            // always have valid UTF-8
+            // from a `&str`, so we'll always have valid UTF-8
             SmallString::Inlined { buf, len } => unsafe {
                 std::str::from_utf8_unchecked(
                     &buf[..*len as usize],
@@ -119,11 +128,155 @@ impl From<&str> for SmallString {
     }
 }
 
+impl From<&SmallString> for SmallString {
+    fn from(string: &SmallString) -> Self {
+        string.clone()
+    }
+}
+
+/// A cheap handle to a string stored in a [`StringInterner`].
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+pub struct Symbol(u32);
+
+/// Deduplicates strings, handing out a [`Symbol`] per distinct
+/// string so that repeated identifiers are stored only once and
+/// compare in constant time.
+#[derive(Default)]
+pub struct StringInterner {
+    symbols: HashMap<SmallString, Symbol>,
+    strings: Vec<SmallString>,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the symbol for `string`, interning it if it
+    /// wasn't seen before.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        let string = SmallString::new(string);
+
+        self.strings.push(string.clone());
+        self.symbols.insert(string, symbol);
+
+        symbol
+    }
+
+    /// Returns the symbol of `string`, if it was interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+
+    /// Returns the string a symbol refers to, if that symbol
+    /// was produced by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings
+            .get(symbol.0 as usize)
+            .map(SmallString::as_str)
+    }
+
+    /// The amount of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::ops::Not;
+    use std::{collections::HashSet, ops::Not};
+
+    use super::{SmallString, StringInterner};
 
-    use super::SmallString;
+    #[test]
+    fn hashes_consistently_with_str() {
+        let set: HashSet<SmallString> = [
+            SmallString::new("idx"),
+            SmallString::new(
+                "a_very_long_identifier_on_the_heap",
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(set.contains("idx"));
+        assert!(
+            set.contains("a_very_long_identifier_on_the_heap")
+        );
+    }
+
+    #[test]
+    fn interns_equal_strings_to_the_same_symbol() {
+        let mut interner = StringInterner::new();
+
+        let first = interner.intern("i32");
+        let other = interner.intern("local");
+        let second = interner.intern("i32");
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get("local"), Some(other));
+        assert_eq!(interner.get("f64"), None);
+    }
+
+    #[test]
+    fn resolves_symbols_back_to_text() {
+        let mut interner = StringInterner::new();
+        let long =
+            "an_identifier_longer_than_the_inline_capacity";
+
+        let short_symbol = interner.intern("idx");
+        let long_symbol = interner.intern(long);
+
+        assert_eq!(interner.resolve(short_symbol), Some("idx"));
+        assert_eq!(interner.resolve(long_symbol), Some(long));
+    }
+
+    #[test]
+    fn accessors_agree_on_the_contents() {
+        for identifier in
+            ["$counter", "$an_identifier_stored_on_the_heap"]
+        {
+            let string = SmallString::new(identifier);
+            let converted: SmallString = identifier.into();
+
+            assert_eq!(string, converted);
+            assert_eq!(string.as_str(), identifier);
+            assert_eq!(
+                AsRef::<str>::as_ref(&string),
+                identifier
+            );
+            assert_eq!(&*string, identifier);
+            assert_eq!(format!("{string}"), string.as_str());
+        }
+    }
+
+    #[test]
+    fn converting_a_reference_shares_the_heap_allocation() {
+        let long = SmallString::new(
+            "a_very_long_identifier_on_the_heap",
+        );
+
+        let converted = SmallString::from(&long);
+        let copied = SmallString::new(&long);
+
+        assert_eq!(converted, long);
+        assert_eq!(converted.as_ptr(), long.as_ptr());
+        assert_eq!(copied, long);
+        assert_ne!(copied.as_ptr(), long.as_ptr());
+    }
 
     #[test]
     fn creates_inlined_small_strings_correctly() {
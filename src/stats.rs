@@ -0,0 +1,321 @@
+//! Size and content statistics of a module, for tooling that
+//! wants a breakdown of what a module is made of.
+
+use crate::{
+    ast::{
+        Function, Index, Instruction, Module, NumericalValue,
+        Opcode,
+    },
+    emitter::{local_runs, FunctionType, TypeTable},
+    leb128::{SignedLeb128, UnsignedLeb128},
+};
+
+/// Counts of the items of a module, along with an estimate of
+/// the size of each of its sections once emitted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleStats {
+    /// The amount of functions defined in the module
+    pub functions: usize,
+    /// The amount of functions imported by the module
+    pub imports: usize,
+    /// The amount of globals defined in the module
+    pub globals: usize,
+    /// The amount of instructions in the bodies of the defined
+    /// functions, counting folded arguments and the contents of
+    /// blocks
+    pub instructions: usize,
+    /// The estimated size of each section
+    pub section_sizes: SectionSizes,
+}
+
+/// The estimated size in bytes of each section of a module,
+/// including its id and size prefix. Sections the module
+/// wouldn't emit are 0.
+///
+/// Indices given as identifiers are counted as a single byte,
+/// which is exact as long as the index space they refer to
+/// has less than 128 items. Exports that can't be resolved
+/// aren't counted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionSizes {
+    pub type_: usize,
+    pub import: usize,
+    pub function: usize,
+    pub memory: usize,
+    pub global: usize,
+    pub export: usize,
+    pub code: usize,
+}
+
+impl SectionSizes {
+    /// The estimated size of every section together, which is
+    /// the size of the module without its preamble.
+    pub fn total(&self) -> usize {
+        self.type_
+            + self.import
+            + self.function
+            + self.memory
+            + self.global
+            + self.export
+            + self.code
+    }
+}
+
+impl Module {
+    /// Counts the items of this module and estimates the size
+    /// of its sections by walking its AST, without emitting it.
+    pub fn stats(&self) -> ModuleStats {
+        let mut instructions = 0;
+        for instruction in self
+            .functions
+            .iter()
+            .flat_map(|function| &function.body)
+        {
+            instruction.walk(&mut |_| instructions += 1);
+        }
+
+        ModuleStats {
+            functions: self.functions.len(),
+            imports: self.imports.len(),
+            globals: self.globals.len(),
+            instructions,
+            section_sizes: self.section_sizes(),
+        }
+    }
+
+    fn section_sizes(&self) -> SectionSizes {
+        let mut types = TypeTable::with_definitions(&self.types);
+
+        let import = vector(self.imports.iter().map(|import| {
+            let type_index = types
+                .index_of(FunctionType::of(&import.signature));
+
+            name_size(&import.namespace)
+                + name_size(&import.fn_name)
+                + 1
+                + unsigned_size(type_index as u64)
+        }));
+        let function =
+            vector(self.functions.iter().map(|function| {
+                let type_index =
+                    types.index_of(FunctionType::of(function));
+
+                unsigned_size(type_index as u64)
+            }));
+
+        let type_ = vector(types.types.iter().map(|type_| {
+            let (parameters, results) =
+                (type_.parameters.len(), type_.results.len());
+
+            1 + vector_size(parameters, parameters)
+                + vector_size(results, results)
+        }));
+
+        let memory =
+            vector(self.memories.iter().map(|memory| {
+                1 + unsigned_size(memory.min as u64)
+                    + memory.max.map_or(0, |max| {
+                        unsigned_size(max as u64)
+                    })
+            }));
+
+        let global = vector(self.globals.iter().map(|global| {
+            // The type, the mutability, the init and its `end`
+            3 + instruction_size(&global.init)
+        }));
+
+        let export = vector(self.exports().flatten().map(
+            |(name, _, index)| {
+                name_size(name) + 1 + unsigned_size(index as u64)
+            },
+        ));
+
+        let code =
+            vector(self.functions.iter().map(|function| {
+                let body = function_body_size(function);
+
+                unsigned_size(body as u64) + body
+            }));
+
+        SectionSizes {
+            type_: section(type_),
+            import: section(import),
+            function: section(function),
+            memory: section(memory),
+            global: section(global),
+            export: section(export),
+            code: section(code),
+        }
+    }
+}
+
+/// The size of a section with the given contents, or 0 if
+/// it's `None`, as empty sections aren't emitted.
+fn section(contents: Option<usize>) -> usize {
+    contents
+        .map_or(0, |size| 1 + unsigned_size(size as u64) + size)
+}
+
+/// The size of a vector of items of the given sizes, or `None`
+/// if it has no items.
+fn vector(sizes: impl Iterator<Item = usize>) -> Option<usize> {
+    let (count, size) = sizes
+        .fold((0, 0), |(count, size), item| {
+            (count + 1, size + item)
+        });
+
+    (count > 0).then(|| vector_size(count, size))
+}
+
+fn vector_size(count: usize, contents: usize) -> usize {
+    unsigned_size(count as u64) + contents
+}
+
+fn name_size(name: &str) -> usize {
+    vector_size(name.len(), name.len())
+}
+
+/// The size of a code section entry's body: its locals, its
+/// instructions and the closing `end`.
+fn function_body_size(function: &Function) -> usize {
+    let runs = local_runs(&function.local_variables);
+    let locals = runs
+        .iter()
+        .map(|(count, _)| unsigned_size(*count as u64) + 1)
+        .sum();
+
+    vector_size(runs.len(), locals)
+        + instructions_size(&function.body)
+        + 1
+}
+
+fn instructions_size(instructions: &[Instruction]) -> usize {
+    instructions.iter().map(instruction_size).sum()
+}
+
+/// The size of an instruction along with its arguments, which
+/// are emitted before it.
+fn instruction_size(instruction: &Instruction) -> usize {
+    let immediates = match &instruction.opcode {
+        Opcode::Call(index)
+        | Opcode::Br(index)
+        | Opcode::BrIf(index) => index_size(index),
+        Opcode::CallIndirect {
+            type_index,
+            table_index,
+        } => index_size(type_index) + index_size(table_index),
+        Opcode::VariableInstruction(operation) => {
+            index_size(&operation.index)
+        }
+        Opcode::Constant(constant) => match constant.value {
+            NumericalValue::Int32(value) => {
+                signed_size(value as i64)
+            }
+            NumericalValue::Int64(value) => signed_size(value),
+            NumericalValue::Float32(_) => 4,
+            NumericalValue::Float64(_) => 8,
+        },
+        Opcode::MemoryAccess(operation) => {
+            unsigned_size(operation.align.trailing_zeros() as u64)
+                + unsigned_size(operation.offset as u64)
+        }
+        Opcode::MemorySize | Opcode::MemoryGrow => 1,
+        // The block type, the body and its `end`
+        Opcode::Block(block) | Opcode::Loop(block) => {
+            2 + instructions_size(&block.body)
+        }
+        Opcode::If(if_block) => {
+            let else_ = if if_block.else_.is_empty() {
+                0
+            } else {
+                1 + instructions_size(&if_block.else_)
+            };
+
+            2 + instructions_size(&if_block.then) + else_
+        }
+        Opcode::Return
+        | Opcode::Arithmetic(_)
+        | Opcode::Comparison(_)
+        | Opcode::Conversion(_)
+        | Opcode::Unreachable(_)
+        | Opcode::Nop
+        | Opcode::Drop
+        | Opcode::Select => 0,
+    };
+
+    1 + immediates + instructions_size(&instruction.arguments)
+}
+
+fn index_size(index: &Index) -> usize {
+    match index {
+        Index::Numerical(index) => unsigned_size(*index as u64),
+        Index::Identifier(_) => 1,
+    }
+}
+
+fn unsigned_size(value: u64) -> usize {
+    UnsignedLeb128::from(value).encoded_len()
+}
+
+fn signed_size(value: i64) -> usize {
+    SignedLeb128::from(value).encoded_len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ModuleStats, SectionSizes};
+    use crate::{compile::compile, parser::parse_module};
+
+    const SOURCE: &str = r#"(module
+        (import "env" "log" (func $log (param i32)))
+        (memory 1 2)
+        (global $counter (mut i32) (i32.const 0))
+        (func $add (export "add") (param $a i32) (param $b i32)
+            (result i32)
+            (i32.add (local.get $a) (local.get $b)))
+        (func $bump (local $old i32) (local $new i64)
+            (local.set $old (global.get $counter))
+            (global.set $counter
+                (i32.add (local.get $old) (i32.const 300)))
+            (drop (memory.grow (i32.const 1)))
+            (i32.store offset=8 (i32.const 0)
+                (i32.load (i32.const 4)))))"#;
+
+    #[test]
+    fn counts_the_items_of_a_module() {
+        let (_, module) = parse_module(SOURCE).unwrap();
+        let stats = module.stats();
+
+        assert_eq!(
+            stats,
+            ModuleStats {
+                functions: 2,
+                imports: 1,
+                globals: 1,
+                instructions: 16,
+                section_sizes: SectionSizes {
+                    type_: 16,
+                    import: 13,
+                    function: 5,
+                    memory: 6,
+                    global: 8,
+                    export: 9,
+                    code: 45,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn estimates_the_size_of_the_emitted_module() {
+        let (_, module) = parse_module(SOURCE).unwrap();
+        let sizes = module.stats().section_sizes;
+
+        let bytes = compile(SOURCE).unwrap();
+
+        // Skips the magic number and version
+        assert_eq!(sizes.total(), bytes.len() - 8);
+        assert_eq!(sizes.memory, 6);
+        assert_eq!(sizes.export, 9);
+    }
+}
@@ -0,0 +1,314 @@
+//! Type checking of resolved modules, following the validation
+//! algorithm of the spec: every instruction pops its operands
+//! from a stack of value types and pushes its results to it.
+
+mod function;
+mod operands;
+
+use std::fmt;
+
+pub use operands::{check_operands, TypeError};
+
+use crate::{
+    ast::{Global, Module, Opcode, Type},
+    small_string::SmallString,
+};
+
+/// Where in a module validation failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// Within the function at this position in the function
+    /// index space
+    Function(u32),
+    /// Within the initializer of the global at this index
+    Global(u32),
+}
+
+/// Why a module failed validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// An operand has the wrong type
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+    },
+    /// An instruction needs more operands than the stack holds
+    MissingOperand {
+        expected: Option<Type>,
+    },
+    /// A block leaves more values on the stack than its type
+    /// declares
+    ExtraValues {
+        count: usize,
+    },
+    /// Two operands that must agree in type don't
+    OperandsDisagree {
+        first: Type,
+        second: Type,
+    },
+    /// An index that [`Module::resolve_indices`] didn't rewrite
+    UnresolvedIndex,
+    UnknownLocal(u32),
+    UnknownGlobal(u32),
+    UnknownFunction(u32),
+    UnknownLabel(u32),
+    UnknownType(u32),
+    UnknownTable(u32),
+    /// `global.set` on a global that isn't `mut`
+    ImmutableGlobal {
+        index: u32,
+        /// The identifier the global was defined with, if any,
+        /// since the index it was referred to by is already
+        /// resolved
+        identifier: Option<SmallString>,
+    },
+    /// A global initializer that isn't a single `const`
+    NonConstantInitializer,
+    /// A memory access aligned past its natural alignment
+    AlignmentTooLarge {
+        align: u32,
+        natural: u32,
+    },
+    UnknownMemory(u32),
+}
+
+/// Returned when a module doesn't type check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub location: Location,
+    pub kind: ValidationErrorKind,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Function(index) => {
+                write!(f, "function {index}")
+            }
+            Location::Global(index) => {
+                write!(f, "global {index}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationErrorKind as Kind;
+
+        match self {
+            Kind::TypeMismatch { expected, found } => write!(
+                f,
+                "type mismatch: expected {expected}, found {found}"
+            ),
+            Kind::MissingOperand {
+                expected: Some(expected),
+            } => write!(f, "missing operand of type {expected}"),
+            Kind::MissingOperand { expected: None } => {
+                f.write_str("missing operand")
+            }
+            Kind::ExtraValues { count } => {
+                write!(f, "{count} values left on the stack")
+            }
+            Kind::OperandsDisagree { first, second } => write!(
+                f,
+                "operands of types {first} and {second} disagree"
+            ),
+            Kind::UnresolvedIndex => {
+                f.write_str("identifier was not resolved")
+            }
+            Kind::UnknownLocal(index) => {
+                write!(f, "unknown local {index}")
+            }
+            Kind::UnknownGlobal(index) => {
+                write!(f, "unknown global {index}")
+            }
+            Kind::UnknownFunction(index) => {
+                write!(f, "unknown function {index}")
+            }
+            Kind::UnknownLabel(index) => {
+                write!(f, "unknown label {index}")
+            }
+            Kind::UnknownType(index) => {
+                write!(f, "unknown type {index}")
+            }
+            Kind::UnknownTable(index) => {
+                write!(f, "unknown table {index}")
+            }
+            Kind::ImmutableGlobal {
+                identifier: Some(identifier),
+                ..
+            } => write!(f, "global ${identifier} is immutable"),
+            Kind::ImmutableGlobal {
+                index,
+                identifier: None,
+            } => write!(f, "global {index} is immutable"),
+            Kind::NonConstantInitializer => {
+                f.write_str("initializer is not a constant")
+            }
+            Kind::AlignmentTooLarge { align, natural } => write!(
+                f,
+                "alignment {align} is larger than the natural alignment {natural}"
+            ),
+            Kind::UnknownMemory(index) => {
+                write!(f, "unknown memory {index}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.kind)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Module {
+    /// Type checks the globals and function bodies of this
+    /// module.
+    ///
+    /// Indices must already be numerical, so this is meant to
+    /// run after [`Module::resolve_indices`].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        for (global, index) in self.globals.iter().zip(0..) {
+            validate_global(global).map_err(|kind| {
+                ValidationError {
+                    location: Location::Global(index),
+                    kind,
+                }
+            })?;
+        }
+
+        for position in 0..self.functions.len() {
+            self.validate_function(position, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Type checks the body of the function at `position` in
+    /// [`Module::functions`]. An open body is still being
+    /// written, so it isn't checked to leave exactly its
+    /// results on the stack.
+    pub(crate) fn validate_function(
+        &self,
+        position: usize,
+        is_open: bool,
+    ) -> Result<(), ValidationError> {
+        let index = (self.imports.len() + position) as u32;
+
+        function::validate_function(
+            self,
+            &self.functions[position],
+            is_open,
+        )
+        .map_err(|kind| ValidationError {
+            location: Location::Function(index),
+            kind,
+        })
+    }
+}
+
+/// Only constants are allowed, since there are no imported
+/// globals to read from.
+fn validate_global(
+    global: &Global,
+) -> Result<(), ValidationErrorKind> {
+    let Opcode::Constant(constant) = &global.init.opcode else {
+        return Err(ValidationErrorKind::NonConstantInitializer);
+    };
+    if !global.init.arguments.is_empty() {
+        return Err(ValidationErrorKind::NonConstantInitializer);
+    }
+
+    let found = Type::Numerical(constant.value.type_());
+    if found != global.type_ {
+        return Err(ValidationErrorKind::TypeMismatch {
+            expected: global.type_.clone(),
+            found,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{ValidationError, ValidationErrorKind};
+    use crate::{
+        ast::{NumericalType, Type},
+        parser::parse_module,
+    };
+
+    /// Parses, resolves and validates `input`.
+    pub fn validate(input: &str) -> Result<(), ValidationError> {
+        let (_, mut module) = parse_module(input).unwrap();
+        module.resolve_indices().unwrap();
+
+        module.validate()
+    }
+
+    pub fn error_kind(input: &str) -> ValidationErrorKind {
+        validate(input).unwrap_err().kind
+    }
+
+    pub const I32: Type = Type::Numerical(NumericalType::Int32);
+    pub const I64: Type = Type::Numerical(NumericalType::Int64);
+    pub const F64: Type =
+        Type::Numerical(NumericalType::Float64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        testing::{error_kind, validate, I32, I64},
+        Location, ValidationError, ValidationErrorKind,
+    };
+    use crate::ast::{NumericalType, Type};
+
+    #[test]
+    fn rejects_type_mismatches() {
+        let input = "(module
+            (func (param i32))
+            (func (result i32) (i64.add (i64.const 1) (i32.const 2))))";
+
+        assert_eq!(
+            validate(input),
+            Err(ValidationError {
+                location: Location::Function(1),
+                kind: ValidationErrorKind::TypeMismatch {
+                    expected: I64,
+                    found: I32,
+                },
+            })
+        );
+        assert_eq!(
+            validate(input).unwrap_err().to_string(),
+            "function 1: type mismatch: expected i64, found i32"
+        );
+    }
+
+    #[test]
+    fn checks_global_initializers() {
+        assert_eq!(
+            validate("(module (global f32 (i32.const 0)))"),
+            Err(ValidationError {
+                location: Location::Global(0),
+                kind: ValidationErrorKind::TypeMismatch {
+                    expected: Type::Numerical(
+                        NumericalType::Float32
+                    ),
+                    found: I32,
+                },
+            })
+        );
+        assert_eq!(
+            error_kind(
+                "(module (global i32 (i32.add (i32.const 1) (i32.const 2))))"
+            ),
+            ValidationErrorKind::NonConstantInitializer
+        );
+    }
+}
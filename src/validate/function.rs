@@ -0,0 +1,741 @@
+use super::ValidationErrorKind;
+use crate::ast::{
+    Block, ComparisonInstruction, ComparisonOperation,
+    ConversionOperation, Function, IfBlock, Index, Instruction,
+    MemoryInstruction, Module, NumericalType, Opcode, ScopeKind,
+    Type, VariableInstruction,
+};
+
+/// A block being validated.
+struct Frame {
+    /// The types a branch to this block must carry
+    label_types: Vec<Type>,
+    /// The types this block leaves on the stack
+    end_types: Vec<Type>,
+    /// The height of the value stack when this block started
+    height: usize,
+    /// Whether the rest of this block can't be reached, which
+    /// makes the stack accept operands of any type
+    unreachable: bool,
+}
+
+/// Type checks a single function body.
+struct Validator<'a> {
+    module: &'a Module,
+    /// The types of the parameters and then the locals
+    locals: Vec<Type>,
+    /// The operand stack. `None` is a value of unknown type,
+    /// popped from an unreachable stack
+    values: Vec<Option<Type>>,
+    frames: Vec<Frame>,
+}
+
+type Validation<T = ()> = Result<T, ValidationErrorKind>;
+
+/// Type checks the body of `function`, which belongs to
+/// `module`. An open body is still being written, so its
+/// results aren't checked.
+pub(super) fn validate_function(
+    module: &Module,
+    function: &Function,
+    is_open: bool,
+) -> Validation {
+    let locals = function
+        .parameters
+        .iter()
+        .map(|parameter| &parameter.type_)
+        .chain(
+            function
+                .local_variables
+                .iter()
+                .map(|local| &local.type_),
+        )
+        .cloned()
+        .collect();
+
+    let mut validator = Validator {
+        module,
+        locals,
+        values: vec![],
+        frames: vec![],
+    };
+
+    validator.validate_body(function, is_open)
+}
+
+impl<'a> Validator<'a> {
+    fn validate_body(
+        &mut self,
+        function: &Function,
+        is_open: bool,
+    ) -> Validation {
+        self.push_frame(
+            function.results.clone(),
+            function.results.clone(),
+        );
+        self.validate_instructions(&function.body)?;
+        if !is_open {
+            self.pop_frame()?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_instructions(
+        &mut self,
+        instructions: &[Instruction],
+    ) -> Validation {
+        instructions.iter().try_for_each(|instruction| {
+            self.validate_instruction(instruction)
+        })
+    }
+
+    fn validate_instruction(
+        &mut self,
+        instruction: &Instruction,
+    ) -> Validation {
+        // Folded arguments run first
+        self.validate_instructions(&instruction.arguments)?;
+
+        let i32 = || Type::Numerical(NumericalType::Int32);
+
+        match &instruction.opcode {
+            Opcode::Unreachable(_) => self.mark_unreachable(),
+            Opcode::Nop => {}
+            Opcode::Drop => {
+                self.pop_value()?;
+            }
+            Opcode::Select => {
+                self.pop_expected(&i32())?;
+                let second = self.pop_value()?;
+                let first = self.pop_value()?;
+
+                let value = match (first, second) {
+                    (Some(first), Some(second))
+                        if first != second =>
+                    {
+                        return Err(
+                            ValidationErrorKind::OperandsDisagree {
+                                first,
+                                second,
+                            },
+                        )
+                    }
+                    (first, second) => first.or(second),
+                };
+                self.values.push(value);
+            }
+            Opcode::Constant(constant) => self
+                .push(Type::Numerical(constant.value.type_())),
+            Opcode::Arithmetic(operation) => {
+                let type_ =
+                    Type::Numerical(operation.type_.clone());
+
+                self.pop_expected(&type_)?;
+                self.pop_expected(&type_)?;
+                self.push(type_);
+            }
+            Opcode::Comparison(ComparisonOperation {
+                type_,
+                instr,
+            }) => {
+                let type_ = Type::Numerical(type_.clone());
+
+                self.pop_expected(&type_)?;
+                if *instr != ComparisonInstruction::EqualZero {
+                    self.pop_expected(&type_)?;
+                }
+                self.push(i32());
+            }
+            Opcode::Conversion(ConversionOperation {
+                type_,
+                source,
+                ..
+            }) => {
+                self.pop_expected(&Type::Numerical(
+                    source.clone(),
+                ))?;
+                self.push(Type::Numerical(type_.clone()));
+            }
+            Opcode::VariableInstruction(operation) => {
+                let index = numerical(&operation.index)?;
+
+                let type_ = match operation.scope {
+                    ScopeKind::Local => self
+                        .locals
+                        .get(index as usize)
+                        .cloned()
+                        .ok_or(
+                            ValidationErrorKind::UnknownLocal(
+                                index,
+                            ),
+                        )?,
+                    ScopeKind::Global => {
+                        let global = self
+                            .module
+                            .globals
+                            .get(index as usize)
+                            .ok_or(
+                                ValidationErrorKind::UnknownGlobal(
+                                    index,
+                                ),
+                            )?;
+
+                        if operation.instruction
+                            != VariableInstruction::Get
+                            && !global.mutable
+                        {
+                            return Err(
+                                ValidationErrorKind::ImmutableGlobal {
+                                    index,
+                                    identifier: global
+                                        .identifier
+                                        .clone(),
+                                },
+                            );
+                        }
+
+                        global.type_.clone()
+                    }
+                };
+
+                match operation.instruction {
+                    VariableInstruction::Get => self.push(type_),
+                    VariableInstruction::Set => {
+                        self.pop_expected(&type_)?;
+                    }
+                    VariableInstruction::Tee => {
+                        self.pop_expected(&type_)?;
+                        self.push(type_);
+                    }
+                }
+            }
+            Opcode::Call(index) => {
+                let index = numerical(index)?;
+                let signature = self.signature(index).ok_or(
+                    ValidationErrorKind::UnknownFunction(index),
+                )?;
+
+                for parameter in
+                    signature.parameters.iter().rev()
+                {
+                    self.pop_expected(&parameter.type_)?;
+                }
+                for result in &signature.results {
+                    self.push(result.clone());
+                }
+            }
+            Opcode::MemoryAccess(operation) => {
+                if operation.align > operation.width() {
+                    return Err(
+                        ValidationErrorKind::AlignmentTooLarge {
+                            align: operation.align,
+                            natural: operation.width(),
+                        },
+                    );
+                }
+
+                self.memory()?;
+
+                let type_ =
+                    Type::Numerical(operation.type_.clone());
+                match operation.instr {
+                    MemoryInstruction::Store
+                    | MemoryInstruction::NarrowStore {
+                        ..
+                    } => {
+                        self.pop_expected(&type_)?;
+                        self.pop_expected(&i32())?;
+                    }
+                    MemoryInstruction::Load
+                    | MemoryInstruction::SignedLoad { .. }
+                    | MemoryInstruction::UnsignedLoad {
+                        ..
+                    } => {
+                        self.pop_expected(&i32())?;
+                        self.push(type_);
+                    }
+                }
+            }
+            Opcode::MemorySize => {
+                self.memory()?;
+                self.push(i32());
+            }
+            Opcode::MemoryGrow => {
+                self.memory()?;
+                self.pop_expected(&i32())?;
+                self.push(i32());
+            }
+            Opcode::CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                let type_index = numerical(type_index)?;
+                if self
+                    .module
+                    .types
+                    .get(type_index as usize)
+                    .is_none()
+                {
+                    return Err(
+                        ValidationErrorKind::UnknownType(
+                            type_index,
+                        ),
+                    );
+                }
+
+                // Tables can't be declared yet
+                return Err(ValidationErrorKind::UnknownTable(
+                    numerical(table_index)?,
+                ));
+            }
+            Opcode::Br(depth) => {
+                let types = self.label_types(depth)?;
+                self.pop_all_expected(&types)?;
+                self.mark_unreachable();
+            }
+            Opcode::BrIf(depth) => {
+                self.pop_expected(&i32())?;
+
+                let types = self.label_types(depth)?;
+                self.pop_all_expected(&types)?;
+                for type_ in types {
+                    self.push(type_);
+                }
+            }
+            Opcode::Return => {
+                let types = self.frames[0].label_types.clone();
+                self.pop_all_expected(&types)?;
+                self.mark_unreachable();
+            }
+            Opcode::Block(Block {
+                block_type, body, ..
+            }) => {
+                let types: Vec<_> =
+                    block_type.iter().cloned().collect();
+
+                self.push_frame(types.clone(), types);
+                self.validate_instructions(body)?;
+                self.end_frame()?;
+            }
+            Opcode::Loop(Block {
+                block_type, body, ..
+            }) => {
+                // Branching to a loop restarts it, and loops
+                // have no parameters
+                let types = block_type.iter().cloned().collect();
+
+                self.push_frame(vec![], types);
+                self.validate_instructions(body)?;
+                self.end_frame()?;
+            }
+            Opcode::If(IfBlock {
+                block_type,
+                then,
+                else_,
+                ..
+            }) => {
+                self.pop_expected(&i32())?;
+
+                // A missing `else` is the same as an empty one,
+                // which only type checks if there's no result
+                let types: Vec<_> =
+                    block_type.iter().cloned().collect();
+                for arm in [then, else_] {
+                    self.push_frame(
+                        types.clone(),
+                        types.clone(),
+                    );
+                    self.validate_instructions(arm)?;
+                    self.pop_frame()?;
+                }
+                for type_ in types {
+                    self.push(type_);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The function at `index` in the function index space,
+    /// where imports come first.
+    fn signature(&self, index: u32) -> Option<&'a Function> {
+        let imports = &self.module.imports;
+
+        match index.checked_sub(imports.len() as u32) {
+            Some(index) => {
+                self.module.functions.get(index as usize)
+            }
+            None => Some(&imports[index as usize].signature),
+        }
+    }
+
+    /// Fails unless the module has a memory for memory
+    /// instructions to refer to, which is always memory 0.
+    fn memory(&self) -> Validation {
+        if self.module.memories.is_empty() {
+            return Err(ValidationErrorKind::UnknownMemory(0));
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, type_: Type) {
+        self.values.push(Some(type_));
+    }
+
+    fn pop_value(&mut self) -> Validation<Option<Type>> {
+        let frame = self.frames.last().expect("no open block");
+
+        if self.values.len() == frame.height {
+            return if frame.unreachable {
+                Ok(None)
+            } else {
+                Err(ValidationErrorKind::MissingOperand {
+                    expected: None,
+                })
+            };
+        }
+
+        Ok(self.values.pop().flatten())
+    }
+
+    fn pop_expected(&mut self, expected: &Type) -> Validation {
+        match self.pop_value() {
+            Ok(Some(found)) if found != *expected => {
+                Err(ValidationErrorKind::TypeMismatch {
+                    expected: expected.clone(),
+                    found,
+                })
+            }
+            Ok(_) => Ok(()),
+            Err(ValidationErrorKind::MissingOperand {
+                ..
+            }) => Err(ValidationErrorKind::MissingOperand {
+                expected: Some(expected.clone()),
+            }),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn pop_all_expected(
+        &mut self,
+        types: &[Type],
+    ) -> Validation {
+        types
+            .iter()
+            .rev()
+            .try_for_each(|type_| self.pop_expected(type_))
+    }
+
+    fn push_frame(
+        &mut self,
+        label_types: Vec<Type>,
+        end_types: Vec<Type>,
+    ) {
+        self.frames.push(Frame {
+            label_types,
+            end_types,
+            height: self.values.len(),
+            unreachable: false,
+        });
+    }
+
+    /// Closes the innermost block, returning the types it leaves
+    /// on the stack of the enclosing one.
+    fn pop_frame(&mut self) -> Validation<Vec<Type>> {
+        let frame = self.frames.last().expect("no open block");
+        let (end_types, height) =
+            (frame.end_types.clone(), frame.height);
+
+        self.pop_all_expected(&end_types)?;
+        if self.values.len() != height {
+            return Err(ValidationErrorKind::ExtraValues {
+                count: self.values.len() - height,
+            });
+        }
+        self.frames.pop();
+
+        Ok(end_types)
+    }
+
+    /// Closes the innermost block and pushes its results.
+    fn end_frame(&mut self) -> Validation {
+        for type_ in self.pop_frame()? {
+            self.push(type_);
+        }
+
+        Ok(())
+    }
+
+    fn mark_unreachable(&mut self) {
+        let frame =
+            self.frames.last_mut().expect("no open block");
+
+        self.values.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    /// The types a branch to the label at `depth` carries.
+    fn label_types(
+        &self,
+        depth: &Index,
+    ) -> Validation<Vec<Type>> {
+        let depth = numerical(depth)?;
+
+        self.frames
+            .len()
+            .checked_sub(depth as usize + 1)
+            .map(|position| {
+                self.frames[position].label_types.clone()
+            })
+            .ok_or(ValidationErrorKind::UnknownLabel(depth))
+    }
+}
+
+fn numerical(index: &Index) -> Validation<u32> {
+    match index {
+        Index::Numerical(index) => Ok(*index as u32),
+        Index::Identifier(_) => {
+            Err(ValidationErrorKind::UnresolvedIndex)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{NumericalType, Type},
+        validate::{
+            testing::{error_kind, validate, F64, I32, I64},
+            Location, ValidationError, ValidationErrorKind,
+        },
+    };
+
+    #[test]
+    fn accepts_well_typed_modules() {
+        validate(
+            r#"(module
+                (import "env" "log" (func $log (param i32)))
+                (global $count (mut i64) (i64.const 0))
+                (func $add (param $a i32) (param $b i32) (result i32)
+                    (i32.add (local.get $a) (local.get $b)))
+                (func $main (result i32) (local $x i32)
+                    (call $log (call $add (i32.const 1) (i32.const 2)))
+                    (global.set $count (i64.extend_i32_u (local.tee $x (i32.const 3))))
+                    (block $done (result i32)
+                        (br_if $done (i32.const 4) (i32.eqz (local.get $x)))
+                        (drop)
+                        (if (result i32) (local.get $x)
+                            (then (i32.const 5))
+                            (else (return (i32.const 6))))))
+                (func (unreachable) (i32.add) (drop))
+                (func (loop $again (br $again))))"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn checks_folded_operands() {
+        validate(
+            "(module (func (result i32)
+                (i32.lt_s
+                    (i32.add (i32.const 1) (i32.const 2))
+                    (i32.const 4))))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            error_kind(
+                "(module (func (result i32)
+                    (i32.add (i32.const 1) (f64.const 2.0))))"
+            ),
+            ValidationErrorKind::TypeMismatch {
+                expected: I32,
+                found: F64,
+            }
+        );
+        assert_eq!(
+            error_kind(
+                "(module (func (result i32)
+                    (f64.eq (f64.const 1) (i32.const 2))))"
+            ),
+            ValidationErrorKind::TypeMismatch {
+                expected: F64,
+                found: I32,
+            }
+        );
+    }
+
+    #[test]
+    fn infers_variable_types_from_declarations() {
+        validate(
+            "(module
+                (global $g f64 (f64.const 1))
+                (func (param $a i32) (result i32) (local $b i32)
+                    (i32.add (local.get $a) (local.get $b)))
+                (func (result f64)
+                    (f64.mul (global.get $g) (global.get $g))))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            error_kind(
+                "(module (func (param $a i32) (result i32) (local $b f32)
+                    (i32.add (local.get $a) (local.get $b))))"
+            ),
+            ValidationErrorKind::TypeMismatch {
+                expected: I32,
+                found: Type::Numerical(NumericalType::Float32),
+            }
+        );
+        assert_eq!(
+            error_kind(
+                "(module (global $g (mut i64) (i64.const 0))
+                    (func (global.set $g (i32.const 1))))"
+            ),
+            ValidationErrorKind::TypeMismatch {
+                expected: I64,
+                found: I32,
+            }
+        );
+    }
+
+    #[test]
+    fn checks_function_results() {
+        assert_eq!(
+            error_kind("(module (func (result i32)))"),
+            ValidationErrorKind::MissingOperand {
+                expected: Some(I32),
+            }
+        );
+        assert_eq!(
+            error_kind("(module (func (i32.const 1)))"),
+            ValidationErrorKind::ExtraValues { count: 1 }
+        );
+        assert_eq!(
+            error_kind(
+                "(module (func (result i32) (if (i32.const 1) (then (i32.const 2)))))"
+            ),
+            ValidationErrorKind::ExtraValues { count: 1 }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_indices() {
+        assert_eq!(
+            error_kind("(module (func (br 1)))"),
+            ValidationErrorKind::UnknownLabel(1)
+        );
+        assert_eq!(
+            error_kind("(module (func (drop (local.get 0))))"),
+            ValidationErrorKind::UnknownLocal(0)
+        );
+        assert_eq!(
+            error_kind("(module (func (call 3)))"),
+            ValidationErrorKind::UnknownFunction(3)
+        );
+        assert_eq!(
+            error_kind(
+                "(module (global i32 (i32.const 0)) (func (global.set 0 (i32.const 1))))"
+            ),
+            ValidationErrorKind::ImmutableGlobal {
+                index: 0,
+                identifier: None,
+            }
+        );
+        assert_eq!(
+            error_kind(
+                "(module (func (call_indirect (type 0) (i32.const 0))))"
+            ),
+            ValidationErrorKind::UnknownType(0)
+        );
+        assert_eq!(
+            error_kind(
+                "(module (type (func)) (func (call_indirect (type 0) (i32.const 0))))"
+            ),
+            ValidationErrorKind::UnknownTable(0)
+        );
+    }
+
+    #[test]
+    fn rejects_memory_accesses() {
+        assert_eq!(
+            error_kind(
+                "(module (func (drop (i32.load8_u align=2 (i32.const 0)))))"
+            ),
+            ValidationErrorKind::AlignmentTooLarge {
+                align: 2,
+                natural: 1,
+            }
+        );
+        assert_eq!(
+            error_kind(
+                "(module (func (drop (i32.load (i32.const 0)))))"
+            ),
+            ValidationErrorKind::UnknownMemory(0)
+        );
+        assert_eq!(
+            error_kind("(module (func (drop (memory.size))))"),
+            ValidationErrorKind::UnknownMemory(0)
+        );
+        assert_eq!(
+            error_kind(
+                "(module (memory 1) (func (f64.store (i32.const 0) (f32.const 1))))"
+            ),
+            ValidationErrorKind::TypeMismatch {
+                expected: F64,
+                found: Type::Numerical(NumericalType::Float32),
+            }
+        );
+    }
+
+    #[test]
+    fn accepts_memory_instructions() {
+        validate(
+            "(module
+                (memory 1 2)
+                (func (param $p i32) (result i64)
+                    (i64.store32 (local.get $p) (i64.const 7))
+                    (drop (memory.grow (i32.const 1)))
+                    (i64.add
+                        (i64.load (memory.size))
+                        (i64.load8_s offset=1 (local.get $p)))))",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn only_sets_mutable_globals() {
+        validate(
+            "(module
+                (global $counter (mut i32) (i32.const 0))
+                (func (global.set $counter (i32.const 1))))",
+        )
+        .unwrap();
+
+        let error = validate(
+            "(module
+                (global $counter (mut i32) (i32.const 0))
+                (global $limit i32 (i32.const 10))
+                (func (drop (global.get $limit)))
+                (func (global.set $limit (i32.const 1))))",
+        );
+
+        assert_eq!(
+            error,
+            Err(ValidationError {
+                location: Location::Function(1),
+                kind: ValidationErrorKind::ImmutableGlobal {
+                    index: 1,
+                    identifier: Some("limit".into()),
+                },
+            })
+        );
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "function 1: global $limit is immutable"
+        );
+    }
+}
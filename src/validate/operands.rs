@@ -0,0 +1,205 @@
+use std::fmt;
+
+use crate::ast::{
+    ComparisonOperation, Index, Instruction, MemoryInstruction,
+    NumericalType, Opcode, ScopeKind, Type, VariableInstruction,
+};
+
+/// Returned by [`check_operands`] when a folded argument of an
+/// arithmetic or comparison instruction has the wrong type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeError {
+    /// The instruction that was fed the wrong operand
+    pub instruction: Instruction,
+    pub expected: Type,
+    pub found: Type,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` expects operands of type {}, found {}",
+            self.instruction, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/// Checks that the folded arguments of every arithmetic and
+/// comparison instruction within `instruction` have the type
+/// the instruction operates on, as in
+/// `(i32.add (i32.const 1) (i32.const 2))`.
+///
+/// `locals` holds the types of the parameters and then the
+/// locals of the enclosing function, to look up the type of
+/// `local.get` and `local.tee`.
+///
+/// Unlike [`Module::validate`](crate::ast::Module::validate),
+/// this doesn't simulate the operand stack: only folded
+/// arguments whose type can be told on their own are checked,
+/// so operands pushed by earlier instructions, calls, globals
+/// and identifiers that weren't resolved are all accepted.
+///
+/// Instructions are checked in the order
+/// [`Instruction::walk`] visits them, so an outer instruction
+/// is reported before the arguments within it.
+pub fn check_operands(
+    instruction: &Instruction,
+    locals: &[Type],
+) -> Result<(), TypeError> {
+    let mut result = Ok(());
+
+    instruction.walk(&mut |instruction| {
+        if result.is_ok() {
+            result = check_arguments(instruction, locals);
+        }
+    });
+
+    result
+}
+
+/// Checks the direct arguments of `instruction`.
+fn check_arguments(
+    instruction: &Instruction,
+    locals: &[Type],
+) -> Result<(), TypeError> {
+    let expected = match &instruction.opcode {
+        Opcode::Arithmetic(operation) => &operation.type_,
+        Opcode::Comparison(ComparisonOperation {
+            type_,
+            ..
+        }) => type_,
+        _ => return Ok(()),
+    };
+    let expected = Type::Numerical(expected.clone());
+
+    for argument in &instruction.arguments {
+        match result_type(argument, locals) {
+            Some(found) if found != expected => {
+                return Err(TypeError {
+                    instruction: instruction.clone(),
+                    expected,
+                    found,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The type of the single value `instruction` leaves on the
+/// stack, if it can be told without knowing the module.
+fn result_type(
+    instruction: &Instruction,
+    locals: &[Type],
+) -> Option<Type> {
+    let type_ = match &instruction.opcode {
+        Opcode::Constant(constant) => constant.value.type_(),
+        Opcode::Arithmetic(operation) => operation.type_.clone(),
+        Opcode::Comparison(_) => NumericalType::Int32,
+        Opcode::Conversion(operation) => operation.type_.clone(),
+        Opcode::MemoryAccess(operation) => {
+            match operation.instr {
+                MemoryInstruction::Store
+                | MemoryInstruction::NarrowStore { .. } => {
+                    return None
+                }
+                _ => operation.type_.clone(),
+            }
+        }
+        Opcode::VariableInstruction(operation) => {
+            let (ScopeKind::Local, Index::Numerical(index)) =
+                (operation.scope, &operation.index)
+            else {
+                return None;
+            };
+            if operation.instruction == VariableInstruction::Set
+            {
+                return None;
+            }
+
+            return locals
+                .get(usize::try_from(*index).ok()?)
+                .cloned();
+        }
+        _ => return None,
+    };
+
+    Some(Type::Numerical(type_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_operands, TypeError};
+    use crate::{
+        ast::{Instruction, Type},
+        parser::parse_instruction,
+        validate::testing::{F64, I32, I64},
+    };
+
+    fn check(
+        input: &str,
+        locals: &[Type],
+    ) -> Result<(), TypeError> {
+        let (_, instruction) = parse_instruction(input).unwrap();
+
+        check_operands(&instruction, locals)
+    }
+
+    fn instruction(input: &str) -> Instruction {
+        parse_instruction(input).unwrap().1
+    }
+
+    #[test]
+    fn accepts_well_typed_trees() {
+        check(
+            "(i32.lt_s
+                (i32.add (i32.const 1) (local.get 0))
+                (i32.wrap_i64 (local.get 1)))",
+            &[I32, I64],
+        )
+        .unwrap();
+
+        // Nothing is known about these operands
+        check("(i32.add (call 0) (global.get 0))", &[]).unwrap();
+    }
+
+    #[test]
+    fn reports_mismatched_operands() {
+        let input = "(i32.add (i32.const 1) (f64.const 2.0))";
+
+        assert_eq!(
+            check(input, &[]),
+            Err(TypeError {
+                instruction: instruction(input),
+                expected: I32,
+                found: F64,
+            })
+        );
+        assert_eq!(
+            check(input, &[]).unwrap_err().to_string(),
+            "`(i32.add (i32.const 1) (f64.const 2.0))` expects operands of type i32, found f64"
+        );
+    }
+
+    #[test]
+    fn reports_nested_mismatches() {
+        assert_eq!(
+            check(
+                "(block (drop (f64.eq (f64.const 1) (local.get 0))))",
+                &[I64],
+            ),
+            Err(TypeError {
+                instruction: instruction(
+                    "(f64.eq (f64.const 1) (local.get 0))"
+                ),
+                expected: F64,
+                found: I64,
+            })
+        );
+    }
+}
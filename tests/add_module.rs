@@ -0,0 +1,55 @@
+use water::{
+    ast::{
+        ArithmeticInstruction, ArithmeticOperation, Index,
+        Instruction, NumericalType, Opcode, ScopeKind,
+        VariableInstruction, VariableOperation,
+    },
+    parser::parse_module,
+};
+
+const ADD_MODULE: &str = r#"
+(module
+  (import "console" "log" (func $log (param i32)))
+  (func (export "add") (param $a i32) (param $b i32) (result i32)
+    (i32.add
+      (local.get $a)
+      (local.get $b))))
+"#;
+
+fn local_get(identifier: &str) -> Instruction {
+    Instruction {
+        opcode: Opcode::VariableInstruction(VariableOperation {
+            scope: ScopeKind::Local,
+            instruction: VariableInstruction::Get,
+            index: Index::Identifier(identifier.into()),
+        }),
+        arguments: vec![],
+    }
+}
+
+#[test]
+fn parses_the_add_module() {
+    let (rest, module) = parse_module(ADD_MODULE).unwrap();
+    assert!(rest.trim().is_empty());
+
+    assert_eq!(module.imports.len(), 1);
+    let import = &module.imports[0];
+    assert_eq!(import.namespace.as_str(), "console");
+    assert_eq!(import.fn_name.as_str(), "log");
+    assert_eq!(import.signature.identifier, Some("log".into()));
+
+    assert_eq!(module.functions.len(), 1);
+    let add = &module.functions[0];
+    assert_eq!(add.exports, vec!["add".into()]);
+    assert_eq!(add.parameters.len(), 2);
+    assert_eq!(add.results.len(), 1);
+
+    let expected = Instruction {
+        opcode: Opcode::Arithmetic(ArithmeticOperation {
+            type_: NumericalType::Int32,
+            instr: ArithmeticInstruction::Addition,
+        }),
+        arguments: vec![local_get("a"), local_get("b")],
+    };
+    assert_eq!(add.body, vec![expected]);
+}
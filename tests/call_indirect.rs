@@ -0,0 +1,66 @@
+use water::{
+    ast::{Index, Opcode, Program},
+    emitter::Emitter,
+    parser::parse_module,
+};
+
+const DISPATCH_MODULE: &str = r#"
+(module
+  (type $unop (func (param i32) (result i32)))
+  (type $binop (func (param i32) (param i32) (result i32)))
+  (func (param $a i32) (param $b i32) (result i32)
+    (call_indirect (type $binop)
+      (local.get $a)
+      (local.get $b)
+      (i32.const 0))))
+"#;
+
+#[test]
+fn resolves_and_emits_the_type_of_call_indirect() {
+    let (rest, mut module) =
+        parse_module(DISPATCH_MODULE).unwrap();
+    assert!(rest.trim().is_empty());
+
+    assert_eq!(module.types.len(), 2);
+    assert_eq!(module.types[1].identifier, Some("binop".into()));
+    assert_eq!(module.types[1].parameters.len(), 2);
+
+    module.resolve_indices().unwrap();
+
+    let call = &module.functions[0].body[0];
+    assert_eq!(
+        call.opcode,
+        Opcode::CallIndirect {
+            type_index: Index::Numerical(1),
+            table_index: Index::Numerical(0),
+        }
+    );
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter
+        .emit_program(Program {
+            modules: vec![module],
+        })
+        .unwrap();
+    let bytes = emitter.finish().unwrap();
+
+    // No table can be declared yet, so the output can't be
+    // validated: check every section instead
+    assert_eq!(
+        &bytes[8..],
+        &[
+            // Type section: `[i32] -> [i32]` and
+            // `[i32 i32] -> [i32]`
+            0x01, 0x0c, 0x02, 0x60, 0x01, 0x7f, 0x01, 0x7f, 0x60,
+            0x02, 0x7f, 0x7f, 0x01, 0x7f,
+            // Function section: one function of type `$binop`
+            0x03, 0x02, 0x01, 0x01,
+            // Code section: one body of 11 bytes, no locals
+            0x0a, 0x0d, 0x01, 0x0b, 0x00,
+            // local.get $a, local.get $b, i32.const 0
+            0x20, 0x00, 0x20, 0x01, 0x41, 0x00,
+            // call_indirect 1 0, end
+            0x11, 0x01, 0x00, 0x0b,
+        ]
+    );
+}